@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    backend::{Health, InferenceBackend, Prediction},
+    cog::Connector,
+    types::Result,
+};
+
+/// Model served by a Cog container.
+pub struct CogBackend {
+    connector: Connector,
+}
+
+impl CogBackend {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self { connector: Connector::new(url)? })
+    }
+}
+
+#[async_trait]
+impl InferenceBackend for CogBackend {
+    async fn health(&self) -> Result<Health> {
+        Ok(self.connector.health_check().await?.status)
+    }
+
+    async fn schema(&self) -> Result<Option<Value>> {
+        let schema = self.connector.openapi_schema().await?;
+        Ok(Some(serde_json::to_value(schema)?))
+    }
+
+    async fn predict(&self, prediction_id: &str, input: Value) -> Result<Prediction> {
+        let response = self.connector.predict_with_id::<Value, Value>(prediction_id, input).await?;
+        Ok(response.into())
+    }
+
+    async fn cancel(&self, prediction_id: &str) -> Result<()> {
+        self.connector.cancel(prediction_id).await
+    }
+}
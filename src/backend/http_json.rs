@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, Response, Url,
+};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::{
+    backend::{template, Health, InferenceBackend, Prediction, Status},
+    types::{HttpJsonSettings, Result},
+    utils::now_rfc3339,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid header {0}")]
+    InvalidHeader(String),
+    #[error("Backend does not support cancelling predictions")]
+    CancelNotSupported,
+}
+
+/// Model served over a JSON HTTP API. Bodies are mapped with the templates of the settings.
+pub struct HttpJsonBackend {
+    url: Url,
+    http: Client,
+    settings: HttpJsonSettings,
+}
+
+impl HttpJsonBackend {
+    pub fn new(url: &str, settings: HttpJsonSettings) -> Result<Self> {
+        let url = Url::parse(url)?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &settings.headers {
+            let name =
+                HeaderName::try_from(name).map_err(|_| Error::InvalidHeader(name.clone()))?;
+            let value =
+                HeaderValue::try_from(value).map_err(|_| Error::InvalidHeader(name.to_string()))?;
+            headers.insert(name, value);
+        }
+        let http = Client::builder().default_headers(headers).build()?;
+        Ok(Self { url, http, settings })
+    }
+
+    fn render(template: &Option<String>, response: &Value) -> Option<Value> {
+        let rendered = template::render(&Value::from(template.as_deref()?), response);
+        match rendered {
+            Value::Null => None,
+            Value::String(s) if s.is_empty() => None,
+            value => Some(value),
+        }
+    }
+}
+
+#[async_trait]
+impl InferenceBackend for HttpJsonBackend {
+    async fn health(&self) -> Result<Health> {
+        let Some(path) = &self.settings.health_path else {
+            return Ok(Health::Ready);
+        };
+        self.http
+            .get(self.url.join(path)?)
+            .send()
+            .await
+            .and_then(Response::error_for_status)?;
+        Ok(Health::Ready)
+    }
+
+    async fn schema(&self) -> Result<Option<Value>> {
+        let Some(path) = &self.settings.schema_path else {
+            return Ok(None);
+        };
+        let response = self
+            .http
+            .get(self.url.join(path)?)
+            .send()
+            .await
+            .and_then(Response::error_for_status)?;
+        Ok(Some(response.json().await?))
+    }
+
+    async fn predict(&self, prediction_id: &str, input: Value) -> Result<Prediction> {
+        let body = match &self.settings.request {
+            Some(request) => {
+                template::render(request, &json!({ "input": input, "id": prediction_id }))
+            },
+            None => input,
+        };
+        let started_at = now_rfc3339();
+        let response = self
+            .http
+            .post(self.url.join(&self.settings.predict_path)?)
+            .json(&body)
+            .send()
+            .await
+            .and_then(Response::error_for_status)?
+            .json::<Value>()
+            .await?;
+        let completed_at = now_rfc3339();
+
+        let context = json!({ "response": response });
+        let error = Self::render(&self.settings.error, &context).map(|error| match error {
+            Value::String(error) => error,
+            error => error.to_string(),
+        });
+        let id = Self::render(&self.settings.id, &context).map_or_else(
+            || prediction_id.to_owned(),
+            |id| match id {
+                Value::String(id) => id,
+                id => id.to_string(),
+            },
+        );
+        let output = match (&error, &self.settings.output) {
+            (Some(_), _) => None,
+            (None, Some(output)) => Some(template::render(output, &context)),
+            (None, None) => context.get("response").cloned(),
+        };
+        Ok(Prediction {
+            id: Some(id),
+            status: if error.is_some() { Status::Failed } else { Status::Succeeded },
+            output,
+            error,
+            started_at: Some(started_at),
+            completed_at: Some(completed_at),
+            metrics: None,
+            usage: None,
+            logs: String::new(),
+        })
+    }
+
+    async fn cancel(&self, prediction_id: &str) -> Result<()> {
+        let path = self.settings.cancel_path.as_ref().ok_or(Error::CancelNotSupported)?;
+        let url = self.url.join(&path.replace("{id}", prediction_id))?;
+        self.http.post(url).send().await.and_then(Response::error_for_status)?;
+        Ok(())
+    }
+}
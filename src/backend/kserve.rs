@@ -0,0 +1,310 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::{
+    backend::{Health, InferenceBackend, Prediction, Status},
+    types::{KServeSettings, Result},
+    utils::now_rfc3339,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Input must be an object of tensors by input name")]
+    InvalidInput,
+    #[error("Input {0} is missing")]
+    MissingInput(String),
+    #[error("Input {0} is not a tensor of consistent shape")]
+    InvalidTensor(String),
+    #[error("Input {0} does not fit shape {1:?}")]
+    ShapeMismatch(String, Vec<i64>),
+    #[error("Input {0} is not an input of the model")]
+    UnexpectedInput(String),
+    #[error("The Open Inference Protocol does not support cancelling requests")]
+    CancelNotSupported,
+}
+
+/// Description of a tensor in the model metadata.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TensorMetadata {
+    pub name: String,
+    pub datatype: String,
+    pub shape: Vec<i64>,
+}
+
+/// Model metadata served at `/v2/models/{name}`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ModelMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub platform: Option<String>,
+    pub inputs: Vec<TensorMetadata>,
+    pub outputs: Vec<TensorMetadata>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Tensor {
+    name: String,
+    shape: Vec<i64>,
+    datatype: String,
+    data: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct InferResponse {
+    id: Option<String>,
+    outputs: Vec<Tensor>,
+}
+
+/// Model served over the Open Inference Protocol v2, e.g. by Triton or KServe. Consumers send an
+/// object of tensors by input name, given as nested arrays, and receive the outputs the same way.
+pub struct KServeBackend {
+    url: Url,
+    http: Client,
+    settings: KServeSettings,
+    /// Metadata of the model, until the model fails or stops being ready, e.g. while the server
+    /// reloads it.
+    metadata: Mutex<Option<Arc<ModelMetadata>>>,
+}
+
+impl KServeBackend {
+    pub fn new(url: &str, settings: KServeSettings) -> Result<Self> {
+        let url = Url::parse(url)?;
+        Ok(Self { url, http: Client::new(), settings, metadata: Mutex::default() })
+    }
+
+    /// Path of the model endpoints relative to the server url.
+    fn model_path(&self) -> String {
+        match &self.settings.version {
+            Some(version) => format!("v2/models/{}/versions/{version}", self.settings.model),
+            None => format!("v2/models/{}", self.settings.model),
+        }
+    }
+
+    pub async fn metadata(&self) -> Result<Arc<ModelMetadata>> {
+        if let Some(metadata) = self.cached_metadata().clone() {
+            return Ok(metadata);
+        }
+        let url = self.url.join(&self.model_path())?;
+        let response = self.http.get(url).send().await.and_then(Response::error_for_status)?;
+        let metadata = Arc::new(response.json::<ModelMetadata>().await?);
+        *self.cached_metadata() = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    fn cached_metadata(&self) -> std::sync::MutexGuard<'_, Option<Arc<ModelMetadata>>> {
+        self.metadata.lock().expect("metadata lock should not be poisoned")
+    }
+
+    /// Forget the metadata, so it is fetched again before the next prediction.
+    fn invalidate(&self) {
+        self.cached_metadata().take();
+    }
+
+    fn tensors(&self, input: Value, metadata: &ModelMetadata) -> Result<Vec<Tensor>> {
+        let Value::Object(mut input) = input else {
+            return Err(Error::InvalidInput.into());
+        };
+        let tensors = metadata
+            .inputs
+            .iter()
+            .map(|meta| {
+                let value = input
+                    .remove(&meta.name)
+                    .ok_or_else(|| Error::MissingInput(meta.name.clone()))?;
+                let mut shape = Vec::new();
+                let mut data = Vec::new();
+                flatten(&value, 0, &mut shape, &mut data)
+                    .ok_or_else(|| Error::InvalidTensor(meta.name.clone()))?;
+                let shape = fit_shape(shape, &meta.shape, data.len())
+                    .ok_or_else(|| Error::ShapeMismatch(meta.name.clone(), meta.shape.clone()))?;
+                Ok(Tensor { name: meta.name.clone(), shape, datatype: meta.datatype.clone(), data })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(name) = input.keys().next() {
+            return Err(Error::UnexpectedInput(name.clone()).into());
+        }
+        Ok(tensors)
+    }
+}
+
+#[async_trait]
+impl InferenceBackend for KServeBackend {
+    async fn health(&self) -> Result<Health> {
+        let server = self.http.get(self.url.join("v2/health/ready")?).send().await?;
+        if !server.status().is_success() {
+            return Ok(Health::Starting);
+        }
+        let url = self.url.join(&format!("{}/ready", self.model_path()))?;
+        let health = match self.http.get(url).send().await?.status() {
+            status if status.is_success() => Health::Ready,
+            StatusCode::NOT_FOUND => Health::SetupFailed,
+            _ => Health::Starting,
+        };
+        if health != Health::Ready {
+            self.invalidate();
+        }
+        Ok(health)
+    }
+
+    async fn schema(&self) -> Result<Option<Value>> {
+        let metadata = self.metadata().await?;
+        Ok(Some(input_schema(&metadata)))
+    }
+
+    async fn predict(&self, _prediction_id: &str, input: Value) -> Result<Prediction> {
+        let metadata = self.metadata().await?;
+        let inputs = self.tensors(input, &metadata)?;
+        let url = self.url.join(&format!("{}/infer", self.model_path()))?;
+        let started_at = now_rfc3339();
+        let response = match self.http.post(url).json(&json!({ "inputs": inputs })).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                self.invalidate();
+                return Err(e.into());
+            },
+        };
+        let completed_at = now_rfc3339();
+
+        if !response.status().is_success() {
+            // The model might have been reloaded with other inputs
+            self.invalidate();
+            let status = response.status();
+            let body = response.json::<Value>().await.unwrap_or_default();
+            let error = body["error"].as_str().map_or_else(|| status.to_string(), str::to_owned);
+            return Ok(Prediction {
+                id: None,
+                status: Status::Failed,
+                output: None,
+                error: Some(error),
+                started_at: Some(started_at),
+                completed_at: Some(completed_at),
+                metrics: None,
+                usage: None,
+                logs: String::new(),
+            });
+        }
+
+        let response = response.json::<InferResponse>().await?;
+        let output: Map<String, Value> = response
+            .outputs
+            .into_iter()
+            .map(|tensor| {
+                let dims: Vec<usize> = tensor.shape.iter().map(|dim| *dim as usize).collect();
+                (tensor.name, reshape(&tensor.data, &dims))
+            })
+            .collect();
+        Ok(Prediction {
+            id: response.id,
+            status: Status::Succeeded,
+            output: Some(Value::Object(output)),
+            error: None,
+            started_at: Some(started_at),
+            completed_at: Some(completed_at),
+            metrics: None,
+            usage: None,
+            logs: String::new(),
+        })
+    }
+
+    async fn cancel(&self, _prediction_id: &str) -> Result<()> {
+        Err(Error::CancelNotSupported.into())
+    }
+}
+
+/// JSON schema of the input object described by the model metadata.
+pub fn input_schema(metadata: &ModelMetadata) -> Value {
+    let properties: Map<String, Value> = metadata
+        .inputs
+        .iter()
+        .map(|input| {
+            let mut schema = json!({ "type": json_type(&input.datatype) });
+            for dim in input.shape.iter().rev() {
+                schema = json!({ "type": "array", "items": schema });
+                if *dim >= 0 {
+                    schema["minItems"] = json!(dim);
+                    schema["maxItems"] = json!(dim);
+                }
+            }
+            schema["description"] =
+                json!(format!("{} tensor of shape {:?}", input.datatype, input.shape));
+            (input.name.clone(), schema)
+        })
+        .collect();
+    let required: Vec<_> = metadata.inputs.iter().map(|input| input.name.clone()).collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn json_type(datatype: &str) -> &'static str {
+    match datatype {
+        "BOOL" => "boolean",
+        "BYTES" => "string",
+        dt if dt.starts_with("FP") || dt == "BF16" => "number",
+        _ => "integer",
+    }
+}
+
+/// Flatten nested arrays in row-major order and record their shape. Returns `None` if the arrays
+/// are ragged. Scalars are tensors of shape `[1]`.
+fn flatten(value: &Value, depth: usize, shape: &mut Vec<i64>, data: &mut Vec<Value>) -> Option<()> {
+    match value {
+        Value::Array(items) => {
+            match shape.get(depth) {
+                Some(dim) if *dim != items.len() as i64 => return None,
+                Some(_) => {},
+                None => shape.push(items.len() as i64),
+            }
+            items.iter().try_for_each(|item| flatten(item, depth + 1, shape, data))
+        },
+        scalar => {
+            if depth == 0 {
+                shape.push(1);
+            } else if shape.len() != depth {
+                return None;
+            }
+            data.push(scalar.clone());
+            Some(())
+        },
+    }
+}
+
+/// Shape of a tensor of `len` elements, given with `shape`, for an input declared with
+/// `declared`. Flat data is reshaped to the declared shape, inferring an unknown dimension.
+/// Returns `None` if the tensor does not fit the declared shape.
+fn fit_shape(shape: Vec<i64>, declared: &[i64], len: usize) -> Option<Vec<i64>> {
+    if shape.len() == declared.len() {
+        let fits = shape
+            .iter()
+            .zip(declared)
+            .all(|(dim, declared)| *declared < 0 || dim == declared);
+        return fits.then_some(shape);
+    }
+    if shape.len() != 1 {
+        return None;
+    }
+    let len = len as i64;
+    let known: i64 = declared.iter().filter(|dim| **dim >= 0).product();
+    match declared.iter().filter(|dim| **dim < 0).count() {
+        0 if known == len => Some(declared.to_vec()),
+        1 if known > 0 && len % known == 0 => {
+            Some(declared.iter().map(|dim| if *dim < 0 { len / known } else { *dim }).collect())
+        },
+        _ => None,
+    }
+}
+
+/// Nest flat row-major data into arrays of the given shape.
+fn reshape(data: &[Value], shape: &[usize]) -> Value {
+    match shape.split_first() {
+        None => data.first().cloned().unwrap_or(Value::Null),
+        Some((_, [])) => Value::Array(data.to_vec()),
+        Some((_, rest)) => {
+            let chunk = rest.iter().product::<usize>().max(1);
+            Value::Array(data.chunks(chunk).map(|chunk| reshape(chunk, rest)).collect())
+        },
+    }
+}
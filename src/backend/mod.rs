@@ -0,0 +1,87 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use thiserror::Error;
+
+pub use crate::cog::{Health, Status};
+use crate::{
+    cog::PredictionResponse,
+    types::{Backend, Result, TokenUsage},
+};
+
+pub mod cog;
+pub mod http_json;
+pub mod kserve;
+pub mod openai;
+#[cfg(unix)]
+pub mod subprocess;
+pub mod template;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("The subprocess backend is only supported on Unix")]
+    SubprocessUnsupported,
+}
+
+/// A service making predictions with a model, e.g. a Cog container.
+#[async_trait]
+pub trait InferenceBackend {
+    async fn health(&self) -> Result<Health>;
+    /// Schema of the model's API, if the backend exposes one.
+    async fn schema(&self) -> Result<Option<Value>>;
+    /// Make a prediction. The ID is assigned by the caller, so the prediction can be cancelled
+    /// while it runs.
+    async fn predict(&self, prediction_id: &str, input: Value) -> Result<Prediction>;
+    /// Cancel a running prediction.
+    async fn cancel(&self, prediction_id: &str) -> Result<()>;
+}
+
+/// Outcome of a prediction, whatever the backend.
+#[derive(Clone, Debug)]
+pub struct Prediction {
+    pub id: Option<String>,
+    pub status: Status,
+    pub output: Option<Value>,
+    pub error: Option<String>,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub metrics: Option<HashMap<String, Value>>,
+    pub usage: Option<TokenUsage>,
+    /// Logs of the prediction, if the backend captures them.
+    pub logs: String,
+}
+
+impl From<PredictionResponse> for Prediction {
+    fn from(response: PredictionResponse) -> Self {
+        Self {
+            id: response.id,
+            status: response.status,
+            output: response.output,
+            error: response.error,
+            started_at: response.started_at,
+            completed_at: response.completed_at,
+            metrics: response.metrics,
+            usage: None,
+            logs: response.logs,
+        }
+    }
+}
+
+/// Connect to a replica served by the given backend.
+pub fn connect(url: &str, backend: &Backend) -> Result<Arc<dyn InferenceBackend + Send + Sync>> {
+    Ok(match backend {
+        Backend::Cog => Arc::new(cog::CogBackend::new(url)?),
+        Backend::HttpJson(settings) => {
+            Arc::new(http_json::HttpJsonBackend::new(url, settings.as_ref().clone())?)
+        },
+        Backend::OpenAi(settings) => Arc::new(openai::OpenAiBackend::new(url, settings.clone())?),
+        Backend::KServe(settings) => Arc::new(kserve::KServeBackend::new(url, settings.clone())?),
+        #[cfg(unix)]
+        Backend::Subprocess(settings) => {
+            Arc::new(subprocess::SubprocessBackend::new(settings.clone())?)
+        },
+        #[cfg(not(unix))]
+        Backend::Subprocess(_) => return Err(Error::SubprocessUnsupported.into()),
+    })
+}
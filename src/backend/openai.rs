@@ -0,0 +1,268 @@
+use std::{collections::HashMap, env};
+
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response, Url};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::{
+    backend::{Health, InferenceBackend, Prediction, Status},
+    types::{OpenAiEndpoint, OpenAiSettings, Result, TokenUsage},
+    utils::now_rfc3339,
+};
+
+/// Request parameters a consumer may set besides the prompt.
+const PARAMS: [&str; 8] = [
+    "max_tokens",
+    "temperature",
+    "top_p",
+    "stop",
+    "seed",
+    "n",
+    "presence_penalty",
+    "frequency_penalty",
+];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("API key variable {0} is not set")]
+    MissingApiKey(String),
+    #[error("Input must be a string or an object with `messages` or `prompt`")]
+    InvalidInput,
+    #[error("OpenAI-compatible APIs do not support cancelling requests")]
+    CancelNotSupported,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Completion {
+    id: Option<String>,
+    choices: Vec<Value>,
+    usage: Option<TokenUsage>,
+}
+
+/// LLM served over an OpenAI-compatible API. Consumers send either a prompt, as a string or in
+/// the `prompt` field, or chat `messages`, along with the usual sampling parameters.
+pub struct OpenAiBackend {
+    url: Url,
+    http: Client,
+    api_key: Option<String>,
+    settings: OpenAiSettings,
+}
+
+impl OpenAiBackend {
+    pub fn new(url: &str, settings: OpenAiSettings) -> Result<Self> {
+        let url = Url::parse(url)?;
+        let api_key = match &settings.api_key_env {
+            Some(var) => Some(env::var(var).map_err(|_| Error::MissingApiKey(var.clone()))?),
+            None => None,
+        };
+        Ok(Self { url, http: Client::new(), api_key, settings })
+    }
+
+    fn request(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// Entry of the model in `/v1/models`, if it is served.
+    async fn served_model(&self) -> Result<Option<Value>> {
+        let url = self.url.join("v1/models")?;
+        let models = self
+            .request(self.http.get(url))
+            .send()
+            .await
+            .and_then(Response::error_for_status)?
+            .json::<ModelList>()
+            .await?;
+        Ok(models
+            .data
+            .into_iter()
+            .find(|model| model["id"] == self.settings.model.as_str()))
+    }
+
+    /// Map the consumer's input to the body of a completion request.
+    fn body(&self, input: Value) -> Result<Value> {
+        let input = match input {
+            Value::String(prompt) => Map::from_iter([("prompt".to_owned(), Value::String(prompt))]),
+            Value::Object(input) => input,
+            _ => return Err(Error::InvalidInput.into()),
+        };
+
+        let mut body: Map<String, Value> = input
+            .iter()
+            .filter(|(key, _)| PARAMS.contains(&key.as_str()))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        body.insert("model".to_owned(), json!(self.settings.model));
+        if let Some(limit) = self.settings.max_tokens {
+            let requested = body.get("max_tokens").and_then(Value::as_u64).unwrap_or(u64::MAX);
+            body.insert("max_tokens".to_owned(), json!(requested.min(limit.into())));
+        }
+
+        match (self.settings.endpoint, input.get("messages"), input.get("prompt")) {
+            (OpenAiEndpoint::Chat, Some(Value::Array(messages)), _) => {
+                let mut messages = messages.clone();
+                let has_system = messages.iter().any(|message| message["role"] == "system");
+                if let (false, Some(system)) = (has_system, &self.settings.system_prompt) {
+                    messages.insert(0, json!({ "role": "system", "content": system }));
+                }
+                body.insert("messages".to_owned(), Value::Array(messages));
+            },
+            (OpenAiEndpoint::Chat, None, Some(Value::String(prompt))) => {
+                let mut messages = Vec::new();
+                if let Some(system) = &self.settings.system_prompt {
+                    messages.push(json!({ "role": "system", "content": system }));
+                }
+                messages.push(json!({ "role": "user", "content": prompt }));
+                body.insert("messages".to_owned(), Value::Array(messages));
+            },
+            (
+                OpenAiEndpoint::Completion,
+                _,
+                Some(prompt @ (Value::String(_) | Value::Array(_))),
+            ) => {
+                body.insert("prompt".to_owned(), prompt.clone());
+            },
+            _ => return Err(Error::InvalidInput.into()),
+        }
+        Ok(Value::Object(body))
+    }
+}
+
+/// JSON schema of the input accepted for the model: a prompt, or an object with the prompt or the
+/// chat messages and the sampling parameters.
+pub fn input_schema(settings: &OpenAiSettings) -> Value {
+    let mut properties: Map<String, Value> =
+        PARAMS.iter().map(|param| (param.to_string(), param_schema(param))).collect();
+    if let Some(limit) = settings.max_tokens {
+        properties["max_tokens"]["description"] = json!(format!("Capped at {limit}"));
+    }
+    let prompt = match settings.endpoint {
+        OpenAiEndpoint::Chat => json!({ "type": "string" }),
+        OpenAiEndpoint::Completion => json!({
+            "oneOf": [{ "type": "string" }, { "type": "array", "items": { "type": "string" } }]
+        }),
+    };
+    properties.insert("prompt".to_owned(), prompt);
+    let required = match settings.endpoint {
+        OpenAiEndpoint::Chat => {
+            properties.insert(
+                "messages".to_owned(),
+                json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "role": { "enum": ["system", "user", "assistant"] },
+                            "content": { "type": "string" }
+                        },
+                        "required": ["role", "content"]
+                    }
+                }),
+            );
+            json!([{ "required": ["messages"] }, { "required": ["prompt"] }])
+        },
+        OpenAiEndpoint::Completion => json!([{ "required": ["prompt"] }]),
+    };
+    json!({
+        "oneOf": [
+            { "type": "string", "description": "The prompt" },
+            { "type": "object", "properties": properties, "anyOf": required }
+        ]
+    })
+}
+
+fn param_schema(param: &str) -> Value {
+    match param {
+        "max_tokens" | "n" => json!({ "type": "integer", "minimum": 1 }),
+        "seed" => json!({ "type": "integer" }),
+        "stop" => json!({
+            "oneOf": [{ "type": "string" }, { "type": "array", "items": { "type": "string" } }]
+        }),
+        _ => json!({ "type": "number" }),
+    }
+}
+
+#[async_trait]
+impl InferenceBackend for OpenAiBackend {
+    async fn health(&self) -> Result<Health> {
+        match self.served_model().await? {
+            Some(_) => Ok(Health::Ready),
+            None => {
+                tracing::warn!("⚠️ Model {} is not served at {}", self.settings.model, self.url);
+                Ok(Health::SetupFailed)
+            },
+        }
+    }
+
+    async fn schema(&self) -> Result<Option<Value>> {
+        Ok(Some(input_schema(&self.settings)))
+    }
+
+    async fn predict(&self, _prediction_id: &str, input: Value) -> Result<Prediction> {
+        let path = match self.settings.endpoint {
+            OpenAiEndpoint::Chat => "v1/chat/completions",
+            OpenAiEndpoint::Completion => "v1/completions",
+        };
+        let body = self.body(input)?;
+        let started_at = now_rfc3339();
+        let response =
+            self.request(self.http.post(self.url.join(path)?)).json(&body).send().await?;
+        let completed_at = now_rfc3339();
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.json::<Value>().await.unwrap_or_default();
+            let message = body["error"]["message"]
+                .as_str()
+                .map_or_else(|| status.to_string(), str::to_owned);
+            return Ok(Prediction {
+                id: None,
+                status: Status::Failed,
+                output: None,
+                error: Some(message),
+                started_at: Some(started_at),
+                completed_at: Some(completed_at),
+                metrics: None,
+                usage: None,
+                logs: String::new(),
+            });
+        }
+
+        let completion = response.json::<Completion>().await?;
+        let output = completion.choices.first().map(|choice| match self.settings.endpoint {
+            OpenAiEndpoint::Chat => choice["message"]["content"].clone(),
+            OpenAiEndpoint::Completion => choice["text"].clone(),
+        });
+        let metrics = completion.usage.map(|usage| {
+            HashMap::from([
+                ("prompt_tokens".to_owned(), json!(usage.prompt_tokens)),
+                ("completion_tokens".to_owned(), json!(usage.completion_tokens)),
+                ("total_tokens".to_owned(), json!(usage.total_tokens)),
+            ])
+        });
+        Ok(Prediction {
+            id: completion.id,
+            status: Status::Succeeded,
+            output,
+            error: None,
+            started_at: Some(started_at),
+            completed_at: Some(completed_at),
+            metrics,
+            usage: completion.usage,
+            logs: String::new(),
+        })
+    }
+
+    async fn cancel(&self, _prediction_id: &str) -> Result<()> {
+        Err(Error::CancelNotSupported.into())
+    }
+}
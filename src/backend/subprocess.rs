@@ -0,0 +1,298 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex as StdMutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    sync::Mutex,
+    time::timeout,
+};
+
+use crate::{
+    backend::{Health, InferenceBackend, Prediction, Status},
+    types::{InputMode, Result, SubprocessSettings},
+    utils::now_rfc3339,
+};
+
+/// `PATH` of the command unless set in its environment.
+const DEFAULT_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+/// Placeholder of the input file path in the command arguments.
+const INPUT_PLACEHOLDER: &str = "{input}";
+/// Time for the output of a killed command to be closed.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+static INPUT_FILES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Command is empty")]
+    EmptyCommand,
+    #[error("Prediction {0} is not running")]
+    NotRunning(String),
+}
+
+/// Model run as a local command, once per request. The input JSON is passed on stdin or in a
+/// temporary file, the output JSON is read from stdout and stderr is captured as logs. Each
+/// replica url is a worker slot running one command at a time. The command runs in a process
+/// group of its own, killed as a whole on timeout or cancellation.
+pub struct SubprocessBackend {
+    settings: SubprocessSettings,
+    /// ID of the running prediction and process ID of its command, if any.
+    running: StdMutex<Option<(String, u32)>>,
+    /// Serializes predictions of the slot.
+    slot: Mutex<()>,
+}
+
+impl SubprocessBackend {
+    pub fn new(settings: SubprocessSettings) -> Result<Self> {
+        if settings.command.is_empty() {
+            return Err(Error::EmptyCommand.into());
+        }
+        Ok(Self { settings, running: StdMutex::new(None), slot: Mutex::new(()) })
+    }
+
+    fn path_var(&self) -> &str {
+        self.settings.env.get("PATH").map_or(DEFAULT_PATH, String::as_str)
+    }
+
+    /// Whether the program can be found, either by path or in `PATH`.
+    fn program_exists(&self) -> bool {
+        let program = Path::new(&self.settings.command[0]);
+        if program.components().count() > 1 {
+            return program.is_file();
+        }
+        env::split_paths(self.path_var()).any(|dir| dir.join(program).is_file())
+    }
+
+    fn command(&self, input_file: Option<&Path>) -> Command {
+        let (program, args) = self.settings.command.split_first().expect("command is not empty");
+        let mut command = Command::new(program);
+        for arg in args {
+            match input_file {
+                Some(path) => command.arg(arg.replace(INPUT_PLACEHOLDER, &path.to_string_lossy())),
+                None => command.arg(arg),
+            };
+        }
+        if let (Some(path), false) =
+            (input_file, args.iter().any(|arg| arg.contains(INPUT_PLACEHOLDER)))
+        {
+            command.arg(path);
+        }
+        command
+            .env_clear()
+            .env("PATH", self.path_var())
+            .envs(&self.settings.env)
+            .stdin(if input_file.is_some() { Stdio::null() } else { Stdio::piped() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = &self.settings.working_dir {
+            command.current_dir(dir);
+        }
+
+        let (memory, cpu) = (self.settings.limits.memory_mb, self.settings.limits.cpu_secs);
+        // SAFETY: Only async-signal-safe functions are called between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                // A group of its own, so the processes it starts are killed along with it
+                if libc::setpgid(0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if let Some(memory) = memory {
+                    set_limit(libc::RLIMIT_AS, memory.saturating_mul(1024 * 1024))?;
+                }
+                if let Some(cpu) = cpu {
+                    set_limit(libc::RLIMIT_CPU, cpu)?;
+                }
+                Ok(())
+            });
+        }
+        command
+    }
+
+    /// Write the input to a temporary file readable by the owner only.
+    async fn write_input_file(&self, input: &[u8]) -> Result<PathBuf> {
+        let n = INPUT_FILES.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("wingman-input-{}-{n}.json", std::process::id()));
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .await?;
+        file.write_all(input).await?;
+        file.flush().await?;
+        Ok(path)
+    }
+
+    fn set_running(&self, running: Option<(String, u32)>) {
+        *self.running.lock().expect("running lock should not be poisoned") = running;
+    }
+
+    async fn run(&self, input: Vec<u8>, prediction: &mut Prediction) -> Result<Option<Value>> {
+        let input_file = match self.settings.input {
+            InputMode::Stdin => None,
+            InputMode::File => Some(self.write_input_file(&input).await?),
+        };
+
+        let mut child = self.command(input_file.as_deref()).spawn()?;
+        let pid = child.id().unwrap_or_default();
+        self.set_running(prediction.id.clone().map(|id| (id, pid)));
+
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let limit = self.settings.max_output_bytes;
+        let run = async {
+            let write = async {
+                if let Some(mut stdin) = stdin {
+                    // The command might not read its input, which is fine
+                    let _ = stdin.write_all(&input).await;
+                }
+            };
+            let ((), stdout, stderr) =
+                tokio::join!(write, read_limited(stdout, limit), read_limited(stderr, limit));
+            let status = child.wait().await?;
+            Ok::<_, std::io::Error>((status, stdout?, stderr?))
+        };
+        tokio::pin!(run);
+        let result = match timeout(Duration::from_secs(self.settings.timeout_secs), &mut run).await
+        {
+            Ok(result) => Some(result),
+            Err(_) => {
+                // Killing the group closes the output, so the logs so far can still be read
+                if let Err(e) = kill_group(pid) {
+                    tracing::warn!("⚠️ Failed to kill command {pid}: {e}");
+                }
+                if let Ok(Ok((_, _, (stderr, _)))) = timeout(KILL_GRACE, &mut run).await {
+                    prediction.logs = String::from_utf8_lossy(&stderr).into_owned();
+                }
+                None
+            },
+        };
+        self.set_running(None);
+        if let Some(path) = input_file {
+            let _ = fs::remove_file(path).await;
+        }
+
+        let Some(result) = result else {
+            prediction.error = Some(format!("Timed out after {}s", self.settings.timeout_secs));
+            return Ok(None);
+        };
+        let (status, (stdout, stdout_truncated), (stderr, _)) = result?;
+        prediction.logs = String::from_utf8_lossy(&stderr).into_owned();
+        for line in prediction.logs.lines() {
+            tracing::debug!("🐚 {line}");
+        }
+
+        if !status.success() {
+            prediction.error = Some(match status.code() {
+                Some(code) => format!("Command exited with code {code}"),
+                None => "Command was killed".to_owned(),
+            });
+            return Ok(None);
+        }
+        if stdout_truncated {
+            prediction.error = Some(format!("Output exceeds {limit} bytes"));
+            return Ok(None);
+        }
+        match serde_json::from_slice(&stdout) {
+            Ok(output) => Ok(Some(output)),
+            Err(e) => {
+                prediction.error = Some(format!("Output is not valid JSON: {e}"));
+                Ok(None)
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl InferenceBackend for SubprocessBackend {
+    async fn health(&self) -> Result<Health> {
+        if !self.program_exists() {
+            return Ok(Health::SetupFailed);
+        }
+        let running = self.running.lock().expect("running lock should not be poisoned");
+        Ok(if running.is_none() { Health::Ready } else { Health::Busy })
+    }
+
+    async fn schema(&self) -> Result<Option<Value>> {
+        Ok(None)
+    }
+
+    async fn predict(&self, prediction_id: &str, input: Value) -> Result<Prediction> {
+        let _slot = self.slot.lock().await;
+        let mut prediction = Prediction {
+            id: Some(prediction_id.to_owned()),
+            status: Status::Processing,
+            output: None,
+            error: None,
+            started_at: Some(now_rfc3339()),
+            completed_at: None,
+            metrics: None,
+            usage: None,
+            logs: String::new(),
+        };
+        let output = self.run(serde_json::to_vec(&input)?, &mut prediction).await?;
+        prediction.completed_at = Some(now_rfc3339());
+        prediction.status = if output.is_some() { Status::Succeeded } else { Status::Failed };
+        prediction.output = output;
+        Ok(prediction)
+    }
+
+    async fn cancel(&self, prediction_id: &str) -> Result<()> {
+        let running = self.running.lock().expect("running lock should not be poisoned").clone();
+        let Some((_, pid)) = running.filter(|(id, _)| id == prediction_id) else {
+            return Err(Error::NotRunning(prediction_id.to_owned()).into());
+        };
+        kill_group(pid)?;
+        Ok(())
+    }
+}
+
+/// Kill a command along with the processes it started.
+fn kill_group(pid: u32) -> std::io::Result<()> {
+    // SAFETY: Sending a signal has no memory safety implications.
+    if unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type Resource = libc::c_int;
+
+fn set_limit(resource: Resource, limit: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit { rlim_cur: limit, rlim_max: limit };
+    // SAFETY: The limit is a valid rlimit struct.
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Read up to `limit` bytes. The rest is drained so the process does not block on a full pipe.
+/// Returns whether the output was truncated.
+async fn read_limited<R: AsyncRead + Unpin>(
+    mut reader: R,
+    limit: usize,
+) -> std::io::Result<(Vec<u8>, bool)> {
+    let mut output = Vec::new();
+    (&mut reader).take(limit as u64).read_to_end(&mut output).await?;
+    let drained = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok((output, drained > 0))
+}
@@ -0,0 +1,60 @@
+//! JSON templates. Strings of a template may hold `{{path}}` placeholders, where the path is a
+//! dot separated list of object keys and array indices into the context, e.g.
+//! `{{response.choices.0.text}}`. A string made of a single placeholder is replaced by the
+//! referenced value whatever its type. Placeholders within longer strings are interpolated.
+//! Missing values render as `null`, or as nothing when interpolated.
+
+use serde_json::Value;
+
+/// Render the template with values of the context.
+pub fn render(template: &Value, context: &Value) -> Value {
+    match template {
+        Value::String(template) => render_str(template, context),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| render(item, context)).collect())
+        },
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render(value, context)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+fn render_str(template: &str, context: &Value) -> Value {
+    if let Some(path) = template.strip_prefix("{{").and_then(|rest| rest.strip_suffix("}}")) {
+        if !path.contains("{{") {
+            return lookup(context, path.trim()).cloned().unwrap_or(Value::Null);
+        }
+    }
+
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match lookup(context, rest[start + 2..start + end].trim()) {
+            Some(Value::String(value)) => rendered.push_str(value),
+            Some(Value::Null) | None => {},
+            Some(value) => rendered.push_str(&value.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Value::String(rendered)
+}
+
+/// Find the value at the dot separated path. An empty path refers to the context itself.
+pub fn lookup<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(context, |value, key| match value {
+            Value::Object(fields) => fields.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+            _ => None,
+        })
+}
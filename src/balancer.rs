@@ -0,0 +1,303 @@
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::time::{interval, sleep, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    backend::{self, Health, InferenceBackend, Prediction, Status},
+    cog,
+    data::ModelRepo,
+    events::{EventHub, EventKind, ReplicaHealth},
+    types::{Backend, Balancing, Model, ModelId, Result},
+    utils::now_rfc3339,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Model has no replicas")]
+    NoReplicas,
+    #[error("No replica of model {0} became ready in time")]
+    Timeout(String),
+    #[error("Prediction {0} was cancelled")]
+    Cancelled(String),
+}
+
+/// How long a prediction waits for a ready replica, unless the model sets its own timeout.
+const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often replicas are checked while waiting for one to become ready.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the replicas of all models are checked in the background.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(10);
+/// Time for a cancelled prediction to wind down on its backend.
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// A single service, e.g. a Cog container, serving a model.
+struct Replica {
+    url: String,
+    /// Settings the backend was connected with.
+    settings: Backend,
+    backend: Arc<dyn InferenceBackend + Send + Sync>,
+    /// Number of predictions currently in flight on this replica.
+    outstanding: AtomicUsize,
+    /// Result of the last health check. Replicas are assumed healthy until checked.
+    healthy: AtomicBool,
+    /// Health reported by the last check.
+    health: Mutex<Option<ReplicaHealth>>,
+}
+
+/// A replica acquired for a prediction. The replica's outstanding counter is released on drop.
+pub struct ReplicaGuard {
+    replica: Arc<Replica>,
+}
+
+impl ReplicaGuard {
+    /// Base url of the acquired replica.
+    pub fn url(&self) -> &str {
+        &self.replica.url
+    }
+
+    /// Make a prediction, cancelled on the backend once the token is cancelled or the timeout
+    /// elapses. A prediction that timed out fails, a cancelled one is an error.
+    pub async fn predict_until(
+        &self,
+        prediction_id: &str,
+        input: Value,
+        timeout: Option<Duration>,
+        token: &CancellationToken,
+    ) -> Result<Prediction> {
+        let started_at = now_rfc3339();
+        let deadline = async {
+            match timeout {
+                Some(timeout) => sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        let predict = self.predict(prediction_id, input);
+        tokio::pin!(predict);
+        let cancelled = tokio::select! {
+            prediction = &mut predict => return prediction,
+            _ = deadline => false,
+            _ = token.cancelled() => true,
+        };
+        if let Err(e) = self.cancel(prediction_id).await {
+            tracing::warn!("⚠️ Failed to cancel prediction {prediction_id} on {}: {e}", self.url());
+        }
+        // The outcome of a cancelled prediction is of no use, but the backend cleans up meanwhile
+        let _ = tokio::time::timeout(CANCEL_GRACE, predict).await;
+        if cancelled {
+            return Err(Error::Cancelled(prediction_id.to_owned()).into());
+        }
+        let timeout = timeout.unwrap_or_default().as_secs();
+        Ok(Prediction {
+            id: Some(prediction_id.to_owned()),
+            status: Status::Failed,
+            output: None,
+            error: Some(format!("Timed out after {timeout}s")),
+            started_at: Some(started_at),
+            completed_at: Some(now_rfc3339()),
+            metrics: None,
+            usage: None,
+            logs: String::new(),
+        })
+    }
+}
+
+impl Deref for ReplicaGuard {
+    type Target = dyn InferenceBackend + Send + Sync;
+
+    fn deref(&self) -> &Self::Target {
+        self.replica.backend.as_ref()
+    }
+}
+
+impl Drop for ReplicaGuard {
+    fn drop(&mut self) {
+        self.replica.outstanding.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Pool of model replicas. Routes predictions between the replicas of a model according to the
+/// model's [Balancing] strategy, skipping replicas that are busy or fail health checks.
+#[derive(Default)]
+pub struct ReplicaPool {
+    replicas: DashMap<String, Arc<Replica>>,
+    cursors: DashMap<ModelId, usize>,
+    events: EventHub,
+}
+
+impl ReplicaPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pool publishing the health transitions of its replicas to the hub.
+    pub fn with_events(events: EventHub) -> Self {
+        Self { events, ..Self::default() }
+    }
+
+    /// Number of replicas of the model that are not known to be unhealthy.
+    pub fn capacity(&self, model: &Model) -> usize {
+        model
+            .details
+            .urls
+            .iter()
+            .filter(|url| self.replicas.get(*url).is_none_or(|r| r.healthy.load(Ordering::SeqCst)))
+            .count()
+    }
+
+    /// Acquire a ready replica of the model. Waits until one of the replicas is ready, up to the
+    /// acquire timeout of the model. Returns an error if the model has no replicas, all of them
+    /// report [Health::SetupFailed] or none became ready in time.
+    pub async fn acquire(&self, model: &Model) -> Result<ReplicaGuard> {
+        let timeout = model.details.acquire_timeout_secs.map(Duration::from_secs);
+        let deadline = Instant::now() + timeout.unwrap_or(DEFAULT_ACQUIRE_TIMEOUT);
+        loop {
+            let mut setup_failed = 0;
+            let candidates = self.candidates(model)?;
+            for replica in candidates.iter() {
+                match self.check_replica(replica).await {
+                    Some(Health::Ready) => {
+                        replica.outstanding.fetch_add(1, Ordering::SeqCst);
+                        return Ok(ReplicaGuard { replica: replica.clone() });
+                    },
+                    Some(Health::SetupFailed) => setup_failed += 1,
+                    _ => {},
+                }
+            }
+
+            if setup_failed == candidates.len() {
+                return Err(cog::Error::SetupFailed.into());
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                tracing::warn!("⚠️ No replica of model {} became ready in time", model.name);
+                return Err(Error::Timeout(model.name.clone()).into());
+            }
+            tracing::debug!("No replica of model {} is ready. Retrying...", model.name);
+            sleep(remaining.min(RETRY_INTERVAL)).await;
+        }
+    }
+
+    /// Check the health of every replica of the model, publishing the transitions.
+    pub async fn check(&self, model: &Model) -> Result<()> {
+        for replica in self.candidates(model)? {
+            self.check_replica(&replica).await;
+        }
+        Ok(())
+    }
+
+    /// Check the health of a replica and record it. Returns `None` if the check failed.
+    async fn check_replica(&self, replica: &Replica) -> Option<Health> {
+        let health = match replica.backend.health().await {
+            Ok(health) => Some(health),
+            Err(e) => {
+                tracing::warn!("Health check of {} failed: {e}", replica.url);
+                None
+            },
+        };
+        replica
+            .healthy
+            .store(health.is_some_and(|health| health != Health::SetupFailed), Ordering::SeqCst);
+        self.observe(replica, health.map_or(ReplicaHealth::Unreachable, Into::into));
+        health
+    }
+
+    /// Record the health of a replica, publishing it if it changed.
+    fn observe(&self, replica: &Replica, health: ReplicaHealth) {
+        let mut last = replica.health.lock().expect("health lock should not be poisoned");
+        if *last != Some(health) {
+            *last = Some(health);
+            let url = replica.url.clone();
+            self.events.publish(EventKind::HealthChanged { url, health });
+        }
+    }
+
+    /// Replicas of the model ordered by preference.
+    fn candidates(&self, model: &Model) -> Result<Vec<Arc<Replica>>> {
+        let mut replicas = model
+            .details
+            .urls
+            .iter()
+            .map(|url| self.replica(url, &model.details.backend))
+            .collect::<Result<Vec<_>>>()?;
+        if replicas.is_empty() {
+            return Err(Error::NoReplicas.into());
+        }
+
+        match model.details.balancing {
+            Balancing::LeastOutstanding => {
+                replicas.sort_by_key(|r| r.outstanding.load(Ordering::SeqCst));
+            },
+            Balancing::RoundRobin => {
+                let mut cursor = self.cursors.entry(model.id).or_default();
+                let len = replicas.len();
+                replicas.rotate_left(*cursor % len);
+                *cursor = cursor.wrapping_add(1);
+            },
+        }
+        Ok(replicas)
+    }
+
+    /// Replica at the url. The replica is reconnected if the backend settings changed.
+    fn replica(&self, url: &str, settings: &Backend) -> Result<Arc<Replica>> {
+        if let Some(replica) = self.replicas.get(url).filter(|r| r.settings == *settings) {
+            return Ok(replica.clone());
+        }
+        let replica = Arc::new(Replica {
+            url: url.to_owned(),
+            settings: settings.clone(),
+            backend: backend::connect(url, settings)?,
+            outstanding: AtomicUsize::new(0),
+            healthy: AtomicBool::new(true),
+            health: Mutex::default(),
+        });
+        let mut entry = self.replicas.entry(url.to_owned()).or_insert_with(|| replica.clone());
+        if entry.settings != *settings {
+            *entry = replica;
+        }
+        Ok(entry.clone())
+    }
+}
+
+/// Checks the replicas of the served models in the background, so health transitions are
+/// published and unhealthy replicas are skipped even while no predictions are made.
+pub struct HealthMonitor {
+    replica_pool: Arc<ReplicaPool>,
+    model_repo: Arc<dyn ModelRepo + Send + Sync>,
+}
+
+impl HealthMonitor {
+    pub fn new(
+        replica_pool: Arc<ReplicaPool>,
+        model_repo: Arc<dyn ModelRepo + Send + Sync>,
+    ) -> Self {
+        Self { replica_pool, model_repo }
+    }
+
+    pub async fn run(self, token: CancellationToken) -> Result<()> {
+        let mut poll = interval(HEALTH_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = poll.tick() => {
+                    for model in self.model_repo.list().await {
+                        if let Err(e) = self.replica_pool.check(&model).await {
+                            tracing::warn!("Health check of model {} failed: {e}", model.name);
+                        }
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
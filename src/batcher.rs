@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::{
+    backend::{Prediction, Status},
+    balancer::ReplicaPool,
+    types::{BatchSettings, Model, ModelId, Result},
+    utils::now_millis,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Batch prediction failed: {0}")]
+    Failed(String),
+    #[error("Batch was dropped")]
+    Dropped,
+}
+
+/// Requests collected for the next prediction of a model.
+struct Batch {
+    /// Distinguishes batches of the same model, so a timer only flushes its own batch.
+    id: u64,
+    requests: Vec<(Value, oneshot::Sender<Result<Prediction>>)>,
+}
+
+/// Collects concurrent requests to a batchable model and makes one prediction for all of them.
+/// A batch is sent once it is full, or once its first request waited for the batch window.
+pub struct Batcher {
+    replica_pool: Arc<ReplicaPool>,
+    batches: Mutex<HashMap<ModelId, Batch>>,
+    next_id: AtomicU64,
+    /// Tracks the timers and predictions of batches, so shutdown waits for them.
+    tracker: TaskTracker,
+    /// Cancels the predictions of batches on shutdown.
+    token: CancellationToken,
+}
+
+impl Batcher {
+    pub fn new(
+        replica_pool: Arc<ReplicaPool>,
+        tracker: TaskTracker,
+        token: CancellationToken,
+    ) -> Self {
+        let next_id = AtomicU64::new(0);
+        Self { replica_pool, batches: Mutex::default(), next_id, tracker, token }
+    }
+
+    /// Make a prediction as part of a batch. Resolves once the batch is predicted.
+    pub async fn predict(
+        self: &Arc<Self>,
+        model: &Model,
+        settings: &BatchSettings,
+        input: Value,
+    ) -> Result<Prediction> {
+        let (tx, rx) = oneshot::channel();
+        let full = {
+            let mut batches = self.batches.lock().expect("batcher lock should not be poisoned");
+            let batch = batches.entry(model.id).or_insert_with(|| Batch {
+                id: self.next_id.fetch_add(1, Ordering::SeqCst),
+                requests: Vec::new(),
+            });
+            batch.requests.push((input, tx));
+            if batch.requests.len() == 1 {
+                let (batcher, model, settings) = (self.clone(), model.clone(), settings.clone());
+                let id = batch.id;
+                self.tracker.spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(settings.window_ms)).await;
+                    if let Some(batch) = batcher.take(&model.id, Some(id)) {
+                        batcher.flush(&model, &settings, batch).await;
+                    }
+                });
+            }
+            batch.requests.len() >= settings.max_size
+        };
+        if full {
+            if let Some(batch) = self.take(&model.id, None) {
+                let (batcher, model, settings) = (self.clone(), model.clone(), settings.clone());
+                self.tracker.spawn(async move { batcher.flush(&model, &settings, batch).await });
+            }
+        }
+        rx.await.map_err(|_| Error::Dropped)?
+    }
+
+    /// Take the pending batch of a model, if it is the given one.
+    fn take(&self, model_id: &ModelId, id: Option<u64>) -> Option<Batch> {
+        let mut batches = self.batches.lock().expect("batcher lock should not be poisoned");
+        match batches.get(model_id) {
+            Some(batch) if id.is_none_or(|id| id == batch.id) => batches.remove(model_id),
+            _ => None,
+        }
+    }
+
+    async fn flush(&self, model: &Model, settings: &BatchSettings, batch: Batch) {
+        let (inputs, senders): (Vec<_>, Vec<_>) = batch.requests.into_iter().unzip();
+        let size = inputs.len();
+        tracing::debug!("📦 Predicting a batch of {size} requests to {}", model.name);
+        let input = Value::Object(Map::from_iter([(settings.input_field.clone(), inputs.into())]));
+        let prediction_id = format!("batch-{}-{}", batch.id, now_millis());
+        let timeout = model.details.predict_timeout_secs.map(Duration::from_secs);
+        let prediction = match self.replica_pool.acquire(model).await {
+            Ok(replica) => replica.predict_until(&prediction_id, input, timeout, &self.token).await,
+            Err(e) => Err(e),
+        };
+        let predictions = match prediction {
+            Ok(prediction) => split(settings, prediction, size),
+            Err(e) => {
+                let e = e.to_string();
+                senders.into_iter().for_each(|tx| {
+                    let _ = tx.send(Err(Error::Failed(e.clone()).into()));
+                });
+                return;
+            },
+        };
+        for (tx, prediction) in senders.into_iter().zip(predictions) {
+            // The request might have been dropped, which is fine
+            let _ = tx.send(Ok(prediction));
+        }
+    }
+}
+
+/// Split the prediction of a batch into one prediction per request. If the outputs do not match
+/// the inputs, every request fails.
+pub fn split(settings: &BatchSettings, prediction: Prediction, size: usize) -> Vec<Prediction> {
+    let outputs = match (&prediction.status, &prediction.output) {
+        (Status::Succeeded, Some(output)) => {
+            let outputs = match &settings.output_field {
+                Some(field) => output.get(field),
+                None => Some(output),
+            };
+            match outputs.and_then(Value::as_array) {
+                Some(outputs) if outputs.len() == size => Ok(outputs.clone()),
+                Some(outputs) => {
+                    Err(format!("Batch output has {} items, expected {size}", outputs.len()))
+                },
+                None => Err("Batch output is not a list".to_owned()),
+            }
+        },
+        _ => Err(prediction.error.clone().unwrap_or_else(|| "Batch prediction failed".to_owned())),
+    };
+    match outputs {
+        Ok(outputs) => outputs
+            .into_iter()
+            .map(|output| Prediction { output: Some(output), ..prediction.clone() })
+            .collect(),
+        Err(error) => {
+            let failed = Prediction {
+                status: Status::Failed,
+                output: None,
+                error: Some(error),
+                ..prediction
+            };
+            vec![failed; size]
+        },
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    mem,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+
+use crate::types::{ContentId, ModelId};
+
+/// Key of a cached result: the model, the input content and the model version.
+pub type CacheKey = (ModelId, ContentId, Option<String>);
+
+struct CacheEntry {
+    content_id: ContentId,
+    expires_at: Instant,
+}
+
+/// Approximate memory used by a cached result, including the bookkeeping of the LRU.
+fn entry_size(key: &CacheKey) -> usize {
+    mem::size_of::<CacheKey>()
+        + key.2.as_ref().map_or(0, String::len)
+        + mem::size_of::<CacheEntry>()
+        + 2 * mem::size_of::<usize>()
+}
+
+struct Entries {
+    lru: LruCache<CacheKey, CacheEntry>,
+    bytes: usize,
+}
+
+impl Entries {
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some((key, _)) = self.lru.pop_entry(key) {
+            self.bytes -= entry_size(&key);
+        }
+    }
+
+    fn pop_lru(&mut self) -> bool {
+        match self.lru.pop_lru() {
+            Some((key, _)) => {
+                self.bytes -= entry_size(&key);
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+/// Cache of prediction results. Maps an input to the [ContentId] of an already uploaded result,
+/// so that identical requests can be answered without downloading, predicting and uploading again.
+pub struct ResultCache {
+    entries: Mutex<Entries>,
+    max_bytes: usize,
+}
+
+impl ResultCache {
+    /// Create a new cache using at most about `max_bytes` of memory.
+    pub fn new(max_bytes: usize) -> Self {
+        let entries = Entries { lru: LruCache::unbounded(), bytes: 0 };
+        Self { entries: Mutex::new(entries), max_bytes }
+    }
+
+    /// Get the result of a previous prediction if it has not expired yet.
+    pub fn get(&self, key: &CacheKey) -> Option<ContentId> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        match entries.lru.get(key) {
+            Some(entry) if entry.expires_at > now => Some(entry.content_id),
+            Some(_) => {
+                entries.remove(key);
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// Cache the result of a prediction for the given time to live. When the cache is full, the
+    /// least recently used results are evicted.
+    pub fn insert(&self, key: CacheKey, content_id: ContentId, ttl: Duration) {
+        let size = entry_size(&key);
+        if size > self.max_bytes {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&key);
+        while entries.bytes + size > self.max_bytes && entries.pop_lru() {}
+        let expires_at = Instant::now() + ttl;
+        entries.lru.put(key, CacheEntry { content_id, expires_at });
+        entries.bytes += size;
+    }
+
+    /// Number of cached results.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().lru.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Approximate memory used by the cached results.
+    pub fn size(&self) -> usize {
+        self.entries.lock().unwrap().bytes
+    }
+}
@@ -0,0 +1,287 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use reqwest::{Client, Method, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    balancer::ReplicaPool,
+    config::{validate_model, Config},
+    data::ledger::EarningsSummary,
+    protocol::AiroClient,
+    signer::ProviderKey,
+    types::{
+        Agreement, AgreementState, Backend, Balance, Balancing, CacheSettings, ExecutionResult,
+        Model, ModelDetails, Result,
+    },
+    utils::now_millis,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Signer is not configured")]
+    NoSigner,
+    #[error("Model {0} not found")]
+    ModelNotFound(String),
+    #[error("Model {0} has no urls")]
+    NoUrls(String),
+    #[error("Invalid model: {0}")]
+    InvalidModel(String),
+    #[error("{method} {url} failed with {status}: {body}")]
+    Api { method: Method, url: Url, status: reqwest::StatusCode, body: String },
+}
+
+/// Airo wingman. Serves AI models on the Airo network.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the wingman. This is the default command.
+    Run,
+    /// Manage the models served by a running wingman.
+    Models {
+        #[command(flatten)]
+        api: ApiArgs,
+        #[command(subcommand)]
+        command: ModelsCommand,
+    },
+    /// List the agreements of a running wingman.
+    Agreements {
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+    /// Show the status of a running wingman.
+    Status {
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+    /// Make a prediction without involving the chain. Useful to check a model works.
+    Predict {
+        /// Name of the model. Its settings are looked up in the running wingman.
+        #[arg(long, required_unless_present = "url")]
+        model: Option<String>,
+        /// Base url of the replica to predict with. Defaults to the first replica of the model.
+        /// Is assumed to be a Cog container unless the model is given.
+        #[arg(long)]
+        url: Option<String>,
+        /// JSON file with the prediction input.
+        #[arg(long)]
+        input: PathBuf,
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+    /// Show the provider address and balance. The signer is loaded from the configuration.
+    Account,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ModelsCommand {
+    /// List the models.
+    List,
+    /// Add or update a model.
+    Add {
+        /// Name of the model.
+        name: String,
+        /// Price per request.
+        #[arg(long)]
+        price: Balance,
+        /// Base url of a Cog replica serving the model. Can be repeated.
+        #[arg(long = "url", required = true)]
+        urls: Vec<String>,
+        /// Route predictions to replicas in turn instead of the least loaded one.
+        #[arg(long)]
+        round_robin: bool,
+        /// Version of the model.
+        #[arg(long)]
+        version: Option<String>,
+        /// Cache results for the given number of seconds.
+        #[arg(long)]
+        cache_ttl: Option<u64>,
+        /// Maximum number of requests committed to across active agreements.
+        #[arg(long)]
+        capacity: Option<u32>,
+    },
+    /// Remove a model.
+    Remove {
+        /// Name of the model.
+        name: String,
+    },
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ApiArgs {
+    /// Base url of the HTTP API of the running wingman.
+    #[arg(long, env = "AW_API_URL", default_value = "http://127.0.0.1:8000", global = true)]
+    pub api_url: Url,
+}
+
+/// Run the command given on the command line.
+pub async fn run(cli: Cli) -> Result<()> {
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => crate::start().await,
+        Command::Models { api, command } => models(ApiClient::new(api), command).await,
+        Command::Agreements { api } => {
+            let agreements: Value = ApiClient::new(api).get("v1/agreements").await?;
+            print_json(&agreements)
+        },
+        Command::Status { api } => status(ApiClient::new(api)).await,
+        Command::Predict { model, url, input, api } => {
+            predict(ApiClient::new(api), model, url, input).await
+        },
+        Command::Account => account().await,
+    }
+}
+
+async fn models(api: ApiClient, command: ModelsCommand) -> Result<()> {
+    match command {
+        ModelsCommand::List => {
+            let models: Value = api.get("v1/models").await?;
+            print_json(&models)
+        },
+        ModelsCommand::Add { name, price, urls, round_robin, version, cache_ttl, capacity } => {
+            let details = ModelDetails {
+                price_per_request: price,
+                urls,
+                backend: Backend::Cog,
+                balancing: if round_robin { Balancing::RoundRobin } else { Balancing::default() },
+                acquire_timeout_secs: None,
+                predict_timeout_secs: None,
+                version,
+                cache: cache_ttl.map(|ttl_secs| CacheSettings { ttl_secs }),
+                capacity,
+                batching: None,
+                consumers: Default::default(),
+                content: Default::default(),
+            };
+            // Batching is not set, so the concurrency of the wingman does not matter yet. The
+            // wingman validates the model again when it's saved
+            let problems = validate_model(&name, &details, usize::MAX);
+            if !problems.is_empty() {
+                return Err(Error::InvalidModel(problems.join(", ")).into());
+            }
+            api.send(Method::PUT, &format!("v1/models/{name}"), Some(&details)).await?;
+            println!("Model {name} saved");
+            Ok(())
+        },
+        ModelsCommand::Remove { name } => {
+            api.send::<()>(Method::DELETE, &format!("v1/models/{name}"), None).await?;
+            println!("Model {name} removed");
+            Ok(())
+        },
+    }
+}
+
+async fn status(api: ApiClient) -> Result<()> {
+    api.send::<()>(Method::GET, "check/health", None).await?;
+    let models: Vec<Model> = api.get("v1/models").await?;
+    let agreements: Vec<Agreement> = api.get("v1/agreements").await?;
+    // Balances exceed 64 bits, so the summaries are read as is rather than as JSON values
+    let earnings: Vec<EarningsSummary> = api.get("v1/earnings?group_by=model").await?;
+    let net = earnings.iter().fold(0i128, |net, summary| net.saturating_add(summary.net));
+
+    println!("Wingman at {} is healthy", api.base);
+    println!("Models:");
+    for model in &models {
+        let replicas = model.details.urls.len();
+        println!(
+            "  {} ({replicas} replicas, {} per request)",
+            model.name, model.details.price_per_request
+        );
+    }
+    let active = agreements.iter().filter(|a| a.state == AgreementState::Active).count();
+    println!("Agreements: {} ({active} active)", agreements.len());
+    println!("Net earnings: {net}");
+    Ok(())
+}
+
+async fn predict(
+    api: ApiClient,
+    model: Option<String>,
+    url: Option<String>,
+    input: PathBuf,
+) -> Result<()> {
+    let input: Value = serde_json::from_slice(&std::fs::read(&input)?)?;
+    let mut model = match model {
+        Some(name) => {
+            let models: Vec<Model> = api.get("v1/models").await?;
+            models
+                .into_iter()
+                .find(|model| model.name == name)
+                .ok_or(Error::ModelNotFound(name))?
+        },
+        None => Model::new("local".to_owned(), ModelDetails::default()),
+    };
+    if let Some(url) = url {
+        model.details.urls = vec![url];
+    }
+    model.details.urls.truncate(1);
+    if model.details.urls.is_empty() {
+        return Err(Error::NoUrls(model.name).into());
+    }
+
+    let pool = ReplicaPool::new();
+    let replica = pool.acquire(&model).await?;
+    eprintln!("Predicting with {}", replica.url());
+    // Print the result as it would be uploaded in response to a request
+    let prediction = replica.predict(&format!("cli-{}", now_millis()), input).await?;
+    print_json(&ExecutionResult::from(prediction))
+}
+
+async fn account() -> Result<()> {
+    let mut config = Config::load()?;
+    let signer = config.signer.take().ok_or(Error::NoSigner)?;
+    let signer = ProviderKey::load(&signer)?;
+    let client = AiroClient::new(&config.node.url, signer).await?;
+    println!("Address: {}", client.provider());
+    println!("Free balance: {}", client.free_balance().await?);
+    Ok(())
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Client of the HTTP API of a running wingman.
+struct ApiClient {
+    base: Url,
+    http: Client,
+}
+
+impl ApiClient {
+    fn new(args: ApiArgs) -> Self {
+        Self { base: args.api_url, http: Client::new() }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self.send::<()>(Method::GET, path, None).await?;
+        response.json().await.map_err(Into::into)
+    }
+
+    async fn send<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<reqwest::Response> {
+        let url = self.base.join(path)?;
+        let mut request = self.http.request(method.clone(), url.clone());
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Api { method, url, status, body }.into());
+        }
+        Ok(response)
+    }
+}
@@ -25,7 +25,7 @@ pub struct HTTPValidationError {
 }
 
 /// Status of setup or prediction.
-#[derive(Debug, PartialEq, Deserialize, strum::Display)]
+#[derive(Clone, Debug, PartialEq, Deserialize, strum::Display)]
 #[serde(rename_all = "lowercase")]
 pub enum Status {
     Starting,
@@ -49,8 +49,9 @@ pub struct SetupResult {
 }
 
 /// Health status.
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, strum::Display)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "snake_case")]
 pub enum Health {
     Unknown,
     Starting,
@@ -171,4 +172,37 @@ impl Connector {
             .await
             .map_err(Into::into)
     }
+
+    /// Make a prediction with the given ID, using the `PUT /predictions/{id}` endpoint of the Cog
+    /// API, so it can be cancelled while it runs. This method is blocking.
+    pub async fn predict_with_id<In, Out>(
+        &self,
+        prediction_id: &str,
+        inputs: In,
+    ) -> Result<PredictionResponse<In, Out>>
+    where
+        In: Serialize + DeserializeOwned,
+        Out: DeserializeOwned,
+    {
+        let url = self.url.join(&format!("predictions/{prediction_id}"))?;
+        let req = json!({ "id": prediction_id, "input": inputs });
+
+        let res = self.http.put(url).json(&req).send().await?;
+        if res.status() == StatusCode::UNPROCESSABLE_ENTITY {
+            let errors = res.json::<HTTPValidationError>().await?.detail;
+            return Err(Error::InputValidation { errors }.into());
+        }
+
+        res.error_for_status()?
+            .json::<PredictionResponse<In, Out>>()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Cancel a running prediction. Only predictions created with an ID can be cancelled.
+    pub async fn cancel(&self, prediction_id: &str) -> Result<()> {
+        let url = self.url.join(&format!("predictions/{prediction_id}/cancel"))?;
+        self.http.post(url).send().await.and_then(Response::error_for_status)?;
+        Ok(())
+    }
 }
@@ -1,37 +1,554 @@
-use std::{env, ffi::OsStr, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    fmt::{self, Display},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-/// Configuration for the application.
-#[derive(Debug)]
+use reqwest::Url;
+use serde::{
+    de::{
+        value::{Error as ValueError, MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+        IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+use crate::{
+    data::Retention,
+    policy::validate_check,
+    signer::PASSPHRASE_ENV,
+    types::{deserialize_tagged_balance, Backend, Balance, ModelDetails, ModelName},
+};
+
+/// Configuration for the application. Is loaded from a TOML or YAML file given in the
+/// `AW_CONFIG` environment variable, if any. Environment variables override the file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
-    /// The port to listen on. Defaults to 9090. Can be overridden with the `AW_PORT` environment
-    /// variable.
-    pub http_port: u16,
+    pub node: NodeConfig,
+    pub signer: Option<SignerConfig>,
+    pub http: HttpConfig,
+    pub engine: EngineConfig,
+    pub bidding: BiddingConfig,
+    pub cache: CacheConfig,
+    pub storage: StorageConfig,
+    /// Models served on startup, by name.
+    pub models: BTreeMap<ModelName, ModelDetails>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
     /// The blockchain node to connect to. Defaults to `ws://127.0.0.1:9944`.
     /// Can be overridden with the `AIRO_NODE` environment variable.
-    pub airo_node: String,
-    /// The secret uri of an Infrastructure Provider used for automation. Is specified in the
-    /// `AIRO_SURI` environment variable. The variable is required.
-    // TODO. Improve security by using a secret store.
-    pub airo_suri: String,
+    pub url: String,
 }
 
-impl Config {
-    pub fn new() -> Self {
-        fn get_or_panic<K: AsRef<OsStr> + Display>(key: K) -> String {
-            env::var(key.as_ref())
-                .unwrap_or_else(|_| panic!("🚨 Environment variable {key} is not set"))
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self { url: "ws://127.0.0.1:9944".to_owned() }
+    }
+}
+
+/// Source of the signing key of the Infrastructure Provider used for automation.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SignerConfig {
+    /// Secret uri given inline or with the `AIRO_SURI` environment variable, which takes
+    /// precedence over any configured source. Meant for development.
+    Uri {
+        /// Never written out, e.g. when the configuration is dumped.
+        #[serde(skip_serializing)]
+        suri: Zeroizing<String>,
+    },
+    /// Secret uri read from a file accessible by its owner only.
+    File { path: PathBuf },
+    /// Substrate keystore directory. The public key picks the account key if there are several.
+    Keystore { path: PathBuf, public_key: Option<String> },
+    /// Encrypted JSON keyfile exported from polkadot.js. The passphrase is read from
+    /// `passphrase_file` if set, from the `AIRO_PASSPHRASE` environment variable otherwise.
+    Json { path: PathBuf, passphrase_file: Option<PathBuf> },
+}
+
+impl fmt::Debug for SignerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uri { .. } => f.debug_struct("Uri").field("suri", &"***").finish(),
+            Self::File { path } => f.debug_struct("File").field("path", path).finish(),
+            Self::Keystore { path, public_key } => f
+                .debug_struct("Keystore")
+                .field("path", path)
+                .field("public_key", public_key)
+                .finish(),
+            Self::Json { path, passphrase_file } => f
+                .debug_struct("Json")
+                .field("path", path)
+                .field("passphrase_file", passphrase_file)
+                .finish(),
         }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// The address to listen on. Defaults to `0.0.0.0:8000`. The port can be overridden with the
+    /// `AW_PORT` environment variable.
+    pub bind: SocketAddr,
+}
 
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self { bind: SocketAddr::from(([0, 0, 0, 0], 8000)) }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// Maximum number of requests processed concurrently. Defaults to 4. Can be overridden with
+    /// the `AW_CONCURRENCY` environment variable.
+    pub concurrency: usize,
+    /// Time after the acceptance of a bid the requests of the agreement are answered, in
+    /// seconds. Agreements are expired afterwards. Defaults to 7 days.
+    pub response_window_secs: u64,
+    /// Order in which requests are processed when all workers are busy.
+    pub scheduling: SchedulingConfig,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
         Self {
-            http_port: envmnt::get_u16("AW_PORT", 8000),
-            airo_node: envmnt::get_or("AIRO_NODE", "ws://127.0.0.1:9944"),
-            airo_suri: get_or_panic("AIRO_SURI"),
+            concurrency: 4,
+            response_window_secs: 7 * 24 * 60 * 60,
+            scheduling: SchedulingConfig::default(),
         }
     }
 }
 
-impl Default for Config {
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulingConfig {
+    /// How requests are ordered. Defaults to arrival order.
+    pub policy: SchedulingPolicy,
+    /// Requests of agreements expiring within this time go first, in seconds. Defaults to 1 hour.
+    pub urgent_secs: u64,
+    /// Requests waiting longer than this go first regardless of their price, so requests of
+    /// cheap agreements are not starved, in seconds. Defaults to 5 minutes.
+    pub max_wait_secs: u64,
+}
+
+impl Default for SchedulingConfig {
+    fn default() -> Self {
+        Self { policy: SchedulingPolicy::default(), urgent_secs: 60 * 60, max_wait_secs: 5 * 60 }
+    }
+}
+
+/// How to order requests waiting for a worker.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingPolicy {
+    /// In arrival order. Deadlines and waiting times are ignored.
+    #[default]
+    Fifo,
+    /// Requests of the agreements paying the most per request first.
+    StrictPriority,
+    /// Agreements take turns, in proportion to what they pay per request.
+    WeightedFair,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BiddingConfig {
+    /// Whether to bid on new orders. Defaults to `true`. Can be overridden with the
+    /// `AW_BIDDING` environment variable.
+    pub enabled: bool,
+    /// How bid prices are derived from the model prices. Defaults to the model prices.
+    pub strategy: BidStrategy,
+}
+
+impl Default for BiddingConfig {
+    fn default() -> Self {
+        Self { enabled: true, strategy: BidStrategy::default() }
+    }
+}
+
+/// How to price bids. The price of a model is the most ever bid. Strategies reacting to the
+/// market compare with the lowest winning competing bid, or the lowest competing bid if none won
+/// recently, and never bid below `floor_percent` of the model price.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BidStrategy {
+    /// Bid the model price.
+    #[default]
+    Fixed,
+    /// Bid `step` below the competition.
+    Undercut {
+        #[serde(deserialize_with = "deserialize_tagged_balance")]
+        step: Balance,
+        floor_percent: u8,
+    },
+    /// Bid the same as the competition.
+    Match { floor_percent: u8 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Memory budget of the cached prediction results, in bytes. Defaults to 16 MiB. Can be
+    /// overridden with the `AW_CACHE_MAX_BYTES` environment variable.
+    pub max_bytes: usize,
+}
+
+impl Default for CacheConfig {
     fn default() -> Self {
-        Self::new()
+        Self { max_bytes: 16 * 1024 * 1024 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Directory where the wingman persists its state. State is kept in memory only if not set.
+    /// Can be overridden with the `AW_DATA_DIR` environment variable.
+    pub data_dir: Option<PathBuf>,
+    /// Request records received longer ago are pruned, in days. Defaults to 30.
+    pub history_retention_days: u64,
+    /// Maximum number of request records kept. Defaults to 100000.
+    pub history_max_records: usize,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self { data_dir: None, history_retention_days: 30, history_max_records: 100_000 }
+    }
+}
+
+impl StorageConfig {
+    /// Limits of the request history.
+    pub fn retention(&self) -> Retention {
+        Retention {
+            max_age: Duration::from_secs(self.history_retention_days.saturating_mul(24 * 60 * 60)),
+            max_records: self.history_max_records,
+        }
+    }
+}
+
+/// All problems found while loading the configuration.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            writeln!(f, "  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Load the configuration from the file given in `AW_CONFIG`, apply environment overrides and
+    /// validate the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(Self::path().as_deref())
+    }
+
+    /// Path of the configuration file given in `AW_CONFIG`, if any.
+    pub fn path() -> Option<PathBuf> {
+        env::var_os("AW_CONFIG").map(PathBuf::from)
+    }
+
+    /// Load the configuration from the given file, apply environment overrides and validate the
+    /// result.
+    pub fn load_from(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => {
+                Self::from_file(path).map_err(|problem| ConfigError { problems: vec![problem] })?
+            },
+            None => Self::default(),
+        };
+
+        let mut problems = config.apply_env();
+        problems.extend(config.validate());
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+
+    /// Parse the configuration file. The format is chosen by the file extension.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let value: Value = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+            Some("yaml" | "yml") => serde_norway::from_str(&content).map_err(|e| e.to_string()),
+            _ => return Err(format!("Unsupported config format of {}", path.display())),
+        }
+        .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+        serde_path_to_error::deserialize(ConfigValue(value))
+            .map_err(|e| format!("Invalid {}: {}: {}", path.display(), e.path(), e.inner()))
+    }
+
+    /// Override the configuration with environment variables. Returns invalid values.
+    fn apply_env(&mut self) -> Vec<String> {
+        fn parse<T: FromStr>(key: &str, target: &mut T, problems: &mut Vec<String>)
+        where
+            T::Err: Display,
+        {
+            if let Ok(value) = env::var(key) {
+                match value.parse() {
+                    Ok(value) => *target = value,
+                    Err(e) => problems.push(format!("{key}: invalid value {value:?}: {e}")),
+                }
+            }
+        }
+
+        let mut problems = Vec::new();
+        if let Ok(url) = env::var("AIRO_NODE") {
+            self.node.url = url;
+        }
+        if let Ok(suri) = env::var("AIRO_SURI") {
+            self.signer = Some(SignerConfig::Uri { suri: Zeroizing::new(suri) });
+        }
+        let mut port = self.http.bind.port();
+        parse("AW_PORT", &mut port, &mut problems);
+        self.http.bind.set_port(port);
+        parse("AW_CONCURRENCY", &mut self.engine.concurrency, &mut problems);
+        parse("AW_BIDDING", &mut self.bidding.enabled, &mut problems);
+        parse("AW_CACHE_MAX_BYTES", &mut self.cache.max_bytes, &mut problems);
+        if let Some(dir) = env::var_os("AW_DATA_DIR") {
+            self.storage.data_dir = Some(PathBuf::from(dir));
+        }
+        problems
+    }
+
+    /// Validate the configuration. Returns all problems found.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        match Url::parse(&self.node.url) {
+            Ok(url) if matches!(url.scheme(), "ws" | "wss") => {},
+            Ok(_) => problems.push(format!("node.url: {} is not a ws(s) url", self.node.url)),
+            Err(e) => problems.push(format!("node.url: {e}")),
+        }
+        match &self.signer {
+            None => problems.push("signer: key source is not set (AIRO_SURI)".to_owned()),
+            Some(SignerConfig::Uri { suri }) if suri.trim().is_empty() => {
+                problems.push("signer.suri: must not be empty".to_owned())
+            },
+            Some(SignerConfig::Uri { .. }) => {},
+            Some(SignerConfig::File { path }) if !path.is_file() => {
+                problems.push(format!("signer.path: {} is not a file", path.display()))
+            },
+            Some(SignerConfig::Keystore { path, .. }) if !path.is_dir() => {
+                problems.push(format!("signer.path: {} is not a directory", path.display()))
+            },
+            Some(SignerConfig::Json { path, passphrase_file }) => {
+                if !path.is_file() {
+                    problems.push(format!("signer.path: {} is not a file", path.display()));
+                }
+                match passphrase_file {
+                    Some(file) if !file.is_file() => problems
+                        .push(format!("signer.passphrase_file: {} is not a file", file.display())),
+                    Some(_) => {},
+                    None if env::var_os(PASSPHRASE_ENV).is_none() => problems
+                        .push(format!("signer: keyfile passphrase is not set ({PASSPHRASE_ENV})")),
+                    None => {},
+                }
+            },
+            Some(_) => {},
+        }
+        match self.bidding.strategy {
+            BidStrategy::Undercut { floor_percent, .. } | BidStrategy::Match { floor_percent }
+                if floor_percent > 100 =>
+            {
+                problems.push("bidding.strategy.floor_percent: must be at most 100".to_owned())
+            },
+            _ => {},
+        }
+        if self.engine.concurrency == 0 {
+            problems.push("engine.concurrency: must be at least 1".to_owned());
+        }
+        if self.engine.response_window_secs == 0 {
+            problems.push("engine.response_window_secs: must be positive".to_owned());
+        }
+        if self.engine.scheduling.max_wait_secs == 0 {
+            problems.push("engine.scheduling.max_wait_secs: must be positive".to_owned());
+        }
+        if let Some(dir) = &self.storage.data_dir {
+            if dir.exists() && !dir.is_dir() {
+                problems.push(format!("storage.data_dir: {} is not a directory", dir.display()));
+            }
+        }
+        if self.storage.history_retention_days == 0 {
+            problems.push("storage.history_retention_days: must be positive".to_owned());
+        }
+        if self.storage.history_max_records == 0 {
+            problems.push("storage.history_max_records: must be positive".to_owned());
+        }
+        for (name, details) in &self.models {
+            problems.extend(
+                validate_model(name, details, self.engine.concurrency)
+                    .into_iter()
+                    .map(|p| format!("models.{name}.{p}")),
+            );
+        }
+        problems
+    }
+}
+
+/// Validate model details, served with the given `engine.concurrency`. Returns all problems found.
+pub fn validate_model(name: &str, details: &ModelDetails, concurrency: usize) -> Vec<String> {
+    let mut problems = Vec::new();
+    if name.is_empty() {
+        problems.push("name: must not be empty".to_owned());
+    }
+    if details.urls.is_empty() {
+        problems.push("urls: at least one url is required".to_owned());
+    }
+    let mut seen = HashSet::new();
+    for url in &details.urls {
+        if let Err(e) = Url::parse(url) {
+            problems.push(format!("urls: {url}: {e}"));
+        }
+        if !seen.insert(url) {
+            problems.push(format!("urls: {url} is listed twice"));
+        }
+    }
+    match &details.backend {
+        Backend::OpenAi(settings) if settings.model.is_empty() => {
+            problems.push("backend.model: must not be empty".to_owned())
+        },
+        Backend::KServe(settings) if settings.model.is_empty() => {
+            problems.push("backend.model: must not be empty".to_owned())
+        },
+        Backend::Subprocess(settings) => {
+            if settings.command.is_empty() {
+                problems.push("backend.command: must not be empty".to_owned());
+            }
+            if settings.timeout_secs == 0 {
+                problems.push("backend.timeout_secs: must be positive".to_owned());
+            }
+        },
+        _ => {},
+    }
+    if details.cache.as_ref().is_some_and(|cache| cache.ttl_secs == 0) {
+        problems.push("cache.ttl_secs: must be positive".to_owned());
+    }
+    if details.acquire_timeout_secs == Some(0) {
+        problems.push("acquire_timeout_secs: must be positive".to_owned());
+    }
+    if details.predict_timeout_secs == Some(0) {
+        problems.push("predict_timeout_secs: must be positive".to_owned());
+    }
+    if details.capacity == Some(0) {
+        problems.push("capacity: must be positive".to_owned());
+    }
+    for consumer in details.consumers.allow.intersection(&details.consumers.deny) {
+        problems.push(format!("consumers: {consumer} is both allowed and denied"));
+    }
+    for (stage, checks) in [("input", &details.content.input), ("output", &details.content.output)]
+    {
+        for (i, check) in checks.iter().enumerate() {
+            if let Some(problem) = validate_check(check) {
+                problems.push(format!("content.{stage}[{i}]: {problem}"));
+            }
+        }
+    }
+    if let Some(batching) = &details.batching {
+        if batching.max_size == 0 {
+            problems.push("batching.max_size: must be at least 1".to_owned());
+        }
+        // Requests of a batch are predicted while holding a permit each
+        if batching.max_size > concurrency {
+            problems.push(format!(
+                "batching.max_size: must be at most engine.concurrency ({concurrency})"
+            ));
+        }
+        if batching.input_field.is_empty() {
+            problems.push("batching.input_field: must not be empty".to_owned());
+        }
+    }
+    problems
+}
+
+/// A parsed config file. Unlike [Value], it deserializes 128-bit integers like any other value,
+/// so balances can be given as integers or as strings.
+struct ConfigValue(Value);
+
+impl<'de> Deserializer<'de> for ConfigValue {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Number(n) => n.deserialize_any(visitor).map_err(serde::de::Error::custom),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Array(values) => {
+                visitor.visit_seq(SeqDeserializer::new(values.into_iter().map(ConfigValue)))
+            },
+            Value::Object(map) => visitor.visit_map(MapDeserializer::new(
+                map.into_iter().map(|(key, value)| (key, ConfigValue(value))),
+            )),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Object(map) => visitor.visit_enum(MapAccessDeserializer::new(
+                MapDeserializer::new(map.into_iter().map(|(key, value)| (key, ConfigValue(value)))),
+            )),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, ValueError> for ConfigValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
     }
 }
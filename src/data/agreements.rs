@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    data::journal::Journal,
+    types::{Agreement, AgreementId, Result},
+};
+
+/// Update applied to a stored agreement.
+pub type AgreementUpdate = Box<dyn for<'a> FnOnce(&'a mut Agreement) + Send>;
+
+#[async_trait]
+pub trait AgreementRepo {
+    async fn list(&self) -> Vec<Agreement>;
+    async fn get(&self, id: AgreementId) -> Option<Agreement>;
+    async fn save(&self, agreement: Agreement);
+    /// Atomically update an agreement. Returns the updated agreement if it exists.
+    async fn update(&self, id: AgreementId, update: AgreementUpdate) -> Option<Agreement>;
+    async fn remove(&self, id: AgreementId);
+}
+
+#[derive(Default)]
+pub struct InMemoryAgreementRepo {
+    db: DashMap<AgreementId, Agreement>,
+}
+
+#[async_trait]
+impl AgreementRepo for InMemoryAgreementRepo {
+    async fn list(&self) -> Vec<Agreement> {
+        let mut agreements: Vec<_> = self.db.iter().map(|kv| kv.value().clone()).collect();
+        agreements.sort_by_key(|agreement| agreement.id);
+        agreements
+    }
+
+    async fn get(&self, id: AgreementId) -> Option<Agreement> {
+        self.db.get(&id).map(|kv| kv.value().clone())
+    }
+
+    async fn save(&self, agreement: Agreement) {
+        self.db.insert(agreement.id, agreement);
+    }
+
+    async fn update(&self, id: AgreementId, update: AgreementUpdate) -> Option<Agreement> {
+        let mut agreement = self.db.get_mut(&id)?;
+        update(agreement.value_mut());
+        Some(agreement.value().clone())
+    }
+
+    async fn remove(&self, id: AgreementId) {
+        self.db.remove(&id);
+    }
+}
+
+/// Change of an agreement recorded in the journal.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AgreementRecord {
+    Saved(Agreement),
+    /// Removals rewrite the journal instead, but journals written before may still hold these.
+    Removed(AgreementId),
+}
+
+/// Agreements persisted in a journal file. Every change is appended, and superseded records are
+/// dropped when the journal is loaded. The journal is rewritten once agreements are removed.
+pub struct FileAgreementRepo {
+    cache: InMemoryAgreementRepo,
+    journal: Journal,
+    /// Held exclusively while the journal is rewritten, so no change made meanwhile is lost.
+    rewriting: RwLock<()>,
+}
+
+impl FileAgreementRepo {
+    async fn append(&self, record: &AgreementRecord) {
+        if let Err(e) = self.journal.append(record).await {
+            tracing::error!("🚫 Failed to persist agreement: {e}");
+        }
+    }
+
+    /// Replace the journal with the current agreements.
+    async fn rewrite(&self) -> Result<()> {
+        let live: Vec<_> = self
+            .cache
+            .db
+            .iter()
+            .map(|kv| AgreementRecord::Saved(kv.value().clone()))
+            .collect();
+        self.journal.rewrite(&live).await
+    }
+}
+
+#[async_trait]
+impl AgreementRepo for FileAgreementRepo {
+    async fn list(&self) -> Vec<Agreement> {
+        self.cache.list().await
+    }
+
+    async fn get(&self, id: AgreementId) -> Option<Agreement> {
+        self.cache.get(id).await
+    }
+
+    async fn save(&self, agreement: Agreement) {
+        let _saving = self.rewriting.read().await;
+        self.append(&AgreementRecord::Saved(agreement.clone())).await;
+        self.cache.save(agreement).await;
+    }
+
+    async fn update(&self, id: AgreementId, update: AgreementUpdate) -> Option<Agreement> {
+        let _saving = self.rewriting.read().await;
+        let agreement = self.cache.update(id, update).await?;
+        self.append(&AgreementRecord::Saved(agreement.clone())).await;
+        Some(agreement)
+    }
+
+    async fn remove(&self, id: AgreementId) {
+        let _rewriting = self.rewriting.write().await;
+        if self.cache.db.remove(&id).is_some() {
+            if let Err(e) = self.rewrite().await {
+                tracing::error!("🚫 Failed to rewrite the agreements: {e}");
+            }
+        }
+    }
+}
+
+pub struct AgreementRepoFac;
+
+impl AgreementRepoFac {
+    pub fn in_memory() -> InMemoryAgreementRepo {
+        InMemoryAgreementRepo::default()
+    }
+
+    pub async fn file(path: &Path) -> Result<FileAgreementRepo> {
+        let (journal, records) = Journal::open::<AgreementRecord>(path).await?;
+        let cache = Self::in_memory();
+        for record in records {
+            match record {
+                AgreementRecord::Saved(agreement) => {
+                    cache.db.insert(agreement.id, agreement);
+                },
+                AgreementRecord::Removed(id) => {
+                    cache.db.remove(&id);
+                },
+            }
+        }
+        let repo = FileAgreementRepo { cache, journal, rewriting: RwLock::default() };
+        repo.rewrite().await?;
+        Ok(repo)
+    }
+}
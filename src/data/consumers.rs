@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::journal::Journal,
+    types::{ConsumerPolicy, ModelName, Result},
+};
+
+/// Consumer policies set through the API. They take precedence over the policies of the
+/// configuration, across reloads and restarts.
+#[async_trait]
+pub trait ConsumerRepo {
+    async fn get(&self, model: &ModelName) -> Option<ConsumerPolicy>;
+    async fn save(&self, model: ModelName, policy: ConsumerPolicy);
+    async fn remove(&self, model: &ModelName);
+}
+
+#[derive(Default)]
+pub struct InMemoryConsumerRepo {
+    db: DashMap<ModelName, ConsumerPolicy>,
+}
+
+#[async_trait]
+impl ConsumerRepo for InMemoryConsumerRepo {
+    async fn get(&self, model: &ModelName) -> Option<ConsumerPolicy> {
+        self.db.get(model).map(|kv| kv.value().clone())
+    }
+
+    async fn save(&self, model: ModelName, policy: ConsumerPolicy) {
+        self.db.insert(model, policy);
+    }
+
+    async fn remove(&self, model: &ModelName) {
+        self.db.remove(model);
+    }
+}
+
+/// Change of a consumer policy recorded in the journal.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ConsumerRecord {
+    Saved { model: ModelName, policy: ConsumerPolicy },
+    Removed(ModelName),
+}
+
+/// Consumer policies persisted in a journal file. Every change is appended, and superseded
+/// records are dropped when the journal is loaded.
+pub struct FileConsumerRepo {
+    cache: InMemoryConsumerRepo,
+    journal: Journal,
+}
+
+impl FileConsumerRepo {
+    async fn append(&self, record: &ConsumerRecord) {
+        if let Err(e) = self.journal.append(record).await {
+            tracing::error!("🚫 Failed to persist consumer policy: {e}");
+        }
+    }
+}
+
+#[async_trait]
+impl ConsumerRepo for FileConsumerRepo {
+    async fn get(&self, model: &ModelName) -> Option<ConsumerPolicy> {
+        self.cache.get(model).await
+    }
+
+    async fn save(&self, model: ModelName, policy: ConsumerPolicy) {
+        self.append(&ConsumerRecord::Saved { model: model.clone(), policy: policy.clone() })
+            .await;
+        self.cache.save(model, policy).await;
+    }
+
+    async fn remove(&self, model: &ModelName) {
+        self.append(&ConsumerRecord::Removed(model.clone())).await;
+        self.cache.remove(model).await;
+    }
+}
+
+pub struct ConsumerRepoFac;
+
+impl ConsumerRepoFac {
+    pub fn in_memory() -> InMemoryConsumerRepo {
+        InMemoryConsumerRepo::default()
+    }
+
+    pub async fn file(path: &Path) -> Result<FileConsumerRepo> {
+        let (journal, records) = Journal::open::<ConsumerRecord>(path).await?;
+        let cache = Self::in_memory();
+        for record in records {
+            match record {
+                ConsumerRecord::Saved { model, policy } => {
+                    cache.db.insert(model, policy);
+                },
+                ConsumerRecord::Removed(model) => {
+                    cache.db.remove(&model);
+                },
+            }
+        }
+        let live: Vec<_> = cache
+            .db
+            .iter()
+            .map(|kv| ConsumerRecord::Saved { model: kv.key().clone(), policy: kv.value().clone() })
+            .collect();
+        journal.rewrite(&live).await?;
+        Ok(FileConsumerRepo { cache, journal })
+    }
+}
@@ -0,0 +1,208 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use tokio::{sync::RwLock, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    data::journal::Journal,
+    types::{AgreementId, ModelId, RequestRecord, RequestStatus, Result},
+    utils::now_millis,
+};
+
+/// How often the history is pruned.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Filter of request records. Unset fields match any record.
+#[derive(Clone, Debug, Default)]
+pub struct HistoryFilter {
+    pub agreement_id: Option<AgreementId>,
+    pub model_id: Option<ModelId>,
+    pub status: Option<RequestStatus>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, record: &RequestRecord) -> bool {
+        self.agreement_id.is_none_or(|id| id == record.agreement_id)
+            && self.model_id.is_none_or(|id| id == record.model_id)
+            && self.status.is_none_or(|status| status == record.status)
+    }
+}
+
+/// Limits of the request history. The oldest records beyond them are pruned, except those of
+/// requests still being processed.
+#[derive(Clone, Copy, Debug)]
+pub struct Retention {
+    /// Records received longer ago are pruned.
+    pub max_age: Duration,
+    /// Number of records kept, besides those of requests being processed.
+    pub max_records: usize,
+}
+
+#[async_trait]
+pub trait HistoryRepo {
+    async fn get(&self, agreement_id: AgreementId, request_index: u32) -> Option<RequestRecord>;
+    /// Query records matching the filter, most recent first. Returns the total number of
+    /// matching records along with the requested page.
+    async fn query(
+        &self,
+        filter: &HistoryFilter,
+        offset: usize,
+        limit: usize,
+    ) -> (usize, Vec<RequestRecord>);
+    async fn save(&self, record: RequestRecord);
+    /// Remove the records beyond the retention limits. Returns the number of removed records.
+    async fn prune(&self, retention: &Retention, now: u64) -> usize;
+}
+
+#[derive(Default)]
+pub struct InMemoryHistoryRepo {
+    db: DashMap<(AgreementId, u32), RequestRecord>,
+}
+
+#[async_trait]
+impl HistoryRepo for InMemoryHistoryRepo {
+    async fn get(&self, agreement_id: AgreementId, request_index: u32) -> Option<RequestRecord> {
+        self.db.get(&(agreement_id, request_index)).map(|kv| kv.value().clone())
+    }
+
+    async fn query(
+        &self,
+        filter: &HistoryFilter,
+        offset: usize,
+        limit: usize,
+    ) -> (usize, Vec<RequestRecord>) {
+        let mut records: Vec<_> = self
+            .db
+            .iter()
+            .filter(|kv| filter.matches(kv.value()))
+            .map(|kv| kv.value().clone())
+            .collect();
+        records.sort_by(|a, b| {
+            b.received_at
+                .cmp(&a.received_at)
+                .then(b.agreement_id.cmp(&a.agreement_id))
+                .then(b.request_index.cmp(&a.request_index))
+        });
+        let total = records.len();
+        (total, records.into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn save(&self, record: RequestRecord) {
+        self.db.insert((record.agreement_id, record.request_index), record);
+    }
+
+    async fn prune(&self, retention: &Retention, now: u64) -> usize {
+        let max_age = u64::try_from(retention.max_age.as_millis()).unwrap_or(u64::MAX);
+        let cutoff = now.saturating_sub(max_age);
+        let mut finished: Vec<_> = self
+            .db
+            .iter()
+            .filter(|kv| kv.value().status != RequestStatus::Received)
+            .map(|kv| (kv.value().received_at, *kv.key()))
+            .collect();
+        // Most recent first, so the oldest records are beyond the limit
+        finished.sort_unstable_by(|a, b| b.cmp(a));
+        let expired: Vec<_> = finished
+            .into_iter()
+            .enumerate()
+            .filter(|(i, (received_at, _))| *i >= retention.max_records || *received_at < cutoff)
+            .map(|(_, (_, key))| key)
+            .collect();
+        for key in &expired {
+            self.db.remove(key);
+        }
+        expired.len()
+    }
+}
+
+/// History persisted in a journal file. Every update of a record is appended, the latest one
+/// wins when the journal is loaded. The journal is rewritten once records are pruned.
+pub struct FileHistoryRepo {
+    cache: InMemoryHistoryRepo,
+    journal: Journal,
+    /// Held exclusively while the journal is rewritten, so no record saved meanwhile is lost.
+    rewriting: RwLock<()>,
+}
+
+#[async_trait]
+impl HistoryRepo for FileHistoryRepo {
+    async fn get(&self, agreement_id: AgreementId, request_index: u32) -> Option<RequestRecord> {
+        self.cache.get(agreement_id, request_index).await
+    }
+
+    async fn query(
+        &self,
+        filter: &HistoryFilter,
+        offset: usize,
+        limit: usize,
+    ) -> (usize, Vec<RequestRecord>) {
+        self.cache.query(filter, offset, limit).await
+    }
+
+    async fn save(&self, record: RequestRecord) {
+        let _saving = self.rewriting.read().await;
+        if let Err(e) = self.journal.append(&record).await {
+            tracing::error!("🚫 Failed to persist request record: {e}");
+        }
+        self.cache.save(record).await;
+    }
+
+    async fn prune(&self, retention: &Retention, now: u64) -> usize {
+        let _rewriting = self.rewriting.write().await;
+        let pruned = self.cache.prune(retention, now).await;
+        if pruned > 0 {
+            let live: Vec<_> = self.cache.db.iter().map(|kv| kv.value().clone()).collect();
+            if let Err(e) = self.journal.rewrite(&live).await {
+                tracing::error!("🚫 Failed to rewrite the request history: {e}");
+            }
+        }
+        pruned
+    }
+}
+
+/// Prunes the history in the background, starting right away.
+pub struct HistoryPruner {
+    history_repo: Arc<dyn HistoryRepo + Send + Sync>,
+    retention: Retention,
+}
+
+impl HistoryPruner {
+    pub fn new(history_repo: Arc<dyn HistoryRepo + Send + Sync>, retention: Retention) -> Self {
+        Self { history_repo, retention }
+    }
+
+    pub async fn run(self, token: CancellationToken) -> Result<()> {
+        let mut poll = interval(PRUNE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = poll.tick() => {
+                    let pruned = self.history_repo.prune(&self.retention, now_millis()).await;
+                    if pruned > 0 {
+                        tracing::info!("🧹 Pruned {pruned} request records");
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct HistoryRepoFac;
+
+impl HistoryRepoFac {
+    pub fn in_memory() -> InMemoryHistoryRepo {
+        InMemoryHistoryRepo::default()
+    }
+
+    pub async fn file(path: &Path) -> Result<FileHistoryRepo> {
+        let (journal, records) = Journal::open::<RequestRecord>(path).await?;
+        let cache = Self::in_memory();
+        for record in records {
+            cache.db.insert((record.agreement_id, record.request_index), record);
+        }
+        Ok(FileHistoryRepo { cache, journal, rewriting: RwLock::default() })
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+use crate::types::Result;
+
+/// Append-only file of JSON records, one record per line.
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Open the journal at the given path, creating it if necessary, and read all of its
+    /// records. Lines that can't be parsed are skipped.
+    pub async fn open<T: DeserializeOwned>(path: &Path) -> Result<(Self, Vec<T>)> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let file = Self::open_file(path).await?;
+
+        let mut records = Vec::new();
+        let content = fs::read(path).await?;
+        for line in content.split(|byte| *byte == b'\n').filter(|line| !line.is_empty()) {
+            match serde_json::from_slice(line) {
+                Ok(record) => records.push(record),
+                Err(e) => tracing::warn!("⚠️ Skipping corrupted record in {}: {e}", path.display()),
+            }
+        }
+        Ok((Self { path: path.to_owned(), file: Mutex::new(file) }, records))
+    }
+
+    async fn open_file(path: &Path) -> Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(path)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Append a record to the journal.
+    pub async fn append<T: Serialize>(&self, record: &T) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await.map_err(Into::into)
+    }
+
+    /// Replace the records of the journal, e.g. to drop records that were superseded. The records
+    /// are written to a temporary file first, which then replaces the journal, so a failure
+    /// leaves the journal as it was.
+    pub async fn rewrite<'a, T, I>(&self, records: I) -> Result<()>
+    where
+        T: Serialize + 'a,
+        I: IntoIterator<Item = &'a T>,
+    {
+        let mut content = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut content, record)?;
+            content.push(b'\n');
+        }
+        let mut file = self.file.lock().await;
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        let mut tmp = File::create(&tmp_path).await?;
+        tmp.write_all(&content).await?;
+        tmp.sync_all().await?;
+        fs::rename(&tmp_path, &self.path).await?;
+        // Persist the rename itself. Directories can't be opened on every platform
+        if let Some(dir) = self.path.parent() {
+            if let Ok(dir) = File::open(dir).await {
+                let _ = dir.sync_all().await;
+            }
+        }
+        *file = Self::open_file(&self.path).await?;
+        Ok(())
+    }
+}
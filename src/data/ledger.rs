@@ -0,0 +1,141 @@
+use std::{collections::BTreeMap, path::Path, sync::RwLock};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    data::journal::Journal,
+    types::{Balance, LedgerEntry, LedgerEntryKind, Result},
+    utils::utc_date,
+};
+
+#[async_trait]
+pub trait LedgerRepo {
+    async fn record(&self, entry: LedgerEntry);
+    /// All entries in the order they were recorded.
+    async fn entries(&self) -> Vec<LedgerEntry>;
+}
+
+#[derive(Default)]
+pub struct InMemoryLedgerRepo {
+    db: RwLock<Vec<LedgerEntry>>,
+}
+
+#[async_trait]
+impl LedgerRepo for InMemoryLedgerRepo {
+    async fn record(&self, entry: LedgerEntry) {
+        self.db.write().expect("ledger lock should not be poisoned").push(entry);
+    }
+
+    async fn entries(&self) -> Vec<LedgerEntry> {
+        self.db.read().expect("ledger lock should not be poisoned").clone()
+    }
+}
+
+/// Ledger persisted in a journal file.
+pub struct FileLedgerRepo {
+    cache: InMemoryLedgerRepo,
+    journal: Journal,
+}
+
+#[async_trait]
+impl LedgerRepo for FileLedgerRepo {
+    async fn record(&self, entry: LedgerEntry) {
+        if let Err(e) = self.journal.append(&entry).await {
+            tracing::error!("🚫 Failed to persist ledger entry: {e}");
+        }
+        self.cache.record(entry).await;
+    }
+
+    async fn entries(&self) -> Vec<LedgerEntry> {
+        self.cache.entries().await
+    }
+}
+
+pub struct LedgerRepoFac;
+
+impl LedgerRepoFac {
+    pub fn in_memory() -> InMemoryLedgerRepo {
+        InMemoryLedgerRepo::default()
+    }
+
+    pub async fn file(path: &Path) -> Result<FileLedgerRepo> {
+        let (journal, entries) = Journal::open::<LedgerEntry>(path).await?;
+        let cache = InMemoryLedgerRepo { db: RwLock::new(entries) };
+        Ok(FileLedgerRepo { cache, journal })
+    }
+}
+
+/// Earnings and fees summed over a group of ledger entries.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EarningsSummary {
+    /// The day (`YYYY-MM-DD`, UTC) or the model ID the entries are grouped by.
+    pub key: String,
+    /// Number of answered requests.
+    pub requests: u64,
+    #[schema(value_type = u128)]
+    pub earned: Balance,
+    #[schema(value_type = u128)]
+    pub fees: Balance,
+    /// Earnings minus fees.
+    #[schema(value_type = i128)]
+    pub net: i128,
+}
+
+/// Sum up ledger entries grouped by the given key, ordered by the key.
+pub fn summarize<F>(entries: &[LedgerEntry], key: F) -> Vec<EarningsSummary>
+where
+    F: Fn(&LedgerEntry) -> String,
+{
+    let mut groups = BTreeMap::<String, EarningsSummary>::new();
+    for entry in entries {
+        let key = key(entry);
+        let summary = groups
+            .entry(key.clone())
+            .or_insert_with(|| EarningsSummary { key, ..Default::default() });
+        match entry.kind {
+            LedgerEntryKind::Earning => {
+                summary.requests += 1;
+                summary.earned += entry.amount;
+            },
+            LedgerEntryKind::BidFee | LedgerEntryKind::ResponseFee => summary.fees += entry.amount,
+        }
+    }
+    groups
+        .into_values()
+        .map(|mut summary| {
+            summary.net = summary.earned as i128 - summary.fees as i128;
+            summary
+        })
+        .collect()
+}
+
+/// Sum up ledger entries per day.
+pub fn daily(entries: &[LedgerEntry]) -> Vec<EarningsSummary> {
+    summarize(entries, |entry| utc_date(entry.timestamp))
+}
+
+/// Sum up ledger entries per model.
+pub fn per_model(entries: &[LedgerEntry]) -> Vec<EarningsSummary> {
+    summarize(entries, |entry| format!("{:?}", entry.model_id))
+}
+
+/// Render ledger entries as CSV.
+pub fn to_csv(entries: &[LedgerEntry]) -> String {
+    let mut csv = String::from("timestamp,date,kind,agreement_id,model_id,amount,tx_hash\n");
+    for entry in entries {
+        let tx_hash = entry.tx_hash.map(|hash| format!("{hash:?}")).unwrap_or_default();
+        csv.push_str(&format!(
+            "{},{},{},{},{:?},{},{}\n",
+            entry.timestamp,
+            utc_date(entry.timestamp),
+            entry.kind,
+            entry.agreement_id,
+            entry.model_id,
+            entry.amount,
+            tx_hash
+        ));
+    }
+    csv
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::RwLock,
+};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::types::{AccountId, Balance, CompetitorBid, ModelId, OrderId};
+
+/// Number of competing bids kept per model.
+pub const BID_WINDOW: usize = 100;
+/// Number of recent orders whose model is remembered.
+const ORDER_WINDOW: usize = 10_000;
+
+/// Rolling history of the bids competing providers place on orders.
+#[async_trait]
+pub trait MarketRepo {
+    /// Remember the model of an order, so bids on the order can be attributed to the model.
+    async fn record_order(&self, order_id: OrderId, model_id: ModelId);
+    /// Record a competing bid. Bids on unknown orders are ignored.
+    async fn record_bid(
+        &self,
+        order_id: OrderId,
+        provider: AccountId,
+        price_per_request: Balance,
+        timestamp: u64,
+    );
+    /// Mark the bid of a provider on an order as won.
+    async fn record_acceptance(&self, order_id: OrderId, provider: &AccountId);
+    /// Recent competing bids on a model, oldest first.
+    async fn bids(&self, model_id: &ModelId) -> Vec<CompetitorBid>;
+    /// Statistics of the recent competing bids on a model.
+    async fn stats(&self, model_id: &ModelId) -> Option<MarketStats>;
+    /// Statistics of all models bid on, ordered by model ID.
+    async fn list(&self) -> Vec<MarketStats>;
+}
+
+#[derive(Default)]
+struct Market {
+    orders: HashMap<OrderId, ModelId>,
+    /// Orders in the order they were created, to forget the oldest ones.
+    order_queue: VecDeque<OrderId>,
+    bids: HashMap<ModelId, VecDeque<CompetitorBid>>,
+}
+
+#[derive(Default)]
+pub struct InMemoryMarketRepo {
+    db: RwLock<Market>,
+}
+
+impl InMemoryMarketRepo {
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Market> {
+        self.db.read().expect("market lock should not be poisoned")
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Market> {
+        self.db.write().expect("market lock should not be poisoned")
+    }
+}
+
+#[async_trait]
+impl MarketRepo for InMemoryMarketRepo {
+    async fn record_order(&self, order_id: OrderId, model_id: ModelId) {
+        let mut db = self.write();
+        if db.orders.insert(order_id, model_id).is_none() {
+            db.order_queue.push_back(order_id);
+        }
+        while db.order_queue.len() > ORDER_WINDOW {
+            if let Some(oldest) = db.order_queue.pop_front() {
+                db.orders.remove(&oldest);
+            }
+        }
+    }
+
+    async fn record_bid(
+        &self,
+        order_id: OrderId,
+        provider: AccountId,
+        price_per_request: Balance,
+        timestamp: u64,
+    ) {
+        let mut db = self.write();
+        let Some(&model_id) = db.orders.get(&order_id) else {
+            return;
+        };
+        let bids = db.bids.entry(model_id).or_default();
+        bids.push_back(CompetitorBid {
+            order_id,
+            model_id,
+            provider,
+            price_per_request,
+            timestamp,
+            won: false,
+        });
+        if bids.len() > BID_WINDOW {
+            bids.pop_front();
+        }
+    }
+
+    async fn record_acceptance(&self, order_id: OrderId, provider: &AccountId) {
+        let mut db = self.write();
+        let Some(model_id) = db.orders.get(&order_id).copied() else {
+            return;
+        };
+        let bids = db.bids.get_mut(&model_id).into_iter().flatten();
+        for bid in bids.filter(|bid| bid.order_id == order_id && bid.provider == *provider) {
+            bid.won = true;
+        }
+    }
+
+    async fn bids(&self, model_id: &ModelId) -> Vec<CompetitorBid> {
+        self.read()
+            .bids
+            .get(model_id)
+            .map(|bids| bids.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    async fn stats(&self, model_id: &ModelId) -> Option<MarketStats> {
+        let db = self.read();
+        summarize(*model_id, db.bids.get(model_id)?.iter())
+    }
+
+    async fn list(&self) -> Vec<MarketStats> {
+        let db = self.read();
+        let mut stats: Vec<_> =
+            db.bids.iter().filter_map(|(id, bids)| summarize(*id, bids.iter())).collect();
+        stats.sort_by_key(|stats| stats.model_id);
+        stats
+    }
+}
+
+pub struct MarketRepoFac;
+
+impl MarketRepoFac {
+    pub fn in_memory() -> InMemoryMarketRepo {
+        InMemoryMarketRepo::default()
+    }
+}
+
+/// Prices of the recent competing bids on a model.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct MarketStats {
+    #[schema(value_type = String)]
+    pub model_id: ModelId,
+    /// Number of competing bids.
+    pub bids: usize,
+    /// Number of competing bids that won.
+    pub won: usize,
+    #[schema(value_type = u128)]
+    pub lowest: Balance,
+    #[schema(value_type = u128)]
+    pub highest: Balance,
+    #[schema(value_type = u128)]
+    pub average: Balance,
+    #[schema(value_type = Option<u128>)]
+    pub lowest_winning: Option<Balance>,
+    /// Price of the most recent winning bid.
+    #[schema(value_type = Option<u128>)]
+    pub last_winning: Option<Balance>,
+}
+
+impl MarketStats {
+    /// The price to compete with: the lowest winning bid, or the lowest bid if none won.
+    pub fn reference_price(&self) -> Balance {
+        self.lowest_winning.unwrap_or(self.lowest)
+    }
+}
+
+/// Summarize the bids on a model. Returns `None` if there are none.
+pub fn summarize<'a, I>(model_id: ModelId, bids: I) -> Option<MarketStats>
+where
+    I: IntoIterator<Item = &'a CompetitorBid>,
+{
+    let mut stats: Option<MarketStats> = None;
+    let mut total: Balance = 0;
+    for bid in bids {
+        let price = bid.price_per_request;
+        total = total.saturating_add(price);
+        let stats = stats.get_or_insert(MarketStats {
+            model_id,
+            bids: 0,
+            won: 0,
+            lowest: price,
+            highest: price,
+            average: 0,
+            lowest_winning: None,
+            last_winning: None,
+        });
+        stats.bids += 1;
+        stats.lowest = stats.lowest.min(price);
+        stats.highest = stats.highest.max(price);
+        if bid.won {
+            stats.won += 1;
+            stats.lowest_winning = Some(stats.lowest_winning.map_or(price, |p| p.min(price)));
+            stats.last_winning = Some(price);
+        }
+    }
+    stats.map(|mut stats| {
+        // The total saturates on extreme prices, keep the average within the observed range
+        stats.average = (total / stats.bids as Balance).clamp(stats.lowest, stats.highest);
+        stats
+    })
+}
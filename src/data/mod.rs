@@ -0,0 +1,89 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::types::{Hasher, Model, ModelId, ModelName};
+use async_trait::async_trait;
+use subxt::config::Hasher as HasherT;
+
+pub use agreements::{AgreementRepo, AgreementRepoFac};
+pub use consumers::{ConsumerRepo, ConsumerRepoFac};
+pub use history::{HistoryFilter, HistoryPruner, HistoryRepo, HistoryRepoFac, Retention};
+pub use ledger::{LedgerRepo, LedgerRepoFac};
+pub use market::{MarketRepo, MarketRepoFac};
+pub use queue::{WorkQueue, WorkQueueFac};
+
+pub mod agreements;
+pub mod consumers;
+pub mod history;
+pub mod journal;
+pub mod ledger;
+pub mod market;
+pub mod queue;
+
+#[async_trait]
+pub trait ModelRepo {
+    async fn list(&self) -> Vec<Model>;
+    async fn contains(&self, name: &ModelName) -> bool;
+    async fn get_by_model_id(&self, id: &ModelId) -> Option<Model>;
+    async fn save(&self, model: Model);
+    async fn remove(&self, name: &ModelName);
+    /// Save and remove models in one step. Readers see either none or all of the changes.
+    async fn apply(&self, save: Vec<Model>, remove: Vec<ModelName>);
+}
+
+#[derive(Default)]
+pub struct InMemoryModelRepo {
+    db: RwLock<HashMap<ModelId, Model>>,
+}
+
+impl InMemoryModelRepo {
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<ModelId, Model>> {
+        self.db.read().expect("model lock should not be poisoned")
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<ModelId, Model>> {
+        self.db.write().expect("model lock should not be poisoned")
+    }
+}
+
+#[async_trait]
+impl ModelRepo for InMemoryModelRepo {
+    async fn list(&self) -> Vec<Model> {
+        self.read().values().cloned().collect()
+    }
+
+    async fn contains(&self, name: &ModelName) -> bool {
+        let id = Hasher::hash(name.as_bytes());
+        self.read().contains_key(&id)
+    }
+
+    async fn get_by_model_id(&self, id: &ModelId) -> Option<Model> {
+        self.read().get(id).cloned()
+    }
+
+    async fn save(&self, model: Model) {
+        self.write().insert(model.id, model);
+    }
+
+    async fn remove(&self, name: &ModelName) {
+        let id = Hasher::hash(name.as_bytes());
+        self.write().remove(&id);
+    }
+
+    async fn apply(&self, save: Vec<Model>, remove: Vec<ModelName>) {
+        let mut db = self.write();
+        for name in remove {
+            db.remove(&Hasher::hash(name.as_bytes()));
+        }
+        for model in save {
+            db.insert(model.id, model);
+        }
+    }
+}
+
+pub struct ModelRepoFac;
+
+impl ModelRepoFac {
+    pub fn in_memory() -> InMemoryModelRepo {
+        InMemoryModelRepo::default()
+    }
+}
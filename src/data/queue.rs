@@ -0,0 +1,199 @@
+use std::{
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use async_trait::async_trait;
+use dashmap::{mapref::entry::Entry, DashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::journal::Journal,
+    types::{AgreementId, DeadLetter, DeadLetterId, Result, WorkItem},
+};
+
+/// Key of a queued request: the agreement ID and the request index.
+pub type WorkKey = (AgreementId, u32);
+
+/// Requests being processed. Each request is queued once, and stays queued until it is
+/// finished, so processing can resume after a restart. Requests that failed are kept as dead
+/// letters until they are retried or discarded.
+#[async_trait]
+pub trait WorkQueue {
+    /// Queue a request. Returns `false` if it is queued already.
+    async fn push(&self, item: WorkItem) -> bool;
+    /// Record the progress of a queued request.
+    async fn update(&self, item: WorkItem);
+    /// Remove a finished request.
+    async fn finish(&self, key: WorkKey);
+    /// All queued requests, in the order they were received.
+    async fn list(&self) -> Vec<WorkItem>;
+    /// Keep a failed request as a dead letter. Returns the ID of the dead letter.
+    async fn bury(&self, item: WorkItem, reason: String, failed_at: u64) -> DeadLetterId;
+    /// All dead letters, in the order they failed.
+    async fn dead_letters(&self) -> Vec<DeadLetter>;
+    /// Remove a dead letter, returning it if it exists.
+    async fn remove_dead_letter(&self, id: DeadLetterId) -> Option<DeadLetter>;
+}
+
+#[derive(Default)]
+pub struct InMemoryWorkQueue {
+    db: DashMap<WorkKey, WorkItem>,
+    dead: DashMap<DeadLetterId, DeadLetter>,
+    next_id: AtomicU64,
+}
+
+impl InMemoryWorkQueue {
+    fn insert_dead_letter(&self, letter: DeadLetter) {
+        self.next_id.fetch_max(letter.id + 1, Ordering::SeqCst);
+        self.dead.insert(letter.id, letter);
+    }
+}
+
+#[async_trait]
+impl WorkQueue for InMemoryWorkQueue {
+    async fn push(&self, item: WorkItem) -> bool {
+        match self.db.entry(item.key()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(item);
+                true
+            },
+        }
+    }
+
+    async fn update(&self, item: WorkItem) {
+        self.db.insert(item.key(), item);
+    }
+
+    async fn finish(&self, key: WorkKey) {
+        self.db.remove(&key);
+    }
+
+    async fn list(&self) -> Vec<WorkItem> {
+        let mut items: Vec<_> = self.db.iter().map(|kv| kv.value().clone()).collect();
+        items.sort_by_key(|item| (item.received_at, item.key()));
+        items
+    }
+
+    async fn bury(&self, item: WorkItem, reason: String, failed_at: u64) -> DeadLetterId {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.dead.insert(id, DeadLetter { id, item, reason, failed_at });
+        id
+    }
+
+    async fn dead_letters(&self) -> Vec<DeadLetter> {
+        let mut letters: Vec<_> = self.dead.iter().map(|kv| kv.value().clone()).collect();
+        letters.sort_by_key(|letter| letter.id);
+        letters
+    }
+
+    async fn remove_dead_letter(&self, id: DeadLetterId) -> Option<DeadLetter> {
+        self.dead.remove(&id).map(|(_, letter)| letter)
+    }
+}
+
+/// Change of a queued request recorded in the journal.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WorkRecord {
+    Saved(WorkItem),
+    Finished(WorkKey),
+    Buried(DeadLetter),
+    Unburied(DeadLetterId),
+}
+
+/// Work queue persisted in a journal file. Every change is appended, and finished requests and
+/// removed dead letters are dropped when the journal is loaded.
+pub struct FileWorkQueue {
+    cache: InMemoryWorkQueue,
+    journal: Journal,
+}
+
+impl FileWorkQueue {
+    async fn append(&self, record: &WorkRecord) {
+        if let Err(e) = self.journal.append(record).await {
+            tracing::error!("🚫 Failed to persist work queue: {e}");
+        }
+    }
+}
+
+#[async_trait]
+impl WorkQueue for FileWorkQueue {
+    async fn push(&self, item: WorkItem) -> bool {
+        let record = WorkRecord::Saved(item.clone());
+        let pushed = self.cache.push(item).await;
+        if pushed {
+            self.append(&record).await;
+        }
+        pushed
+    }
+
+    async fn update(&self, item: WorkItem) {
+        self.append(&WorkRecord::Saved(item.clone())).await;
+        self.cache.update(item).await;
+    }
+
+    async fn finish(&self, key: WorkKey) {
+        self.append(&WorkRecord::Finished(key)).await;
+        self.cache.finish(key).await;
+    }
+
+    async fn list(&self) -> Vec<WorkItem> {
+        self.cache.list().await
+    }
+
+    async fn bury(&self, item: WorkItem, reason: String, failed_at: u64) -> DeadLetterId {
+        let id = self.cache.bury(item, reason, failed_at).await;
+        let letter = self.cache.dead.get(&id).map(|letter| letter.clone());
+        if let Some(letter) = letter {
+            self.append(&WorkRecord::Buried(letter)).await;
+        }
+        id
+    }
+
+    async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.cache.dead_letters().await
+    }
+
+    async fn remove_dead_letter(&self, id: DeadLetterId) -> Option<DeadLetter> {
+        let letter = self.cache.remove_dead_letter(id).await?;
+        self.append(&WorkRecord::Unburied(id)).await;
+        Some(letter)
+    }
+}
+
+pub struct WorkQueueFac;
+
+impl WorkQueueFac {
+    pub fn in_memory() -> InMemoryWorkQueue {
+        InMemoryWorkQueue::default()
+    }
+
+    pub async fn file(path: &Path) -> Result<FileWorkQueue> {
+        let (journal, records) = Journal::open::<WorkRecord>(path).await?;
+        let cache = Self::in_memory();
+        for record in records {
+            match record {
+                WorkRecord::Saved(item) => {
+                    cache.db.insert(item.key(), item);
+                },
+                WorkRecord::Finished(key) => {
+                    cache.db.remove(&key);
+                },
+                WorkRecord::Buried(letter) => cache.insert_dead_letter(letter),
+                WorkRecord::Unburied(id) => {
+                    cache.dead.remove(&id);
+                },
+            }
+        }
+        let live: Vec<_> = cache
+            .db
+            .iter()
+            .map(|kv| WorkRecord::Saved(kv.value().clone()))
+            .chain(cache.dead.iter().map(|kv| WorkRecord::Buried(kv.value().clone())))
+            .collect();
+        journal.rewrite(&live).await?;
+        Ok(FileWorkQueue { cache, journal })
+    }
+}
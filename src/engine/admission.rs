@@ -0,0 +1,117 @@
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use crate::{
+    balancer::ReplicaPool,
+    data::AgreementRepo,
+    protocol::StateReader,
+    types::{AccountId, AgreementState, Model, ModelId, OrderId},
+    utils::now_millis,
+};
+
+/// Bids not accepted within this time no longer reserve capacity, in milliseconds.
+const PENDING_BID_TTL: u64 = 60 * 60 * 1000;
+
+/// Whether another order of a model can be taken on.
+#[derive(Debug, PartialEq)]
+pub enum Admission {
+    /// The order can be bid on. The consumer is known if the order had to be looked up.
+    Admitted { consumer: Option<AccountId> },
+    /// None of the replicas of the model are healthy.
+    NoHealthyReplicas,
+    /// The order does not exist on chain.
+    OrderNotFound,
+    /// The order could not be read from the chain.
+    OrderUnavailable { reason: String },
+    /// The consumer of the order is not served by the model.
+    ConsumerRefused { consumer: AccountId },
+    /// The order would exceed the capacity of the model.
+    OverCapacity { committed: u32, requested: u32, capacity: u32 },
+}
+
+/// Bid placed on an order and not accepted yet.
+struct PendingBid {
+    model_id: ModelId,
+    requests: u32,
+    placed_at: u64,
+}
+
+/// Decides which orders to bid on, so that no more agreements are won than the replicas of a
+/// model can serve. Capacity is committed by the requests outstanding on active agreements and
+/// reserved by bids not accepted yet.
+pub struct AdmissionControl {
+    replica_pool: Arc<ReplicaPool>,
+    agreement_repo: Arc<dyn AgreementRepo + Send + Sync>,
+    state_reader: Arc<dyn StateReader + Send + Sync>,
+    pending: Mutex<HashMap<OrderId, PendingBid>>,
+}
+
+impl AdmissionControl {
+    pub fn new(
+        replica_pool: Arc<ReplicaPool>,
+        agreement_repo: Arc<dyn AgreementRepo + Send + Sync>,
+        state_reader: Arc<dyn StateReader + Send + Sync>,
+    ) -> Self {
+        Self { replica_pool, agreement_repo, state_reader, pending: Mutex::default() }
+    }
+
+    /// Number of requests committed to on the model, by active agreements and pending bids.
+    pub async fn committed(&self, model_id: &ModelId) -> u32 {
+        let agreements: u32 = self
+            .agreement_repo
+            .list()
+            .await
+            .iter()
+            .filter(|a| a.state == AgreementState::Active && a.details.model_id == *model_id)
+            .map(|a| a.outstanding())
+            .sum();
+        let now = now_millis();
+        let mut pending = self.pending.lock().expect("pending bids lock should not be poisoned");
+        pending.retain(|_, bid| now.saturating_sub(bid.placed_at) < PENDING_BID_TTL);
+        let reserved: u32 = pending
+            .values()
+            .filter(|bid| bid.model_id == *model_id)
+            .map(|bid| bid.requests)
+            .sum();
+        agreements.saturating_add(reserved)
+    }
+
+    /// Check whether an order of the model can be taken on. Admitted orders reserve capacity
+    /// until [`Self::release`] is called.
+    pub async fn admit(&self, model: &Model, order_id: OrderId) -> Admission {
+        if self.replica_pool.capacity(model) == 0 {
+            return Admission::NoHealthyReplicas;
+        }
+        if model.details.capacity.is_none() && model.details.consumers.is_empty() {
+            return Admission::Admitted { consumer: None };
+        }
+        let order = match self.state_reader.get_order(order_id).await {
+            Ok(Some(order)) => order,
+            Ok(None) => return Admission::OrderNotFound,
+            Err(e) => return Admission::OrderUnavailable { reason: e.to_string() },
+        };
+        if !model.details.consumers.allows(&order.consumer) {
+            return Admission::ConsumerRefused { consumer: order.consumer };
+        }
+        let Some(capacity) = model.details.capacity else {
+            return Admission::Admitted { consumer: Some(order.consumer) };
+        };
+        let requested = order.requests_total;
+        let committed = self.committed(&model.id).await;
+        if committed.saturating_add(requested) > capacity {
+            return Admission::OverCapacity { committed, requested, capacity };
+        }
+        self.pending.lock().expect("pending bids lock should not be poisoned").insert(
+            order_id,
+            PendingBid { model_id: model.id, requests: requested, placed_at: now_millis() },
+        );
+        Admission::Admitted { consumer: Some(order.consumer) }
+    }
+
+    /// Stop reserving capacity for an order, once a bid on it was accepted or failed.
+    pub fn release(&self, order_id: OrderId) {
+        self.pending
+            .lock()
+            .expect("pending bids lock should not be poisoned")
+            .remove(&order_id);
+    }
+}
@@ -1,19 +1,25 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio::sync::{mpsc::Receiver, watch};
 
 use crate::{
-    data::ModelRepo,
-    engine::Engine,
+    config::{BidStrategy, BiddingConfig},
+    data::{market::MarketStats, LedgerRepo, MarketRepo, ModelRepo},
+    engine::{Admission, AdmissionControl, Engine},
     protocol::{ChainEvent, TxSubmitter},
-    types::{stdResult, Result},
+    types::{Balance, LedgerEntry, LedgerEntryKind, Result},
+    utils::now_millis,
 };
 
 pub struct BidEngine {
     chain_rx: Receiver<ChainEvent>,
     tx_submitter: Arc<dyn TxSubmitter + Send + Sync>,
     model_repo: Arc<dyn ModelRepo + Send + Sync>,
+    admission: AdmissionControl,
+    ledger_repo: Arc<dyn LedgerRepo + Send + Sync>,
+    market_repo: Arc<dyn MarketRepo + Send + Sync>,
+    config: watch::Receiver<BiddingConfig>,
 }
 
 impl BidEngine {
@@ -21,32 +27,131 @@ impl BidEngine {
         chain_rx: Receiver<ChainEvent>,
         tx_submitter: Arc<dyn TxSubmitter + Send + Sync>,
         model_repo: Arc<dyn ModelRepo + Send + Sync>,
+        admission: AdmissionControl,
+        ledger_repo: Arc<dyn LedgerRepo + Send + Sync>,
+        market_repo: Arc<dyn MarketRepo + Send + Sync>,
+        config: watch::Receiver<BiddingConfig>,
     ) -> Self {
         tracing::info!("🚀 Starting bid engine");
-        Self { chain_rx, model_repo, tx_submitter }
+        if !config.borrow().enabled {
+            tracing::warn!("⚠️ Bidding is disabled");
+        }
+        Self { chain_rx, model_repo, tx_submitter, admission, ledger_repo, market_repo, config }
     }
 }
 
 #[async_trait]
 impl Engine for BidEngine {
     async fn process_chain_event(&mut self, event: ChainEvent) -> Result<()> {
-        if let ChainEvent::OrderCreated { order_id, model_id } = event {
-            if let Some(model) = self.model_repo.get_by_model_id(&model_id).await {
-                tracing::info!(
-                    "💸 Bidding {} on order {} for model {}",
-                    model.details.price_per_request,
-                    order_id,
-                    model.id
-                );
-
-                self.tx_submitter.bid_create(order_id, model.details.price_per_request).await?;
-            }
+        match event {
+            ChainEvent::OrderCreated { order_id, model_id } => {
+                let strategy = match &*self.config.borrow() {
+                    config if config.enabled => config.strategy.clone(),
+                    _ => return Ok(()),
+                };
+                if let Some(model) = self.model_repo.get_by_model_id(&model_id).await {
+                    let consumer = match self.admission.admit(&model, order_id).await {
+                        Admission::Admitted { consumer } => consumer,
+                        Admission::NoHealthyReplicas => {
+                            tracing::warn!(
+                                "⚠️ Skipping order {order_id}. No healthy replicas of model {}",
+                                model.id
+                            );
+                            return Ok(());
+                        },
+                        Admission::OrderNotFound => {
+                            tracing::warn!("⚠️ Skipping order {order_id}. Order not found");
+                            return Ok(());
+                        },
+                        Admission::OrderUnavailable { reason } => {
+                            tracing::warn!(
+                                "⚠️ Skipping order {order_id}. Failed to read it: {reason}"
+                            );
+                            return Ok(());
+                        },
+                        Admission::ConsumerRefused { consumer } => {
+                            tracing::info!(
+                                "⛔ Skipping order {order_id}. Consumer {consumer} is not served \
+                                 model {}",
+                                model.id
+                            );
+                            return Ok(());
+                        },
+                        Admission::OverCapacity { committed, requested, capacity } => {
+                            tracing::warn!(
+                                "⚠️ Skipping order {order_id}. {requested} more requests on top of \
+                                 {committed} exceed the capacity {capacity} of model {}",
+                                model.id
+                            );
+                            return Ok(());
+                        },
+                    };
+
+                    // Consumers with prices of their own are bid their price, whatever the market
+                    let own_price = consumer
+                        .as_ref()
+                        .and_then(|consumer| model.details.consumers.price(consumer));
+                    let price = match own_price {
+                        Some(price) => price,
+                        None => {
+                            let market = self.market_repo.stats(&model.id).await;
+                            bid_price(&strategy, model.details.price_per_request, market.as_ref())
+                        },
+                    };
+                    tracing::info!("💸 Bidding {price} on order {order_id} for model {}", model.id);
+
+                    let receipt = match self.tx_submitter.bid_create(order_id, price).await {
+                        Ok(receipt) => receipt,
+                        Err(e) => {
+                            self.admission.release(order_id);
+                            return Err(e);
+                        },
+                    };
+                    if let Some(fee) = receipt.fee {
+                        self.ledger_repo
+                            .record(LedgerEntry {
+                                timestamp: now_millis(),
+                                kind: LedgerEntryKind::BidFee,
+                                agreement_id: order_id,
+                                model_id: model.id,
+                                amount: fee,
+                                tx_hash: Some(receipt.hash),
+                            })
+                            .await;
+                    }
+                }
+            },
+            ChainEvent::BidCreated { order_id, price_per_request, own: true, .. } => {
+                tracing::info!("📌 Bid {price_per_request} on order {order_id} placed");
+            },
+            ChainEvent::BidAccepted { order_id, .. } => {
+                // Once accepted, the capacity is committed by the agreement, if it is ours
+                self.admission.release(order_id);
+            },
+            _ => {},
         }
 
         Ok(())
     }
 
-    async fn try_recv(&mut self) -> stdResult<ChainEvent, RecvError> {
+    async fn recv(&mut self) -> Option<ChainEvent> {
         self.chain_rx.recv().await
     }
 }
+
+/// Price to bid on an order for a model with the given price, following the strategy.
+pub fn bid_price(strategy: &BidStrategy, price: Balance, market: Option<&MarketStats>) -> Balance {
+    let Some(market) = market else {
+        return price;
+    };
+    let (target, floor_percent) = match *strategy {
+        BidStrategy::Fixed => return price,
+        BidStrategy::Undercut { step, floor_percent } => {
+            (market.reference_price().saturating_sub(step), floor_percent)
+        },
+        BidStrategy::Match { floor_percent } => (market.reference_price(), floor_percent),
+    };
+    let percent = Balance::from(floor_percent.min(100));
+    let floor = price / 100 * percent + price % 100 * percent / 100;
+    target.clamp(floor, price)
+}
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    engine::Engine,
+    events::{EventHub, EventKind},
+    protocol::ChainEvent,
+    types::Result,
+};
+
+/// Publishes the chain events received, and the bids of this provider, as lifecycle events.
+pub struct EventRelay {
+    chain_rx: Receiver<ChainEvent>,
+    events: EventHub,
+}
+
+impl EventRelay {
+    pub fn new(chain_rx: Receiver<ChainEvent>, events: EventHub) -> Self {
+        tracing::info!("🚀 Starting event relay");
+        Self { chain_rx, events }
+    }
+}
+
+#[async_trait]
+impl Engine for EventRelay {
+    async fn process_chain_event(&mut self, event: ChainEvent) -> Result<()> {
+        self.events.publish(EventKind::ChainEventReceived { name: (&event).into() });
+        match event {
+            ChainEvent::BidCreated { order_id, price_per_request, own: true, .. } => {
+                self.events.publish(EventKind::BidPlaced { order_id, price: price_per_request });
+            },
+            ChainEvent::BidAccepted { order_id, own: true, .. } => {
+                self.events.publish(EventKind::BidAccepted { order_id });
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Option<ChainEvent> {
+        self.chain_rx.recv().await
+    }
+}
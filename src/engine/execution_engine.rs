@@ -1,37 +1,187 @@
 use async_trait::async_trait;
+use dashmap::DashMap;
 use serde_json::Value;
-use std::{collections::HashMap, sync::Arc};
-use tokio::sync::broadcast::{error::RecvError, Receiver};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{
+        mpsc::{Receiver, Sender},
+        watch, OwnedSemaphorePermit, Semaphore,
+    },
+    time::{interval, Interval, MissedTickBehavior},
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use crate::{
-    cog::{Connector, PredictionResponse},
-    data::ModelRepo,
-    engine::Engine,
-    protocol::{ChainEvent, Protocol},
+    backend::{Prediction, Status},
+    balancer::ReplicaPool,
+    batcher::Batcher,
+    cache::ResultCache,
+    config::EngineConfig,
+    data::{AgreementRepo, HistoryRepo, LedgerRepo, ModelRepo, WorkQueue},
+    engine::{
+        scheduler::{Scheduled, Scheduler},
+        Engine, Error,
+    },
+    events::{EventHub, EventKind},
+    policy::{ContentFilter, Rejection, Stage},
+    protocol::{ChainEvent, Error as ProtocolError, Protocol},
     retry_on_err_or_none,
-    types::{stdResult, AgreementId, ContentId, ExecutionResult, ModelId, Result},
+    types::{
+        Agreement, AgreementId, AgreementState, ContentId, DeadLetter, DeadLetterId,
+        ExecutionResult, LedgerEntry, LedgerEntryKind, Model, RequestRecord, RequestStatus, Result,
+        TxReceipt, WorkItem, WorkStage,
+    },
+    utils::now_millis,
 };
 
 const FIVE_TIMES: usize = 5;
+/// How often agreements are checked for expiry and eviction.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// How long finished agreements stay listed after their response window closed, in milliseconds.
+const FINISHED_RETENTION: u64 = 60 * 60 * 1000;
 
 pub struct ExecutionEngine {
     chain_rx: Receiver<ChainEvent>,
-    protocol_client: Arc<dyn Protocol + Send + Sync>,
+    /// Dead letters to retry, see [RequestProcessor::with_retries].
+    retry_rx: Receiver<(Model, WorkItem)>,
+    processor: RequestProcessor,
     model_repo: Arc<dyn ModelRepo + Send + Sync>,
-    agreements: HashMap<AgreementId, ModelId>,
+    settings: watch::Receiver<EngineConfig>,
+    /// Limits the number of requests processed concurrently.
+    permits: Arc<Semaphore>,
+    concurrency: usize,
+    /// Requests waiting for a permit.
+    scheduler: Arc<Scheduler>,
+    /// Ticks when agreements are due to be checked for expiry.
+    sweep_interval: Interval,
 }
 
 impl ExecutionEngine {
     pub fn new(
         chain_rx: Receiver<ChainEvent>,
-        protocol_client: Arc<dyn Protocol + Send + Sync>,
+        retry_rx: Receiver<(Model, WorkItem)>,
+        processor: RequestProcessor,
         model_repo: Arc<dyn ModelRepo + Send + Sync>,
+        mut settings: watch::Receiver<EngineConfig>,
     ) -> Self {
         // TODO. Initialize agreements from the chain
-        let agreements = HashMap::new();
-
         tracing::info!("🚀 Starting execution engine");
-        Self { chain_rx, protocol_client, model_repo, agreements }
+        let concurrency = settings.borrow_and_update().concurrency;
+        let permits = Arc::new(Semaphore::new(concurrency));
+        let mut sweep_interval = interval(SWEEP_INTERVAL);
+        sweep_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            chain_rx,
+            retry_rx,
+            processor,
+            model_repo,
+            settings,
+            permits,
+            concurrency,
+            scheduler: Arc::new(Scheduler::new()),
+            sweep_interval,
+        }
+    }
+
+    /// Resize the pool of permits if the concurrency setting changed. Requests in flight are not
+    /// affected, surplus permits are retired as they are released.
+    fn apply_settings(&mut self) {
+        if !self.settings.has_changed().unwrap_or(false) {
+            return;
+        }
+        let concurrency = self.settings.borrow_and_update().concurrency;
+        if concurrency > self.concurrency {
+            self.permits.add_permits(concurrency - self.concurrency);
+        } else if concurrency < self.concurrency {
+            let surplus = (self.concurrency - concurrency) as u32;
+            let permits = self.permits.clone();
+            self.processor.tracker.spawn(async move {
+                if let Ok(permits) = permits.acquire_many_owned(surplus).await {
+                    permits.forget();
+                }
+            });
+        }
+        tracing::info!("🔄 Concurrency changed from {} to {concurrency}", self.concurrency);
+        self.concurrency = concurrency;
+    }
+
+    /// Resume the requests left in the work queue by a previous run.
+    pub async fn resume_queued(&mut self) -> Result<()> {
+        for item in self.processor.work_queue.list().await {
+            let (agreement_id, request_index) = item.key();
+            let Some(model) = self.model_repo.get_by_model_id(&item.model_id).await else {
+                tracing::warn!(
+                    "⚠️ Request {request_index} on agreement {agreement_id} dropped. Its model is \
+                     not served anymore"
+                );
+                self.processor.work_queue.finish(item.key()).await;
+                continue;
+            };
+            tracing::info!(
+                "🔁 Resuming request {request_index} on agreement {agreement_id} after {:?}",
+                item.stage
+            );
+            self.dispatch(model, item).await;
+        }
+        Ok(())
+    }
+
+    /// Schedule a queued request, and process it in the background if a permit is available.
+    /// Otherwise it waits for a worker to be done and pick it up.
+    async fn dispatch(&mut self, model: Model, item: WorkItem) {
+        self.apply_settings();
+        let window = self.settings.borrow().response_window_secs.saturating_mul(1000);
+        let (price, deadline) = match self.processor.agreement_repo.get(item.agreement_id).await {
+            Some(agreement) => {
+                (agreement.details.price_per_request, agreement.accepted_at.saturating_add(window))
+            },
+            None => (0, u64::MAX),
+        };
+        self.scheduler.push(Scheduled { model, item, price, deadline });
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            self.processor.tracker.spawn(work(
+                self.processor.clone(),
+                self.scheduler.clone(),
+                self.settings.clone(),
+                self.permits.clone(),
+                permit,
+            ));
+        } else {
+            tracing::debug!("⏳ {} requests waiting for a worker", self.scheduler.len());
+        }
+    }
+
+    /// Expire agreements whose response window closed, and evict finished agreements an hour
+    /// later. Evicted agreements stay in the history and the ledger.
+    async fn sweep(&self) {
+        let now = now_millis();
+        let window = self.settings.borrow().response_window_secs.saturating_mul(1000);
+        let agreement_repo = &self.processor.agreement_repo;
+        for agreement in agreement_repo.list().await {
+            let id = agreement.id;
+            let elapsed = now.saturating_sub(agreement.accepted_at);
+            match agreement.state {
+                AgreementState::Active if elapsed >= window => {
+                    tracing::info!(
+                        "⌛ Agreement {id} expired with {} of {} requests processed",
+                        agreement.requests_answered + agreement.requests_failed,
+                        agreement.details.requests_total
+                    );
+                    let expire =
+                        |agreement: &mut Agreement| agreement.state = AgreementState::Expired;
+                    agreement_repo.update(id, Box::new(expire)).await;
+                    self.processor.abort(id);
+                },
+                AgreementState::Active => {},
+                AgreementState::Completed | AgreementState::Expired
+                    if elapsed >= window.saturating_add(FINISHED_RETENTION) =>
+                {
+                    tracing::debug!("🧹 Agreement {id} evicted");
+                    agreement_repo.remove(id).await;
+                },
+                AgreementState::Completed | AgreementState::Expired => {},
+            }
+        }
     }
 }
 
@@ -39,12 +189,12 @@ impl ExecutionEngine {
 impl Engine for ExecutionEngine {
     async fn process_chain_event(&mut self, event: ChainEvent) -> Result<()> {
         match event {
-            ChainEvent::BidAccepted { order_id } => {
+            ChainEvent::BidAccepted { order_id, own: true, .. } => {
                 tracing::info!("🤝 Bid for order {order_id} accepted");
                 let Some(agreement) = retry_on_err_or_none!(
                     FIVE_TIMES,
                     5000,
-                    self.protocol_client.get_agreement(order_id).await
+                    self.processor.protocol_client.get_agreement(order_id).await
                 )?
                 else {
                     tracing::warn!(
@@ -52,19 +202,63 @@ impl Engine for ExecutionEngine {
                     );
                     return Ok(());
                 };
-                self.agreements.insert(order_id, agreement.model_id);
+                let agreement = Agreement::new(order_id, agreement, now_millis());
+                self.processor.agreement_repo.save(agreement).await;
             },
             ChainEvent::RequestCreated { agreement_id, request_index, content_id } => {
-                if let Some(model_id) = self.agreements.get(&agreement_id) {
-                    if let Some(model) = self.model_repo.get_by_model_id(model_id).await {
-                        process_request(
-                            self.protocol_client.clone(),
-                            agreement_id,
-                            &model.details.url,
-                            request_index,
-                            content_id,
-                        )
-                        .await?;
+                let Some(agreement) = self.processor.agreement_repo.get(agreement_id).await else {
+                    // Skip events referencing other agreements
+                    return Ok(());
+                };
+                if agreement.state != AgreementState::Active {
+                    tracing::warn!(
+                        "⚠️ Request {request_index} on agreement {agreement_id} refused. The \
+                         agreement is {:?}",
+                        agreement.state
+                    );
+                    return Ok(());
+                }
+                if !agreement.in_range(request_index) {
+                    tracing::warn!(
+                        "⚠️ Request {request_index} on agreement {agreement_id} refused. Only {} \
+                         requests were paid for",
+                        agreement.details.requests_total
+                    );
+                    return Ok(());
+                }
+                let agreement = self
+                    .processor
+                    .agreement_repo
+                    .update(
+                        agreement_id,
+                        Box::new(move |agreement| {
+                            let count = &mut agreement.details.requests_count;
+                            *count = (*count).max(request_index + 1);
+                        }),
+                    )
+                    .await;
+                if let Some(agreement) = agreement {
+                    let model_id = agreement.details.model_id;
+                    if let Some(model) = self.model_repo.get_by_model_id(&model_id).await {
+                        let consumer = &agreement.details.consumer;
+                        if !model.details.consumers.allows(consumer) {
+                            let reason = format!("Consumer {consumer} is not served");
+                            tracing::warn!(
+                                "⛔ Request {request_index} on agreement {agreement_id} refused. \
+                                 {reason}"
+                            );
+                            self.processor
+                                .refuse(agreement_id, &model, request_index, content_id, reason)
+                                .await;
+                            return Ok(());
+                        }
+                        let queued = self
+                            .processor
+                            .enqueue(agreement_id, &model, request_index, content_id)
+                            .await;
+                        if let Some(item) = queued {
+                            self.dispatch(model, item).await;
+                        }
                     } else {
                         // Model is not served anymore
                         return Ok(());
@@ -74,63 +268,543 @@ impl Engine for ExecutionEngine {
                     return Ok(());
                 }
             },
+            ChainEvent::ResponseCreated { agreement_id, request_index, .. } => {
+                if self.processor.agreement_repo.get(agreement_id).await.is_none() {
+                    // Skip events referencing other agreements
+                    return Ok(());
+                }
+                tracing::info!(
+                    "📬 Response to request {request_index} on agreement {agreement_id} recorded"
+                );
+            },
             _ => {},
         }
 
         Ok(())
     }
 
-    async fn try_recv(&mut self) -> stdResult<ChainEvent, RecvError> {
-        self.chain_rx.recv().await
+    /// Receive the next chain event. Meanwhile, retried requests are dispatched and agreements
+    /// are swept whenever the sweep is due.
+    async fn recv(&mut self) -> Option<ChainEvent> {
+        loop {
+            tokio::select! {
+                event = self.chain_rx.recv() => return event,
+                Some((model, item)) = self.retry_rx.recv() => self.dispatch(model, item).await,
+                _ = self.sweep_interval.tick() => self.sweep().await,
+            }
+        }
     }
 }
 
-async fn process_request(
+/// Process scheduled requests until none are left, holding a permit.
+async fn work(
+    processor: RequestProcessor,
+    scheduler: Arc<Scheduler>,
+    settings: watch::Receiver<EngineConfig>,
+    permits: Arc<Semaphore>,
+    mut permit: OwnedSemaphorePermit,
+) {
+    loop {
+        loop {
+            let scheduling = settings.borrow().scheduling.clone();
+            let Some(Scheduled { model, item, .. }) = scheduler.pop(&scheduling, now_millis())
+            else {
+                break;
+            };
+            let (agreement_id, request_index) = item.key();
+            if let Err(e) = processor.resume(&model, item).await {
+                tracing::error!(
+                    "🚫 Request {request_index} on agreement {agreement_id} failed: {e}"
+                );
+            }
+        }
+        // Requests scheduled after the permit is released are picked up by their dispatcher
+        drop(permit);
+        if scheduler.is_empty() {
+            return;
+        }
+        match permits.clone().try_acquire_owned() {
+            Ok(next) => permit = next,
+            Err(_) => return,
+        }
+    }
+}
+
+/// Processes requests: downloads the input, makes a prediction, uploads the result and submits
+/// the response. Every processed request is recorded in the history and counted towards its
+/// agreement.
+#[derive(Clone)]
+pub struct RequestProcessor {
     protocol_client: Arc<dyn Protocol + Send + Sync>,
-    agreement_id: AgreementId,
-    model_url: &str,
-    request_index: u32,
-    content_id: ContentId,
-) -> Result<()> {
-    tracing::info!("📩 Request {request_index} on agreement {agreement_id} received");
-    let Some(content) =
-        retry_on_err_or_none!(FIVE_TIMES, protocol_client.download(content_id).await)?
-    else {
-        tracing::warn!("⚠️ Content {content_id} not found");
-        return Ok(());
-    };
-    let result = match predict(model_url, &content).await {
-        Ok(res) => res,
-        Err(e) => {
-            tracing::error!("⚠️ Cog prediction failed: {e}. Request ignored.");
-            return Ok(());
-        },
-    };
-    tracing::info!("🛠️ Request {request_index} on agreement {agreement_id} processed");
-    let content_id = protocol_client.hash_upload(result).await?;
-    protocol_client.response_create(agreement_id, request_index, content_id).await?;
-    tracing::info!("✉️ Request {request_index} on agreement {agreement_id} responded");
-    Ok(())
+    replica_pool: Arc<ReplicaPool>,
+    result_cache: Arc<ResultCache>,
+    history_repo: Arc<dyn HistoryRepo + Send + Sync>,
+    agreement_repo: Arc<dyn AgreementRepo + Send + Sync>,
+    ledger_repo: Arc<dyn LedgerRepo + Send + Sync>,
+    work_queue: Arc<dyn WorkQueue + Send + Sync>,
+    batcher: Arc<Batcher>,
+    content_filter: Arc<ContentFilter>,
+    events: EventHub,
+    /// Hands retried requests to the execution engine.
+    retries: Option<Sender<(Model, WorkItem)>>,
+    /// Tracks the background tasks of requests, so shutdown waits for them.
+    tracker: TaskTracker,
+    /// Cancels the running predictions on shutdown.
+    shutdown: CancellationToken,
+    /// Cancels the running prediction of a request, e.g. once its agreement expired.
+    running: Arc<DashMap<(AgreementId, u32), CancellationToken>>,
 }
 
-async fn predict(url: &str, input: &[u8]) -> Result<Vec<u8>> {
-    let input = serde_json::from_slice(input)?;
-    tracing::debug!("🔎 Predicting {input:?} with {url}");
-    let cog = Connector::new(url)?;
-    cog.ensure_ready().await?;
-    let response: ExecutionResult = cog.predict::<Value, Value>(input).await?.into();
-    tracing::debug!("🔎 Predicted {response:?}");
-    serde_json::to_vec(&response).map_err(Into::into)
+impl RequestProcessor {
+    pub fn new(
+        protocol_client: Arc<dyn Protocol + Send + Sync>,
+        replica_pool: Arc<ReplicaPool>,
+        result_cache: Arc<ResultCache>,
+        history_repo: Arc<dyn HistoryRepo + Send + Sync>,
+        agreement_repo: Arc<dyn AgreementRepo + Send + Sync>,
+        ledger_repo: Arc<dyn LedgerRepo + Send + Sync>,
+        work_queue: Arc<dyn WorkQueue + Send + Sync>,
+    ) -> Self {
+        let (tracker, shutdown) = (TaskTracker::new(), CancellationToken::new());
+        let batcher =
+            Arc::new(Batcher::new(replica_pool.clone(), tracker.clone(), shutdown.clone()));
+        Self {
+            protocol_client,
+            replica_pool,
+            result_cache,
+            history_repo,
+            agreement_repo,
+            ledger_repo,
+            work_queue,
+            batcher,
+            content_filter: Arc::new(ContentFilter::new()),
+            events: EventHub::new(),
+            retries: None,
+            tracker,
+            shutdown,
+            running: Arc::default(),
+        }
+    }
+
+    /// Publish the progress of requests to the hub.
+    pub fn with_events(mut self, events: EventHub) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Hand retried requests to the execution engine through the channel. Without it, retried
+    /// requests stay queued until the next start.
+    pub fn with_retries(mut self, retries: Sender<(Model, WorkItem)>) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// Spawn the background tasks of requests on the tracker. Running predictions are cancelled
+    /// once the token is, and the requests stay queued to be resumed on the next start.
+    pub fn with_tracker(mut self, tracker: TaskTracker, shutdown: CancellationToken) -> Self {
+        let replica_pool = self.replica_pool.clone();
+        self.batcher = Arc::new(Batcher::new(replica_pool, tracker.clone(), shutdown.clone()));
+        self.tracker = tracker;
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Cancel the running predictions of an agreement.
+    fn abort(&self, agreement_id: AgreementId) {
+        self.running.retain(|(id, request_index), token| {
+            if *id != agreement_id {
+                return true;
+            }
+            tracing::info!("🛑 Cancelling request {request_index} on agreement {agreement_id}");
+            token.cancel();
+            false
+        });
+    }
+
+    pub(crate) fn history_repo(&self) -> Arc<dyn HistoryRepo + Send + Sync> {
+        self.history_repo.clone()
+    }
+
+    pub(crate) fn agreement_repo(&self) -> Arc<dyn AgreementRepo + Send + Sync> {
+        self.agreement_repo.clone()
+    }
+
+    pub(crate) fn ledger_repo(&self) -> Arc<dyn LedgerRepo + Send + Sync> {
+        self.ledger_repo.clone()
+    }
+
+    /// Queue a newly created request. Returns `None` if the request was seen before, so a
+    /// request is never answered twice.
+    async fn enqueue(
+        &self,
+        agreement_id: AgreementId,
+        model: &Model,
+        request_index: u32,
+        content_id: ContentId,
+    ) -> Option<WorkItem> {
+        let item = WorkItem::new(agreement_id, request_index, model.id, content_id, now_millis());
+        if self.history_repo.get(agreement_id, request_index).await.is_some()
+            || !self.work_queue.push(item.clone()).await
+        {
+            tracing::warn!("⚠️ Request {request_index} on agreement {agreement_id} seen before");
+            return None;
+        }
+        tracing::info!("📩 Request {request_index} on agreement {agreement_id} received");
+        let record =
+            RequestRecord::new(agreement_id, request_index, model.id, content_id, item.received_at);
+        self.history_repo.save(record).await;
+        Some(item)
+    }
+
+    /// Record a request refused before processing, so it is not silently dropped.
+    async fn refuse(
+        &self,
+        agreement_id: AgreementId,
+        model: &Model,
+        request_index: u32,
+        content_id: ContentId,
+        reason: String,
+    ) {
+        if self.history_repo.get(agreement_id, request_index).await.is_some() {
+            return;
+        }
+        let mut record =
+            RequestRecord::new(agreement_id, request_index, model.id, content_id, now_millis());
+        record.status = RequestStatus::Refused;
+        record.error = Some(reason);
+        self.history_repo.save(record).await;
+    }
+
+    /// Continue processing a queued request from its last completed step. Progress is recorded
+    /// in the work queue after every step. Requests that fail are moved to the dead letters.
+    pub async fn resume(&self, model: &Model, mut item: WorkItem) -> Result<()> {
+        let (agreement_id, request_index) = item.key();
+        let mut record = match self.history_repo.get(agreement_id, request_index).await {
+            Some(record) => record,
+            None => RequestRecord::new(
+                agreement_id,
+                request_index,
+                item.model_id,
+                item.input_content_id,
+                item.received_at,
+            ),
+        };
+        self.events.publish(EventKind::RequestStarted { agreement_id, request_index });
+        if let Err(e) = self.advance(model, &mut item, &mut record).await {
+            if self.shutdown.is_cancelled() {
+                // Left in the work queue, to be resumed on the next start
+                return Err(e);
+            }
+            self.bury(item, record, e.to_string()).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn advance(
+        &self,
+        model: &Model,
+        item: &mut WorkItem,
+        record: &mut RequestRecord,
+    ) -> Result<()> {
+        let (agreement_id, request_index) = item.key();
+        let cache_key = (model.id, item.input_content_id, model.details.version.clone());
+        loop {
+            match item.stage {
+                WorkStage::Received => {
+                    let cached = model
+                        .details
+                        .cache
+                        .as_ref()
+                        .and_then(|_| self.result_cache.get(&cache_key));
+                    if let Some(content_id) = cached {
+                        record.cached = true;
+                        item.output_content_id = Some(content_id);
+                        item.stage = WorkStage::Uploaded;
+                    } else {
+                        let content_id = item.input_content_id;
+                        let content = retry_on_err_or_none!(
+                            FIVE_TIMES,
+                            self.protocol_client.download(content_id).await
+                        )?
+                        .ok_or(Error::ContentNotFound(content_id))?;
+                        item.input = Some(serde_json::from_slice(&content)?);
+                        item.stage = WorkStage::Downloaded;
+                    }
+                },
+                WorkStage::Downloaded => {
+                    let input = item.input.clone().unwrap_or_default();
+                    let checks = &model.details.content;
+                    let filter = &self.content_filter;
+                    let rejection =
+                        filter.check(&model.name, &checks.input, Stage::Input, &input).await?;
+                    let result = match rejection {
+                        Some(rejection) => self.reject(record, rejection),
+                        None => {
+                            let response = self.predict(model, item.key(), input).await?;
+                            tracing::info!(
+                                "🛠️ Request {request_index} on agreement {agreement_id} processed"
+                            );
+                            let status = (&response.status).into();
+                            self.events.publish(EventKind::RequestPredicted {
+                                agreement_id,
+                                request_index,
+                                status,
+                            });
+                            record.prediction_status = Some(response.status.to_string());
+                            record.predict_started_at.clone_from(&response.started_at);
+                            record.predict_completed_at.clone_from(&response.completed_at);
+                            record.metrics.clone_from(&response.metrics);
+                            record.error.clone_from(&response.error);
+                            let rejection = match (&response.status, &response.output) {
+                                (Status::Succeeded, Some(output)) => {
+                                    let checks = &checks.output;
+                                    filter.check(&model.name, checks, Stage::Output, output).await?
+                                },
+                                _ => None,
+                            };
+                            match rejection {
+                                Some(rejection) => self.reject(record, rejection),
+                                None => ExecutionResult::from(response),
+                            }
+                        },
+                    };
+                    self.history_repo.save(record.clone()).await;
+                    item.input = None;
+                    item.result = Some(serde_json::to_value(result)?);
+                    item.stage = WorkStage::Predicted;
+                },
+                WorkStage::Predicted => {
+                    let result = item.result.take().unwrap_or_default();
+                    let upload = self.protocol_client.hash_upload(serde_json::to_vec(&result)?);
+                    let content_id = match upload.await {
+                        Ok(content_id) => content_id,
+                        Err(e) => {
+                            item.result = Some(result);
+                            return Err(e);
+                        },
+                    };
+                    let succeeded = result["status"] == Status::Succeeded.to_string();
+                    if let (true, Some(cache)) = (succeeded, &model.details.cache) {
+                        let ttl = Duration::from_secs(cache.ttl_secs);
+                        self.result_cache.insert(cache_key.clone(), content_id, ttl);
+                    }
+                    item.output_content_id = Some(content_id);
+                    item.stage = WorkStage::Uploaded;
+                },
+                WorkStage::Uploaded => {
+                    let content_id = item.output_content_id.expect("uploaded result has an ID");
+                    self.respond(record, content_id).await?;
+                    let cached = record.cached;
+                    self.events.publish(EventKind::RequestResponded {
+                        agreement_id,
+                        request_index,
+                        cached,
+                    });
+                    if record.cached {
+                        tracing::info!(
+                            "✉️ Request {request_index} on agreement {agreement_id} responded \
+                             from cache"
+                        );
+                    } else {
+                        tracing::info!(
+                            "✉️ Request {request_index} on agreement {agreement_id} responded"
+                        );
+                    }
+                    return Ok(());
+                },
+            }
+            self.work_queue.update(item.clone()).await;
+        }
+    }
+
+    /// Answer a request rejected by a content policy with the rejection.
+    fn reject(&self, record: &mut RequestRecord, rejection: Rejection) -> ExecutionResult {
+        let (agreement_id, request_index) = (record.agreement_id, record.request_index);
+        let code = rejection.code.clone();
+        self.events
+            .publish(EventKind::RequestRejected { agreement_id, request_index, code });
+        record.error = Some(rejection.to_string());
+        ExecutionResult::from(rejection)
+    }
+
+    /// Make the prediction of a request. It is cancelled once its agreement expires, on
+    /// shutdown, or once it runs longer than the prediction timeout of the model.
+    async fn predict(
+        &self,
+        model: &Model,
+        key: (AgreementId, u32),
+        input: Value,
+    ) -> Result<Prediction> {
+        let token = self.shutdown.child_token();
+        self.running.insert(key, token.clone());
+        let response = self.predict_until(model, key, input, &token).await;
+        self.running.remove(&key);
+        response
+    }
+
+    async fn predict_until(
+        &self,
+        model: &Model,
+        (agreement_id, request_index): (AgreementId, u32),
+        input: Value,
+        token: &CancellationToken,
+    ) -> Result<Prediction> {
+        if let Some(batching) = &model.details.batching {
+            tracing::debug!("🔎 Predicting {input:?} in a batch");
+            // The batch is shared, only this request stops waiting for it
+            return tokio::select! {
+                response = self.batcher.predict(model, batching, input) => response,
+                _ = token.cancelled() => Err(Error::Cancelled(agreement_id, request_index).into()),
+            };
+        }
+        let replica = self.replica_pool.acquire(model).await?;
+        tracing::debug!("🔎 Predicting {input:?} with {}", replica.url());
+        let prediction_id = format!("{agreement_id}-{request_index}-{}", now_millis());
+        let timeout = model.details.predict_timeout_secs.map(Duration::from_secs);
+        let response = replica.predict_until(&prediction_id, input, timeout, token).await?;
+        tracing::debug!("🔎 Predicted {response:?}");
+        Ok(response)
+    }
+
+    async fn respond(&self, record: &mut RequestRecord, content_id: ContentId) -> Result<()> {
+        record.output_content_id = Some(content_id);
+        let (agreement_id, request_index) = (record.agreement_id, record.request_index);
+        let receipt = match self
+            .protocol_client
+            .response_create(agreement_id, request_index, content_id)
+            .await
+        {
+            Ok(receipt) => Some(receipt),
+            // Responded before a restart, after the response was submitted
+            Err(e) if matches!(e.downcast_ref(), Some(ProtocolError::ResponseAlreadyExists)) => {
+                tracing::warn!(
+                    "⚠️ Request {request_index} on agreement {agreement_id} was responded already"
+                );
+                None
+            },
+            Err(e) => return Err(e),
+        };
+        record.status = RequestStatus::Responded;
+        record.responded_at = Some(now_millis());
+        record.tx_hash = receipt.map(|receipt| receipt.hash);
+        if let Some(TxReceipt { hash, fee: Some(fee) }) = receipt {
+            self.ledger_repo
+                .record(LedgerEntry {
+                    timestamp: now_millis(),
+                    kind: LedgerEntryKind::ResponseFee,
+                    agreement_id,
+                    model_id: record.model_id,
+                    amount: fee,
+                    tx_hash: Some(hash),
+                })
+                .await;
+        }
+        self.complete(record.clone()).await;
+        Ok(())
+    }
+
+    /// Move a failed request to the dead letters. It is not counted towards its agreement until
+    /// it is retried or discarded.
+    async fn bury(&self, item: WorkItem, mut record: RequestRecord, reason: String) {
+        let (agreement_id, request_index) = item.key();
+        tracing::warn!(
+            "🪦 Request {request_index} on agreement {agreement_id} moved to the dead letters: \
+             {reason}"
+        );
+        let failed =
+            EventKind::RequestFailed { agreement_id, request_index, reason: reason.clone() };
+        self.events.publish(failed);
+        record.status = RequestStatus::Failed;
+        record.error = Some(reason.clone());
+        self.history_repo.save(record).await;
+        // Buried before it is finished, so a crash in between leaves it queued rather than lost
+        let key = item.key();
+        self.work_queue.bury(item, reason, now_millis()).await;
+        self.work_queue.finish(key).await;
+    }
+
+    /// Requests that failed, oldest first.
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.work_queue.dead_letters().await
+    }
+
+    /// Retry a dead letter from the step it failed at. The request is queued again and handed to
+    /// the execution engine, which schedules it like any other request. Returns `false` if the
+    /// dead letter does not exist.
+    pub async fn retry(&self, id: DeadLetterId, model: Model) -> bool {
+        let letters = self.work_queue.dead_letters().await;
+        let Some(letter) = letters.into_iter().find(|letter| letter.id == id) else {
+            return false;
+        };
+        let (agreement_id, request_index) = letter.item.key();
+        tracing::info!("🔁 Retrying request {request_index} on agreement {agreement_id}");
+        // Queued before it is unburied, so a crash in between leaves it queued rather than lost
+        self.work_queue.push(letter.item.clone()).await;
+        if self.work_queue.remove_dead_letter(id).await.is_none() {
+            // Retried concurrently
+            return false;
+        }
+        let retried = match &self.retries {
+            Some(retries) => retries.send((model, letter.item)).await.is_ok(),
+            None => false,
+        };
+        if !retried {
+            tracing::warn!(
+                "⚠️ Request {request_index} on agreement {agreement_id} is resumed on the next \
+                 start. The execution engine is not running"
+            );
+        }
+        true
+    }
+
+    /// Give up on a dead letter. The request counts as failed towards its agreement. Returns
+    /// `false` if the dead letter does not exist.
+    pub async fn discard(&self, id: DeadLetterId) -> bool {
+        let Some(letter) = self.work_queue.remove_dead_letter(id).await else {
+            return false;
+        };
+        let (agreement_id, request_index) = letter.item.key();
+        tracing::info!("🗑️ Request {request_index} on agreement {agreement_id} discarded");
+        self.agreement_repo
+            .update(agreement_id, Box::new(|a| a.record_outcome(false)))
+            .await;
+        true
+    }
+
+    async fn complete(&self, record: RequestRecord) {
+        let answered = record.status == RequestStatus::Responded;
+        let agreement = self
+            .agreement_repo
+            .update(record.agreement_id, Box::new(move |a| a.record_outcome(answered)))
+            .await;
+        if let (true, Some(agreement)) = (answered, agreement) {
+            self.ledger_repo
+                .record(LedgerEntry {
+                    timestamp: now_millis(),
+                    kind: LedgerEntryKind::Earning,
+                    agreement_id: record.agreement_id,
+                    model_id: record.model_id,
+                    amount: agreement.details.price_per_request,
+                    tx_hash: record.tx_hash,
+                })
+                .await;
+        }
+        self.work_queue.finish((record.agreement_id, record.request_index)).await;
+        self.history_repo.save(record).await;
+    }
 }
 
-impl From<PredictionResponse> for ExecutionResult {
-    fn from(response: PredictionResponse) -> Self {
+impl From<Prediction> for ExecutionResult {
+    fn from(response: Prediction) -> Self {
         Self {
             status: response.status.to_string(),
             output: response.output,
             error: response.error,
             started_at: response.started_at,
             completed_at: response.completed_at,
+            usage: response.usage,
+            policy_code: None,
         }
     }
 }
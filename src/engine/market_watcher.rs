@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    data::MarketRepo,
+    engine::Engine,
+    protocol::{ChainEvent, MarketReader},
+    types::{AccountId, Result},
+    utils::now_millis,
+};
+
+/// Records the bids of competing providers and which of them win.
+pub struct MarketWatcher {
+    chain_rx: Receiver<ChainEvent>,
+    market_repo: Arc<dyn MarketRepo + Send + Sync>,
+}
+
+impl MarketWatcher {
+    pub fn new(
+        chain_rx: Receiver<ChainEvent>,
+        market_repo: Arc<dyn MarketRepo + Send + Sync>,
+    ) -> Self {
+        tracing::info!("🚀 Starting market watcher");
+        Self { chain_rx, market_repo }
+    }
+
+    /// Record the orders and competing bids open on chain, placed before the wingman started.
+    pub async fn load(&self, reader: &dyn MarketReader, provider: &AccountId) -> Result<()> {
+        for (order_id, model_id) in reader.list_orders().await? {
+            self.market_repo.record_order(order_id, model_id).await;
+        }
+        let mut bids = 0;
+        for (order_id, bidder, price_per_request) in reader.list_bids().await? {
+            if bidder != *provider {
+                self.market_repo
+                    .record_bid(order_id, bidder, price_per_request, now_millis())
+                    .await;
+                bids += 1;
+            }
+        }
+        tracing::info!("👀 Loaded {bids} competing bids placed before start");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Engine for MarketWatcher {
+    async fn process_chain_event(&mut self, event: ChainEvent) -> Result<()> {
+        match event {
+            ChainEvent::OrderCreated { order_id, model_id } => {
+                self.market_repo.record_order(order_id, model_id).await;
+            },
+            ChainEvent::BidCreated { order_id, provider, price_per_request, own: false } => {
+                tracing::debug!("👀 {provider} bid {price_per_request} on order {order_id}");
+                self.market_repo
+                    .record_bid(order_id, provider, price_per_request, now_millis())
+                    .await;
+            },
+            ChainEvent::BidAccepted { order_id, provider, own: false } => {
+                self.market_repo.record_acceptance(order_id, &provider).await;
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Option<ChainEvent> {
+        self.chain_rx.recv().await
+    }
+}
@@ -1,45 +1,53 @@
 use async_trait::async_trait;
 use thiserror::Error;
-use tokio::sync::broadcast::error::RecvError;
 use tokio_util::sync::CancellationToken;
 
+pub use admission::{Admission, AdmissionControl};
 pub use bid_engine::BidEngine;
-pub use execution_engine::ExecutionEngine;
+pub use event_relay::EventRelay;
+pub use execution_engine::{ExecutionEngine, RequestProcessor};
+pub use market_watcher::MarketWatcher;
+pub use scheduler::{Scheduled, Scheduler};
 
 use crate::{
     protocol::ChainEvent,
-    types::{stdResult, Result},
+    types::{AgreementId, ContentId, Result},
 };
 
+pub mod admission;
 pub mod bid_engine;
+pub mod event_relay;
 pub mod execution_engine;
+pub mod market_watcher;
+pub mod scheduler;
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Chain events receiver closed")]
     ReceiverClosed,
+    #[error("Content {0} not found")]
+    ContentNotFound(ContentId),
+    #[error("Request {1} on agreement {0} was cancelled")]
+    Cancelled(AgreementId, u32),
 }
 
 #[async_trait]
 pub trait Engine {
     async fn process_chain_event(&mut self, event: ChainEvent) -> Result<()>;
 
-    async fn try_recv(&mut self) -> stdResult<ChainEvent, RecvError>;
+    /// Receive the next chain event. Returns `None` once the chain listener is gone.
+    async fn recv(&mut self) -> Option<ChainEvent>;
 
     async fn run(&mut self, token: CancellationToken) -> Result<()> {
         loop {
             tokio::select! {
                 _ = token.cancelled() => break,
-                result = self.try_recv() => {
-                    match result {
-                        Ok(event) => self.process_chain_event(event).await?,
-                        Err(RecvError::Lagged(lost)) => {
-                            tracing::warn!("⚠️ Chain receiver lagged behind by {lost} events");
-                        },
-                        Err(RecvError::Closed) => {
-                            tracing::error!("Channel is closed");
-                            return Err(Error::ReceiverClosed.into());
-                        }}
+                event = self.recv() => {
+                    let Some(event) = event else {
+                        tracing::error!("Channel is closed");
+                        return Err(Error::ReceiverClosed.into());
+                    };
+                    self.process_chain_event(event).await?;
                 }
             }
         }
@@ -0,0 +1,124 @@
+use std::{cmp::Ordering, collections::HashMap, sync::Mutex};
+
+use primitive_types::U256;
+
+use crate::{
+    config::{SchedulingConfig, SchedulingPolicy},
+    types::{AgreementId, Balance, Model, WorkItem},
+};
+
+/// A request waiting for a worker.
+#[derive(Clone)]
+pub struct Scheduled {
+    pub model: Model,
+    pub item: WorkItem,
+    /// Price paid per request of the agreement.
+    pub price: Balance,
+    /// Time the agreement expires, in milliseconds since the Unix epoch.
+    pub deadline: u64,
+}
+
+struct Entry {
+    scheduled: Scheduled,
+    /// Arrival order.
+    seq: u64,
+    /// Virtual start and finish times, for weighted fair queuing.
+    start: U256,
+    finish: U256,
+}
+
+#[derive(Default)]
+struct State {
+    entries: Vec<Entry>,
+    seq: u64,
+    virtual_time: U256,
+    /// Virtual finish time of the last request of each agreement with requests waiting.
+    last_finish: HashMap<AgreementId, U256>,
+}
+
+/// Virtual time a request takes, inversely proportional to its price. Scaled up to keep 64 bits
+/// of precision across the whole range of prices, down to a fraction of a planck.
+fn cost(price: Balance) -> U256 {
+    (U256::one() << 192) / U256::from(price.max(1))
+}
+
+/// Orders requests waiting for a worker. Whatever the policy, requests of agreements close to
+/// expiring go first, earliest deadline first, then requests that waited too long, oldest first.
+#[derive(Default)]
+pub struct Scheduler {
+    state: Mutex<State>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a request to the waiting requests.
+    pub fn push(&self, scheduled: Scheduled) {
+        let mut state = self.state.lock().expect("scheduler lock should not be poisoned");
+        let agreement_id = scheduled.item.agreement_id;
+        let last_finish = state.last_finish.get(&agreement_id).copied().unwrap_or_default();
+        let start = last_finish.max(state.virtual_time);
+        let finish = start.saturating_add(cost(scheduled.price));
+        state.last_finish.insert(agreement_id, finish);
+        state.seq += 1;
+        let seq = state.seq;
+        state.entries.push(Entry { scheduled, seq, start, finish });
+    }
+
+    /// Take the request to process next, if any.
+    pub fn pop(&self, config: &SchedulingConfig, now: u64) -> Option<Scheduled> {
+        let mut state = self.state.lock().expect("scheduler lock should not be poisoned");
+        let index = (0..state.entries.len())
+            .min_by(|&a, &b| compare(config, now, &state.entries[a], &state.entries[b]))?;
+        let entry = state.entries.swap_remove(index);
+        state.virtual_time = state.virtual_time.max(entry.start);
+        let agreement_id = entry.scheduled.item.agreement_id;
+        if !state.entries.iter().any(|e| e.scheduled.item.agreement_id == agreement_id) {
+            state.last_finish.remove(&agreement_id);
+        }
+        Some(entry.scheduled)
+    }
+
+    /// Number of waiting requests.
+    pub fn len(&self) -> usize {
+        self.state.lock().expect("scheduler lock should not be poisoned").entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Urgency of a waiting request: 2 if its agreement is about to expire, 1 if it waited too long,
+/// 0 otherwise.
+fn urgency(config: &SchedulingConfig, now: u64, entry: &Entry) -> u8 {
+    if config.policy == SchedulingPolicy::Fifo {
+        return 0;
+    }
+    let scheduled = &entry.scheduled;
+    if scheduled.deadline.saturating_sub(now) <= config.urgent_secs.saturating_mul(1000) {
+        2
+    } else if now.saturating_sub(scheduled.item.received_at)
+        >= config.max_wait_secs.saturating_mul(1000)
+    {
+        1
+    } else {
+        0
+    }
+}
+
+/// Whether `a` goes before (`Less`) or after (`Greater`) `b`.
+fn compare(config: &SchedulingConfig, now: u64, a: &Entry, b: &Entry) -> Ordering {
+    let rank = urgency(config, now, a);
+    rank.cmp(&urgency(config, now, b))
+        .reverse()
+        .then_with(|| match (rank, config.policy) {
+            (2, _) => a.scheduled.deadline.cmp(&b.scheduled.deadline),
+            (1, _) | (_, SchedulingPolicy::Fifo) => Ordering::Equal,
+            (_, SchedulingPolicy::StrictPriority) => b.scheduled.price.cmp(&a.scheduled.price),
+            (_, SchedulingPolicy::WeightedFair) => a.finish.cmp(&b.finish),
+        })
+        .then(a.seq.cmp(&b.seq))
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+pub use crate::protocol::ChainEventName;
+use crate::{
+    backend::{Health, Status},
+    types::{AgreementId, Balance, OrderId},
+    utils::now_millis,
+};
+
+/// Number of lifecycle events buffered per subscriber. Subscribers falling further behind skip
+/// the oldest events.
+const EVENT_BUFFER: usize = 256;
+
+/// A step in the lifecycle of the wingman, as streamed by `GET /v1/events`.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct WingmanEvent {
+    /// When the event happened, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// What happened. Serialized with a `type` tag, e.g. `{"type": "bid_accepted", "order_id": 1}`.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// A chain event was received, e.g. `order_created`.
+    ChainEventReceived { name: ChainEventName },
+    /// This provider placed a bid.
+    BidPlaced {
+        order_id: OrderId,
+        #[schema(value_type = u128)]
+        price: Balance,
+    },
+    /// A bid of this provider was accepted.
+    BidAccepted { order_id: OrderId },
+    /// Processing of a request started or resumed.
+    RequestStarted { agreement_id: AgreementId, request_index: u32 },
+    /// A request was predicted. The status is the prediction status, e.g. `succeeded`.
+    RequestPredicted { agreement_id: AgreementId, request_index: u32, status: PredictionStatus },
+    /// The input or output of a request was rejected by a content policy.
+    RequestRejected { agreement_id: AgreementId, request_index: u32, code: String },
+    /// The response to a request was submitted.
+    RequestResponded { agreement_id: AgreementId, request_index: u32, cached: bool },
+    /// Processing of a request failed. The request was moved to the dead letters.
+    RequestFailed { agreement_id: AgreementId, request_index: u32, reason: String },
+    /// The health of a replica changed, e.g. from `starting` to `ready`.
+    HealthChanged { url: String, health: ReplicaHealth },
+}
+
+/// Status of a prediction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PredictionStatus {
+    Starting,
+    Processing,
+    Succeeded,
+    Canceled,
+    Failed,
+}
+
+impl From<&Status> for PredictionStatus {
+    fn from(status: &Status) -> Self {
+        match status {
+            Status::Starting => Self::Starting,
+            Status::Processing => Self::Processing,
+            Status::Succeeded => Self::Succeeded,
+            Status::Canceled => Self::Canceled,
+            Status::Failed => Self::Failed,
+        }
+    }
+}
+
+/// Health of a replica, as reported by its health check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplicaHealth {
+    Unknown,
+    Starting,
+    Ready,
+    Busy,
+    SetupFailed,
+    /// The health check failed.
+    Unreachable,
+}
+
+impl From<Health> for ReplicaHealth {
+    fn from(health: Health) -> Self {
+        match health {
+            Health::Unknown => Self::Unknown,
+            Health::Starting => Self::Starting,
+            Health::Ready => Self::Ready,
+            Health::Busy => Self::Busy,
+            Health::SetupFailed => Self::SetupFailed,
+        }
+    }
+}
+
+/// Broadcasts lifecycle events to any number of subscribers. Publishing never waits: events
+/// nobody listens to are dropped.
+#[derive(Clone)]
+pub struct EventHub {
+    tx: broadcast::Sender<WingmanEvent>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self { tx: broadcast::channel(EVENT_BUFFER).0 }
+    }
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&self, kind: EventKind) {
+        // Fails only if there are no subscribers
+        let _ = self.tx.send(WingmanEvent { timestamp: now_millis(), kind });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WingmanEvent> {
+        self.tx.subscribe()
+    }
+}
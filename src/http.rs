@@ -1,47 +1,96 @@
-use std::{
-    net::{Ipv4Addr, SocketAddr},
-    sync::Arc,
-};
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    routing::{get, put},
+    routing::{delete, get, post, put},
     Json, Router,
 };
-use tokio::net::TcpListener;
+use tokio::{net::TcpListener, sync::watch};
 use tokio_util::sync::CancellationToken;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
-    data::ModelRepo,
-    types::{Model, ModelDetails},
+    config::EngineConfig,
+    data::{
+        AgreementRepo, ConsumerRepo, ConsumerRepoFac, HistoryRepo, LedgerRepo, MarketRepo,
+        ModelRepo,
+    },
+    engine::RequestProcessor,
+    events::EventHub,
+    protocol::StateReader,
+    types::{
+        Backend, Balancing, BatchSettings, CacheSettings, HttpJsonSettings, InputMode,
+        KServeSettings, Model, ModelDetails, OpenAiEndpoint, OpenAiSettings, ResourceLimits,
+        SubprocessSettings,
+    },
 };
 
 #[derive(OpenApi)]
 #[openapi(
     nest(
         (path = "/v1", api = models::ModelsApi),
+        (path = "/v1", api = requests::RequestsApi),
+        (path = "/v1", api = agreements::AgreementsApi),
+        (path = "/v1", api = earnings::EarningsApi),
+        (path = "/v1", api = market::MarketApi),
+        (path = "/v1", api = dead_letters::DeadLettersApi),
+        (path = "/v1", api = events::EventsApi),
         (path = "/check", api = check::CheckApi),
     ),
 )]
 pub struct HttpServer {
-    port: u16,
+    address: SocketAddr,
+    processor: RequestProcessor,
     model_repo: Arc<dyn ModelRepo + Send + Sync>,
+    state_reader: Arc<dyn StateReader + Send + Sync>,
+    market_repo: Arc<dyn MarketRepo + Send + Sync>,
+    consumer_repo: Arc<dyn ConsumerRepo + Send + Sync>,
+    engine_settings: watch::Receiver<EngineConfig>,
+    events: EventHub,
 }
 
 impl HttpServer {
-    pub fn new(port: u16, model_repo: Arc<dyn ModelRepo + Send + Sync>) -> Self {
-        Self { port, model_repo }
+    /// Server of the API. Requests, agreements and the ledger are read from the repos of the
+    /// processor.
+    pub fn new(
+        address: SocketAddr,
+        processor: RequestProcessor,
+        model_repo: Arc<dyn ModelRepo + Send + Sync>,
+        state_reader: Arc<dyn StateReader + Send + Sync>,
+        market_repo: Arc<dyn MarketRepo + Send + Sync>,
+        events: EventHub,
+    ) -> Self {
+        let consumer_repo = Arc::new(ConsumerRepoFac::in_memory());
+        let engine_settings = watch::channel(EngineConfig::default()).1;
+        Self {
+            address,
+            processor,
+            model_repo,
+            state_reader,
+            market_repo,
+            consumer_repo,
+            engine_settings,
+            events,
+        }
+    }
+
+    /// Persist the consumer policies set through the API in the repo, see [ConsumerRepo].
+    pub fn with_consumers(mut self, consumer_repo: Arc<dyn ConsumerRepo + Send + Sync>) -> Self {
+        self.consumer_repo = consumer_repo;
+        self
+    }
+
+    /// Validate models saved through the API against the engine settings, e.g. the concurrency.
+    pub fn with_engine_settings(mut self, engine_settings: watch::Receiver<EngineConfig>) -> Self {
+        self.engine_settings = engine_settings;
+        self
     }
-    pub async fn serve(&self, token: CancellationToken) -> crate::Result<()> {
-        let app = Router::new()
-            .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", Self::openapi()))
-            .nest("/check", check::routes())
-            .nest("/v1", self.v1_routes());
 
-        let address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, self.port));
+    pub async fn serve(&self, token: CancellationToken) -> crate::Result<()> {
+        let app = self.router();
+        let address = self.address;
         let listener = TcpListener::bind(&address).await.unwrap();
 
         tracing::info!("🚀 Listening on {}", listener.local_addr().unwrap());
@@ -51,30 +100,86 @@ impl HttpServer {
             .map_err(Into::into)
     }
 
+    pub fn router(&self) -> Router {
+        Router::new()
+            .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", Self::openapi()))
+            .nest("/check", check::routes())
+            .nest("/v1", self.v1_routes())
+    }
+
     fn v1_routes(&self) -> Router {
-        Router::new().merge(models::routes().with_state(models::Deps::new(self.model_repo.clone())))
+        Router::new()
+            .merge(models::routes().with_state(models::Deps::new(
+                self.model_repo.clone(),
+                self.consumer_repo.clone(),
+                self.engine_settings.clone(),
+            )))
+            .merge(
+                requests::routes().with_state(requests::Deps::new(self.processor.history_repo())),
+            )
+            .merge(agreements::routes().with_state(agreements::Deps::new(
+                self.processor.agreement_repo(),
+                self.state_reader.clone(),
+            )))
+            .merge(earnings::routes().with_state(earnings::Deps::new(self.processor.ledger_repo())))
+            .merge(market::routes().with_state(market::Deps::new(self.market_repo.clone())))
+            .merge(dead_letters::routes().with_state(dead_letters::Deps::new(
+                self.processor.clone(),
+                self.model_repo.clone(),
+            )))
+            .merge(events::routes().with_state(events::Deps::new(self.events.clone())))
     }
 }
 
 mod models {
     use super::*;
-    use crate::types::ModelName;
+    use crate::{
+        config::validate_model,
+        types::{ConsumerPolicy, ContentCheck, ContentPolicy, ModelName},
+    };
 
     #[derive(Clone)]
     pub struct Deps {
         model_repo: Arc<dyn ModelRepo + Send + Sync>,
+        consumer_repo: Arc<dyn ConsumerRepo + Send + Sync>,
+        engine_settings: watch::Receiver<EngineConfig>,
     }
 
     impl Deps {
-        pub fn new(model_repo: Arc<dyn ModelRepo + Send + Sync>) -> Self {
-            Self { model_repo }
+        pub fn new(
+            model_repo: Arc<dyn ModelRepo + Send + Sync>,
+            consumer_repo: Arc<dyn ConsumerRepo + Send + Sync>,
+            engine_settings: watch::Receiver<EngineConfig>,
+        ) -> Self {
+            Self { model_repo, consumer_repo, engine_settings }
+        }
+
+        fn concurrency(&self) -> usize {
+            self.engine_settings.borrow().concurrency
         }
     }
 
     #[derive(OpenApi)]
     #[openapi(
-        paths(list_models, save_model, delete_model),
-        components(schemas(Model, ModelDetails))
+        paths(list_models, save_model, delete_model, get_consumers, save_consumers),
+        components(schemas(
+            Model,
+            ModelDetails,
+            Backend,
+            HttpJsonSettings,
+            OpenAiSettings,
+            OpenAiEndpoint,
+            KServeSettings,
+            SubprocessSettings,
+            InputMode,
+            ResourceLimits,
+            Balancing,
+            CacheSettings,
+            BatchSettings,
+            ConsumerPolicy,
+            ContentPolicy,
+            ContentCheck
+        ))
     )]
     pub struct ModelsApi;
 
@@ -82,6 +187,7 @@ mod models {
         Router::new()
             .route("/models", get(list_models))
             .route("/models/:name", put(save_model).delete(delete_model))
+            .route("/models/:name/consumers", get(get_consumers).put(save_consumers))
     }
 
     /// List all models.
@@ -92,18 +198,26 @@ mod models {
         Json(models)
     }
 
-    /// Save model. Either it's created or updated.
+    /// Save model. Either it's created or updated. Its consumer policy is kept over the policy of
+    /// the configuration across reloads and restarts.
     #[utoipa::path(put, path = "/models/{name}",
         params(("name" = String, Path, description = "Model name")),
         request_body = ModelDetails,
-        responses((status = 200, description = "Saved")))]
+        responses(
+            (status = 200, description = "Saved"),
+            (status = 400, description = "Invalid model")))]
     async fn save_model(
         Path(name): Path<ModelName>,
         State(deps): State<Deps>,
         Json(details): Json<ModelDetails>,
-    ) {
-        let model = Model::new(name, details);
-        deps.model_repo.save(model).await;
+    ) -> Result<StatusCode, (StatusCode, String)> {
+        let problems = validate_model(&name, &details, deps.concurrency());
+        if !problems.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, problems.join(", ")));
+        }
+        deps.consumer_repo.save(name.clone(), details.consumers.clone()).await;
+        deps.model_repo.save(Model::new(name, details)).await;
+        Ok(StatusCode::OK)
     }
 
     /// Delete model.
@@ -118,11 +232,549 @@ mod models {
     ) -> Result<StatusCode, StatusCode> {
         if deps.model_repo.contains(&name).await {
             deps.model_repo.remove(&name).await;
+            deps.consumer_repo.remove(&name).await;
             Ok(StatusCode::OK)
         } else {
             Err(StatusCode::NOT_FOUND)
         }
     }
+
+    /// Get the consumer policy of a model.
+    #[utoipa::path(get, path = "/models/{name}/consumers",
+        params(("name" = String, Path, description = "Model name")),
+        responses(
+            (status = 200, description = "Ok", body = ConsumerPolicy),
+            (status = 404, description = "Not found")))]
+    async fn get_consumers(
+        Path(name): Path<ModelName>,
+        State(deps): State<Deps>,
+    ) -> Result<Json<ConsumerPolicy>, StatusCode> {
+        let models = deps.model_repo.list().await;
+        let model = models.into_iter().find(|model| model.name == name);
+        model.map(|model| Json(model.details.consumers)).ok_or(StatusCode::NOT_FOUND)
+    }
+
+    /// Replace the consumer policy of a model. It applies to orders and requests received from
+    /// now on, and is kept over the policy of the configuration across reloads and restarts.
+    #[utoipa::path(put, path = "/models/{name}/consumers",
+        params(("name" = String, Path, description = "Model name")),
+        request_body = ConsumerPolicy,
+        responses(
+            (status = 200, description = "Saved"),
+            (status = 400, description = "Invalid policy"),
+            (status = 404, description = "Not found")))]
+    async fn save_consumers(
+        Path(name): Path<ModelName>,
+        State(deps): State<Deps>,
+        Json(consumers): Json<ConsumerPolicy>,
+    ) -> Result<StatusCode, (StatusCode, String)> {
+        let models = deps.model_repo.list().await;
+        let Some(mut model) = models.into_iter().find(|model| model.name == name) else {
+            return Err((StatusCode::NOT_FOUND, format!("Model {name} not found")));
+        };
+        model.details.consumers = consumers.clone();
+        let problems = validate_model(&model.name, &model.details, deps.concurrency());
+        if !problems.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, problems.join(", ")));
+        }
+        deps.consumer_repo.save(model.name.clone(), consumers).await;
+        deps.model_repo.save(model).await;
+        Ok(StatusCode::OK)
+    }
+}
+
+mod requests {
+    use serde::{Deserialize, Serialize};
+    use utoipa::{IntoParams, ToSchema};
+
+    use super::*;
+    use crate::{
+        data::HistoryFilter,
+        types::{AgreementId, ModelId, RequestRecord, RequestStatus},
+    };
+
+    const DEFAULT_LIMIT: usize = 50;
+    const MAX_LIMIT: usize = 500;
+
+    #[derive(Clone)]
+    pub struct Deps {
+        history_repo: Arc<dyn HistoryRepo + Send + Sync>,
+    }
+
+    impl Deps {
+        pub fn new(history_repo: Arc<dyn HistoryRepo + Send + Sync>) -> Self {
+            Self { history_repo }
+        }
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(
+        paths(list_requests, list_agreement_requests),
+        components(schemas(RequestPage, RequestRecord, RequestStatus))
+    )]
+    pub struct RequestsApi;
+
+    pub fn routes() -> Router<Deps> {
+        Router::new()
+            .route("/requests", get(list_requests))
+            .route("/agreements/:id/requests", get(list_agreement_requests))
+    }
+
+    #[derive(Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct Pagination {
+        /// Number of records to skip.
+        offset: Option<usize>,
+        /// Maximum number of records to return. Defaults to 50, at most 500.
+        limit: Option<usize>,
+    }
+
+    #[derive(Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct RequestsQuery {
+        /// Filter by agreement.
+        agreement_id: Option<AgreementId>,
+        /// Filter by model.
+        #[param(value_type = Option<String>)]
+        model_id: Option<ModelId>,
+        /// Filter by status.
+        #[param(inline)]
+        status: Option<RequestStatus>,
+    }
+
+    /// Page of request records, most recent first.
+    #[derive(Serialize, ToSchema)]
+    pub struct RequestPage {
+        items: Vec<RequestRecord>,
+        /// Total number of matching records.
+        total: usize,
+        offset: usize,
+        limit: usize,
+    }
+
+    async fn query(deps: &Deps, filter: HistoryFilter, page: Pagination) -> RequestPage {
+        let offset = page.offset.unwrap_or_default();
+        let limit = page.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        let (total, items) = deps.history_repo.query(&filter, offset, limit).await;
+        RequestPage { items, total, offset, limit }
+    }
+
+    /// List processed requests.
+    #[utoipa::path(get, path = "/requests",
+        params(RequestsQuery, Pagination),
+        responses((status = 200, description = "Ok", body = RequestPage)))]
+    async fn list_requests(
+        Query(filter): Query<RequestsQuery>,
+        Query(page): Query<Pagination>,
+        State(deps): State<Deps>,
+    ) -> Json<RequestPage> {
+        let RequestsQuery { agreement_id, model_id, status } = filter;
+        let filter = HistoryFilter { agreement_id, model_id, status };
+        Json(query(&deps, filter, page).await)
+    }
+
+    /// List processed requests of an agreement.
+    #[utoipa::path(get, path = "/agreements/{id}/requests",
+        params(("id" = u32, Path, description = "Agreement ID"), Pagination),
+        responses((status = 200, description = "Ok", body = RequestPage)))]
+    async fn list_agreement_requests(
+        Path(agreement_id): Path<AgreementId>,
+        Query(page): Query<Pagination>,
+        State(deps): State<Deps>,
+    ) -> Json<RequestPage> {
+        let filter = HistoryFilter { agreement_id: Some(agreement_id), ..Default::default() };
+        Json(query(&deps, filter, page).await)
+    }
+}
+
+mod agreements {
+    use super::*;
+    use crate::types::{Agreement, AgreementDetails, AgreementId, AgreementState};
+
+    #[derive(Clone)]
+    pub struct Deps {
+        agreement_repo: Arc<dyn AgreementRepo + Send + Sync>,
+        state_reader: Arc<dyn StateReader + Send + Sync>,
+    }
+
+    impl Deps {
+        pub fn new(
+            agreement_repo: Arc<dyn AgreementRepo + Send + Sync>,
+            state_reader: Arc<dyn StateReader + Send + Sync>,
+        ) -> Self {
+            Self { agreement_repo, state_reader }
+        }
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(
+        paths(list_agreements, get_agreement),
+        components(schemas(Agreement, AgreementDetails, AgreementState))
+    )]
+    pub struct AgreementsApi;
+
+    pub fn routes() -> Router<Deps> {
+        Router::new()
+            .route("/agreements", get(list_agreements))
+            .route("/agreements/:id", get(get_agreement))
+    }
+
+    /// List agreements won by the provider.
+    #[utoipa::path(get, path = "/agreements",
+        responses((status = 200, description = "Ok", body = [Agreement])))]
+    async fn list_agreements(State(deps): State<Deps>) -> Json<Vec<Agreement>> {
+        Json(deps.agreement_repo.list().await)
+    }
+
+    /// Get agreement. Its details are refreshed from the chain, the stored agreement is left as is.
+    #[utoipa::path(get, path = "/agreements/{id}",
+        params(("id" = u32, Path, description = "Agreement ID")),
+        responses(
+            (status = 200, description = "Ok", body = Agreement),
+            (status = 404, description = "Not found")))]
+    async fn get_agreement(
+        Path(id): Path<AgreementId>,
+        State(deps): State<Deps>,
+    ) -> Result<Json<Agreement>, StatusCode> {
+        let mut agreement = deps.agreement_repo.get(id).await.ok_or(StatusCode::NOT_FOUND)?;
+        match deps.state_reader.get_agreement(id).await {
+            Ok(Some(details)) => agreement.details = details,
+            Ok(None) => {},
+            Err(e) => tracing::warn!("⚠️ Failed to refresh agreement {id}: {e}"),
+        }
+        Ok(Json(agreement))
+    }
+}
+
+mod earnings {
+    use axum::{http::header, response::IntoResponse};
+    use serde::Deserialize;
+    use utoipa::{IntoParams, ToSchema};
+
+    use super::*;
+    use crate::{
+        data::ledger::{self, EarningsSummary},
+        types::{LedgerEntry, LedgerEntryKind},
+    };
+
+    #[derive(Clone)]
+    pub struct Deps {
+        ledger_repo: Arc<dyn LedgerRepo + Send + Sync>,
+    }
+
+    impl Deps {
+        pub fn new(ledger_repo: Arc<dyn LedgerRepo + Send + Sync>) -> Self {
+            Self { ledger_repo }
+        }
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(
+        paths(get_earnings, export_ledger),
+        components(schemas(EarningsSummary, LedgerEntry, LedgerEntryKind, GroupBy, Format))
+    )]
+    pub struct EarningsApi;
+
+    pub fn routes() -> Router<Deps> {
+        Router::new()
+            .route("/earnings", get(get_earnings))
+            .route("/earnings/export", get(export_ledger))
+    }
+
+    #[derive(Clone, Copy, Default, Deserialize, ToSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum GroupBy {
+        #[default]
+        Day,
+        Model,
+    }
+
+    #[derive(Clone, Copy, Default, Deserialize, ToSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Format {
+        #[default]
+        Json,
+        Csv,
+    }
+
+    #[derive(Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct EarningsQuery {
+        /// Group earnings by day or by model. Defaults to day.
+        #[serde(default)]
+        group_by: GroupBy,
+    }
+
+    #[derive(Deserialize, IntoParams)]
+    #[into_params(parameter_in = Query)]
+    pub struct ExportQuery {
+        /// Export format. Defaults to JSON.
+        #[serde(default)]
+        format: Format,
+    }
+
+    /// Get earnings and fees grouped by day or by model.
+    #[utoipa::path(get, path = "/earnings",
+        params(EarningsQuery),
+        responses((status = 200, description = "Ok", body = [EarningsSummary])))]
+    async fn get_earnings(
+        Query(query): Query<EarningsQuery>,
+        State(deps): State<Deps>,
+    ) -> Json<Vec<EarningsSummary>> {
+        let entries = deps.ledger_repo.entries().await;
+        let summaries = match query.group_by {
+            GroupBy::Day => ledger::daily(&entries),
+            GroupBy::Model => ledger::per_model(&entries),
+        };
+        Json(summaries)
+    }
+
+    /// Export all ledger entries.
+    #[utoipa::path(get, path = "/earnings/export",
+        params(ExportQuery),
+        responses((status = 200, description = "Ok",
+            content(("application/json" = Vec<LedgerEntry>), ("text/csv" = String)))))]
+    async fn export_ledger(
+        Query(query): Query<ExportQuery>,
+        State(deps): State<Deps>,
+    ) -> impl IntoResponse {
+        let entries = deps.ledger_repo.entries().await;
+        match query.format {
+            Format::Json => Json(entries).into_response(),
+            Format::Csv => {
+                ([(header::CONTENT_TYPE, "text/csv")], ledger::to_csv(&entries)).into_response()
+            },
+        }
+    }
+}
+
+mod market {
+    use super::*;
+    use crate::{
+        data::market::MarketStats,
+        types::{CompetitorBid, ModelId},
+    };
+
+    #[derive(Clone)]
+    pub struct Deps {
+        market_repo: Arc<dyn MarketRepo + Send + Sync>,
+    }
+
+    impl Deps {
+        pub fn new(market_repo: Arc<dyn MarketRepo + Send + Sync>) -> Self {
+            Self { market_repo }
+        }
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(list_market, list_bids), components(schemas(MarketStats, CompetitorBid)))]
+    pub struct MarketApi;
+
+    pub fn routes() -> Router<Deps> {
+        Router::new()
+            .route("/market", get(list_market))
+            .route("/market/:model_id/bids", get(list_bids))
+    }
+
+    /// List statistics of the recent bids of competing providers, per model.
+    #[utoipa::path(get, path = "/market",
+        responses((status = 200, description = "Ok", body = [MarketStats])))]
+    async fn list_market(State(deps): State<Deps>) -> Json<Vec<MarketStats>> {
+        Json(deps.market_repo.list().await)
+    }
+
+    /// List the recent bids of competing providers on a model, oldest first.
+    #[utoipa::path(get, path = "/market/{model_id}/bids",
+        params(("model_id" = String, Path, description = "Model ID")),
+        responses((status = 200, description = "Ok", body = [CompetitorBid])))]
+    async fn list_bids(
+        Path(model_id): Path<ModelId>,
+        State(deps): State<Deps>,
+    ) -> Json<Vec<CompetitorBid>> {
+        Json(deps.market_repo.bids(&model_id).await)
+    }
+}
+
+mod dead_letters {
+    use super::*;
+    use crate::types::{DeadLetter, DeadLetterId, WorkItem, WorkStage};
+
+    #[derive(Clone)]
+    pub struct Deps {
+        processor: RequestProcessor,
+        model_repo: Arc<dyn ModelRepo + Send + Sync>,
+    }
+
+    impl Deps {
+        pub fn new(
+            processor: RequestProcessor,
+            model_repo: Arc<dyn ModelRepo + Send + Sync>,
+        ) -> Self {
+            Self { processor, model_repo }
+        }
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(
+        paths(list_dead_letters, retry_dead_letter, delete_dead_letter),
+        components(schemas(DeadLetter, WorkItem, WorkStage))
+    )]
+    pub struct DeadLettersApi;
+
+    pub fn routes() -> Router<Deps> {
+        Router::new()
+            .route("/dead-letters", get(list_dead_letters))
+            .route("/dead-letters/:id", delete(delete_dead_letter))
+            .route("/dead-letters/:id/retry", post(retry_dead_letter))
+    }
+
+    /// List requests that failed, oldest first.
+    #[utoipa::path(get, path = "/dead-letters",
+        responses((status = 200, description = "Ok", body = [DeadLetter])))]
+    async fn list_dead_letters(State(deps): State<Deps>) -> Json<Vec<DeadLetter>> {
+        Json(deps.processor.dead_letters().await)
+    }
+
+    /// Retry a failed request from the step it failed at. The request is processed in the
+    /// background, and moved back to the dead letters if it fails again.
+    #[utoipa::path(post, path = "/dead-letters/{id}/retry",
+        params(("id" = u64, Path, description = "Dead letter ID")),
+        responses(
+            (status = 202, description = "Retrying"),
+            (status = 404, description = "Not found"),
+            (status = 409, description = "Model is not served")))]
+    async fn retry_dead_letter(
+        Path(id): Path<DeadLetterId>,
+        State(deps): State<Deps>,
+    ) -> StatusCode {
+        let letters = deps.processor.dead_letters().await;
+        let Some(letter) = letters.into_iter().find(|letter| letter.id == id) else {
+            return StatusCode::NOT_FOUND;
+        };
+        let Some(model) = deps.model_repo.get_by_model_id(&letter.item.model_id).await else {
+            return StatusCode::CONFLICT;
+        };
+        if deps.processor.retry(id, model).await {
+            StatusCode::ACCEPTED
+        } else {
+            StatusCode::NOT_FOUND
+        }
+    }
+
+    /// Discard a failed request. It counts as unanswered towards its agreement.
+    #[utoipa::path(delete, path = "/dead-letters/{id}",
+        params(("id" = u64, Path, description = "Dead letter ID")),
+        responses(
+            (status = 204, description = "Discarded"),
+            (status = 404, description = "Not found")))]
+    async fn delete_dead_letter(
+        Path(id): Path<DeadLetterId>,
+        State(deps): State<Deps>,
+    ) -> StatusCode {
+        if deps.processor.discard(id).await {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::NOT_FOUND
+        }
+    }
+}
+
+mod events {
+    use std::{convert::Infallible, time::Duration};
+
+    use axum::{
+        extract::ws::{Message, WebSocket, WebSocketUpgrade},
+        response::{
+            sse::{Event, KeepAlive},
+            IntoResponse, Response, Sse,
+        },
+    };
+    use tokio::sync::broadcast::error::RecvError;
+    use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+
+    use super::*;
+    use crate::events::{ChainEventName, EventKind, PredictionStatus, ReplicaHealth, WingmanEvent};
+
+    /// How often an idle event stream is kept alive.
+    const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+    #[derive(Clone)]
+    pub struct Deps {
+        events: EventHub,
+    }
+
+    impl Deps {
+        pub fn new(events: EventHub) -> Self {
+            Self { events }
+        }
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(
+        paths(stream_events),
+        components(schemas(
+            WingmanEvent,
+            EventKind,
+            ChainEventName,
+            PredictionStatus,
+            ReplicaHealth
+        ))
+    )]
+    pub struct EventsApi;
+
+    pub fn routes() -> Router<Deps> {
+        Router::new().route("/events", get(stream_events))
+    }
+
+    /// Stream lifecycle events as they happen: chain events received, bids, the progress of
+    /// requests and replica health transitions. Served as server-sent events, or as JSON text
+    /// messages when upgraded to a WebSocket. Clients falling behind skip the oldest events.
+    #[utoipa::path(get, path = "/events",
+        responses((status = 200, description = "Stream of events", body = WingmanEvent,
+            content_type = "text/event-stream")))]
+    async fn stream_events(ws: Option<WebSocketUpgrade>, State(deps): State<Deps>) -> Response {
+        match ws {
+            Some(ws) => ws.on_upgrade(move |socket| forward(socket, deps.events)).into_response(),
+            None => {
+                let stream = BroadcastStream::new(deps.events.subscribe())
+                    .filter_map(Result::ok)
+                    .map(|event| Event::default().json_data(event))
+                    .filter_map(Result::ok)
+                    .map(Ok::<_, Infallible>);
+                Sse::new(stream)
+                    .keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL))
+                    .into_response()
+            },
+        }
+    }
+
+    /// Forward events to a WebSocket until the client disconnects.
+    async fn forward(mut socket: WebSocket, events: EventHub) {
+        let mut rx = events.subscribe();
+        loop {
+            tokio::select! {
+                event = rx.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::debug!("Event stream skipped {skipped} events");
+                            continue;
+                        },
+                        Err(RecvError::Closed) => return,
+                    };
+                    let Ok(text) = serde_json::to_string(&event) else { continue };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        return;
+                    }
+                },
+                message = socket.recv() => {
+                    if !matches!(message, Some(Ok(_))) {
+                        return;
+                    }
+                },
+            }
+        }
+    }
 }
 
 mod check {
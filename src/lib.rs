@@ -4,60 +4,185 @@
 
 use std::{future::Future, sync::Arc};
 
-use tokio::{
-    signal,
-    sync::broadcast::{channel, Receiver, Sender},
-};
+use tokio::{signal, sync::mpsc};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
+    balancer::{HealthMonitor, ReplicaPool},
+    cache::ResultCache,
     config::Config,
-    data::{ModelRepo, ModelRepoFac},
-    engine::{BidEngine, Engine, ExecutionEngine},
+    data::{
+        AgreementRepo, AgreementRepoFac, ConsumerRepo, ConsumerRepoFac, HistoryPruner, HistoryRepo,
+        HistoryRepoFac, LedgerRepo, LedgerRepoFac, MarketRepoFac, ModelRepoFac, WorkQueue,
+        WorkQueueFac,
+    },
+    engine::{
+        AdmissionControl, BidEngine, Engine, EventRelay, ExecutionEngine, MarketWatcher,
+        RequestProcessor,
+    },
+    events::EventHub,
     http::HttpServer,
-    protocol::{AiroClient, ChainEvent, ChainListener, Protocol, TxSubmitter},
+    protocol::{AiroClient, ChainListener, EventBus},
+    reload::ConfigReloader,
+    signer::ProviderKey,
     types::Result,
 };
 
+pub mod backend;
+pub mod balancer;
+pub mod batcher;
+pub mod cache;
+pub mod cli;
 pub mod cog;
 pub mod config;
 pub mod data;
 pub mod engine;
+pub mod events;
 pub mod http;
+pub mod policy;
 pub mod protocol;
+pub mod reload;
+pub mod signer;
 pub mod types;
 pub mod utils;
 
+/// Number of chain events queued per engine before the chain listener waits for it.
+const EVENT_QUEUE_CAPACITY: usize = 1024;
+/// Number of retried dead letters queued before retrying waits for the execution engine.
+const RETRY_QUEUE_CAPACITY: usize = 64;
+
 pub async fn start() -> Result<()> {
-    let Config { http_port, airo_node, airo_suri } = Config::new();
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
         .with(tracing_subscriber::EnvFilter::from_default_env())
         .init();
+    let mut config = Config::load().map_err(|e| {
+        tracing::error!("🚫 {e}");
+        e
+    })?;
+    let data_dir = config.storage.data_dir.clone();
+    let retention = config.storage.retention();
 
     let tracker = TaskTracker::new();
     let token = CancellationToken::new();
     tracker.spawn_shutdown_listener(token.clone());
 
-    let airo_client = AiroClient::new(&airo_node, &airo_suri).await.map_err(|e| {
+    // Taking the signer config out drops the secret as soon as the key is derived
+    let signer = config.signer.take().expect("signer is validated");
+    let signer = ProviderKey::load(&signer).map_err(|e| {
+        tracing::error!("🚫 Failed to load the signing key: {e}");
+        e
+    })?;
+    let airo_client = AiroClient::new(&config.node.url, signer).await.map_err(|e| {
         tracing::error!("🚫 Failed to connect to airo node: {e}");
         e
     })?;
     let airo_client = Arc::new(airo_client);
-    let (chain_tx, chain_rx_bid) = channel(128);
-    let chain_rx_exec = chain_tx.subscribe();
-    tracker.spawn_chain_listener(token.clone(), airo_client.clone(), chain_tx);
-
+    let mut bus = EventBus::new();
+    let chain_rx_bid = bus.subscribe("bid_engine", EVENT_QUEUE_CAPACITY);
+    let chain_rx_exec = bus.subscribe("execution_engine", EVENT_QUEUE_CAPACITY);
+    let chain_rx_market = bus.subscribe("market_watcher", EVENT_QUEUE_CAPACITY);
+    let chain_rx_relay = bus.subscribe("event_relay", EVENT_QUEUE_CAPACITY);
     let model_repo = Arc::new(ModelRepoFac::in_memory());
-    tracker.spawn_http_server(token.clone(), http_port, model_repo.clone());
-    tracker.spawn_execution_engine(
+    let consumer_repo: Arc<dyn ConsumerRepo + Send + Sync> = match &data_dir {
+        Some(dir) => Arc::new(ConsumerRepoFac::file(&dir.join("consumers.jsonl")).await?),
+        None => Arc::new(ConsumerRepoFac::in_memory()),
+    };
+    let mut reloader = ConfigReloader::new(Config::path(), model_repo.clone())
+        .with_consumers(consumer_repo.clone());
+    let (bidding, engine_settings) = (reloader.bidding(), reloader.engine());
+    let (http_bind, cache_max_bytes) = (config.http.bind, config.cache.max_bytes);
+    reloader.apply(config).await;
+    tracker.spawn(critical_task("config_reloader", token.clone(), reloader.run(token.clone())));
+
+    let events = EventHub::new();
+    let replica_pool = Arc::new(ReplicaPool::with_events(events.clone()));
+    let health_monitor = HealthMonitor::new(replica_pool.clone(), model_repo.clone());
+    tracker.spawn(critical_task(
+        "health_monitor",
         token.clone(),
-        chain_rx_exec,
+        health_monitor.run(token.clone()),
+    ));
+    let result_cache = Arc::new(ResultCache::new(cache_max_bytes));
+    let history_repo: Arc<dyn HistoryRepo + Send + Sync> = match &data_dir {
+        Some(dir) => Arc::new(HistoryRepoFac::file(&dir.join("history.jsonl")).await?),
+        None => Arc::new(HistoryRepoFac::in_memory()),
+    };
+    let history_pruner = HistoryPruner::new(history_repo.clone(), retention);
+    tracker.spawn(critical_task(
+        "history_pruner",
+        token.clone(),
+        history_pruner.run(token.clone()),
+    ));
+    let ledger_repo: Arc<dyn LedgerRepo + Send + Sync> = match &data_dir {
+        Some(dir) => Arc::new(LedgerRepoFac::file(&dir.join("ledger.jsonl")).await?),
+        None => Arc::new(LedgerRepoFac::in_memory()),
+    };
+    let agreement_repo: Arc<dyn AgreementRepo + Send + Sync> = match &data_dir {
+        Some(dir) => Arc::new(AgreementRepoFac::file(&dir.join("agreements.jsonl")).await?),
+        None => Arc::new(AgreementRepoFac::in_memory()),
+    };
+    let work_queue: Arc<dyn WorkQueue + Send + Sync> = match &data_dir {
+        Some(dir) => Arc::new(WorkQueueFac::file(&dir.join("queue.jsonl")).await?),
+        None => Arc::new(WorkQueueFac::in_memory()),
+    };
+    let market_repo = Arc::new(MarketRepoFac::in_memory());
+    let (retry_tx, retry_rx) = mpsc::channel(RETRY_QUEUE_CAPACITY);
+    let processor = RequestProcessor::new(
         airo_client.clone(),
+        replica_pool.clone(),
+        result_cache,
+        history_repo.clone(),
+        agreement_repo.clone(),
+        ledger_repo.clone(),
+        work_queue,
+    )
+    .with_events(events.clone())
+    .with_retries(retry_tx)
+    .with_tracker(tracker.clone(), token.clone());
+    let http = HttpServer::new(
+        http_bind,
+        processor.clone(),
         model_repo.clone(),
+        airo_client.clone(),
+        market_repo.clone(),
+        events.clone(),
+    )
+    .with_consumers(consumer_repo)
+    .with_engine_settings(engine_settings.clone());
+    tracker.spawn_http_server(token.clone(), http);
+
+    let mut execution_engine = ExecutionEngine::new(
+        chain_rx_exec,
+        retry_rx,
+        processor,
+        model_repo.clone(),
+        engine_settings,
+    );
+    // Resumed before listening, so that requests left by a previous run are scheduled first
+    execution_engine.resume_queued().await?;
+    // Loaded before listening as well, the events then cover the orders and bids placed after
+    let market_watcher = MarketWatcher::new(chain_rx_market, market_repo.clone());
+    market_watcher.load(airo_client.as_ref(), airo_client.provider()).await?;
+    tracker.spawn_chain_listener(token.clone(), airo_client.clone(), bus);
+    tracker.spawn_engine(token.clone(), "execution_engine", execution_engine);
+    tracker.spawn_engine(token.clone(), "market_watcher", market_watcher);
+
+    let event_relay = EventRelay::new(chain_rx_relay, events);
+    tracker.spawn_engine(token.clone(), "event_relay", event_relay);
+
+    let admission = AdmissionControl::new(replica_pool, agreement_repo, airo_client.clone());
+    let bid_engine = BidEngine::new(
+        chain_rx_bid,
+        airo_client,
+        model_repo,
+        admission,
+        ledger_repo,
+        market_repo,
+        bidding,
     );
-    tracker.spawn_bid_engine(token, chain_rx_bid, airo_client, model_repo);
+    tracker.spawn_engine(token, "bid_engine", bid_engine);
 
     tracker.close();
     tracker.wait().await;
@@ -81,31 +206,14 @@ trait TaskTrackerEx {
         &self,
         token: CancellationToken,
         chain_listener: Arc<dyn ChainListener + Send + Sync>,
-        chain_tx: Sender<ChainEvent>,
-    );
-
-    fn spawn_http_server(
-        &self,
-        token: CancellationToken,
-        port: u16,
-        model_repo: Arc<dyn ModelRepo + Send + Sync>,
+        bus: EventBus,
     );
 
-    fn spawn_bid_engine(
-        &self,
-        token: CancellationToken,
-        chain_rx: Receiver<ChainEvent>,
-        tx_sender: Arc<dyn TxSubmitter + Send + Sync>,
-        model_repo: Arc<dyn ModelRepo + Send + Sync>,
-    );
+    fn spawn_http_server(&self, token: CancellationToken, http: HttpServer);
 
-    fn spawn_execution_engine(
-        &self,
-        token: CancellationToken,
-        chain_rx: Receiver<ChainEvent>,
-        protocol_client: Arc<dyn Protocol + Send + Sync>,
-        model_repo: Arc<dyn ModelRepo + Send + Sync>,
-    );
+    fn spawn_engine<E>(&self, token: CancellationToken, name: &'static str, engine: E)
+    where
+        E: Engine + Send + 'static;
 
     fn spawn_shutdown_listener(&self, token: CancellationToken);
 }
@@ -115,20 +223,14 @@ impl TaskTrackerEx for TaskTracker {
         &self,
         token: CancellationToken,
         chain_listener: Arc<dyn ChainListener + Send + Sync>,
-        chain_tx: Sender<ChainEvent>,
+        bus: EventBus,
     ) {
         self.spawn(critical_task("chain_listener", token.clone(), async move {
-            chain_listener.listen(token, chain_tx).await
+            chain_listener.listen(token, bus).await
         }));
     }
 
-    fn spawn_http_server(
-        &self,
-        token: CancellationToken,
-        port: u16,
-        model_repo: Arc<dyn ModelRepo + Send + Sync>,
-    ) {
-        let http = HttpServer::new(port, model_repo);
+    fn spawn_http_server(&self, token: CancellationToken, http: HttpServer) {
         self.spawn(critical_task(
             "http_server",
             token.clone(),
@@ -136,30 +238,11 @@ impl TaskTrackerEx for TaskTracker {
         ));
     }
 
-    fn spawn_bid_engine(
-        &self,
-        token: CancellationToken,
-        chain_rx: Receiver<ChainEvent>,
-        tx_sender: Arc<dyn TxSubmitter + Send + Sync>,
-        model_repo: Arc<dyn ModelRepo + Send + Sync>,
-    ) {
-        let mut bid_engine = BidEngine::new(chain_rx, tx_sender, model_repo);
-        self.spawn(critical_task("bid_engine", token.clone(), async move {
-            bid_engine.run(token).await
-        }));
-    }
-
-    fn spawn_execution_engine(
-        &self,
-        token: CancellationToken,
-        chain_rx: Receiver<ChainEvent>,
-        protocol_client: Arc<dyn Protocol + Send + Sync>,
-        model_repo: Arc<dyn ModelRepo + Send + Sync>,
-    ) {
-        let mut execution_engine = ExecutionEngine::new(chain_rx, protocol_client, model_repo);
-        self.spawn(critical_task("execution_engine", token.clone(), async move {
-            execution_engine.run(token).await
-        }));
+    fn spawn_engine<E>(&self, token: CancellationToken, name: &'static str, mut engine: E)
+    where
+        E: Engine + Send + 'static,
+    {
+        self.spawn(critical_task(name, token.clone(), async move { engine.run(token).await }));
     }
 
     fn spawn_shutdown_listener(&self, token: CancellationToken) {
@@ -3,11 +3,14 @@
 #![warn(missing_docs)]
 #![allow(dead_code)] // TODO. Remove.
 
+use airo_wingman::cli::{self, Cli};
+use clap::Parser;
+
 use crate::types::Result;
 
 mod types;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    airo_wingman::start().await
+    cli::run(Cli::parse()).await
 }
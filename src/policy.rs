@@ -0,0 +1,276 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use jsonschema::JSONSchema;
+use regex::Regex;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    backend::Status,
+    types::{stdResult, ContentCheck, ExecutionResult, Result},
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid JSON schema: {0}")]
+    InvalidSchema(String),
+}
+
+/// Step of the processing of a request content is checked at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Stage {
+    Input,
+    Output,
+}
+
+/// Why content was rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rejection {
+    /// Code of the check that rejected the content, e.g. `keyword_matched`.
+    pub code: String,
+    pub message: String,
+}
+
+impl Rejection {
+    fn new(code: &str, message: String) -> Self {
+        Self { code: code.to_owned(), message }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl From<Rejection> for ExecutionResult {
+    fn from(rejection: Rejection) -> Self {
+        Self {
+            status: Status::Failed.to_string(),
+            output: None,
+            error: Some(rejection.message),
+            started_at: None,
+            completed_at: None,
+            usage: None,
+            policy_code: Some(rejection.code),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ModerationRequest<'a> {
+    stage: Stage,
+    model: &'a str,
+    content: &'a Value,
+}
+
+#[derive(Deserialize)]
+struct ModerationResponse {
+    allowed: bool,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// A check ready to run, with its schema or patterns compiled.
+enum CompiledCheck {
+    Schema(Box<JSONSchema>),
+    MaxSize(usize),
+    Regex(Vec<Regex>),
+    /// Lowercased keywords.
+    Keywords(Vec<String>),
+    Moderation {
+        url: String,
+        timeout_secs: u64,
+    },
+}
+
+impl CompiledCheck {
+    fn new(check: &ContentCheck) -> Result<Self> {
+        Ok(match check {
+            ContentCheck::Schema { schema } => Self::Schema(compile_schema(schema)?),
+            ContentCheck::MaxSize { max_bytes } => Self::MaxSize(*max_bytes),
+            ContentCheck::Regex { patterns } => {
+                Self::Regex(patterns.iter().map(|p| Regex::new(p)).collect::<stdResult<_, _>>()?)
+            },
+            ContentCheck::Keywords { keywords } => {
+                Self::Keywords(keywords.iter().map(|keyword| keyword.to_lowercase()).collect())
+            },
+            ContentCheck::Moderation { url, timeout_secs } => {
+                Self::Moderation { url: url.clone(), timeout_secs: *timeout_secs }
+            },
+        })
+    }
+}
+
+/// Checks of a model at a stage, compiled.
+struct CompiledChecks {
+    /// The checks as configured, to notice when the model changes.
+    checks: Vec<ContentCheck>,
+    compiled: Vec<CompiledCheck>,
+}
+
+/// Runs the content checks of models. Schemas and patterns are compiled once per model and
+/// stage, and again whenever the checks of the model change.
+#[derive(Default)]
+pub struct ContentFilter {
+    http: Client,
+    compiled: DashMap<(String, Stage), Arc<CompiledChecks>>,
+}
+
+impl ContentFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run checks in order, up to the first rejecting the content. Fails if a check could not be
+    /// run, e.g. because the moderation service is unavailable.
+    pub async fn check(
+        &self,
+        model: &str,
+        checks: &[ContentCheck],
+        stage: Stage,
+        content: &Value,
+    ) -> Result<Option<Rejection>> {
+        if checks.is_empty() {
+            return Ok(None);
+        }
+        let compiled = self.compiled(model, checks, stage)?;
+        for check in &compiled.compiled {
+            if let Some(rejection) = self.run(model, check, stage, content).await? {
+                tracing::info!("⛔ {stage} of {model} rejected: {}", rejection.message);
+                return Ok(Some(rejection));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checks of the model at the stage, compiled on first use or after they changed.
+    fn compiled(
+        &self,
+        model: &str,
+        checks: &[ContentCheck],
+        stage: Stage,
+    ) -> Result<Arc<CompiledChecks>> {
+        let key = (model.to_owned(), stage);
+        let cached = self.compiled.get(&key).map(|kv| kv.value().clone());
+        if let Some(compiled) = cached.filter(|compiled| compiled.checks == checks) {
+            return Ok(compiled);
+        }
+        let compiled = checks.iter().map(CompiledCheck::new).collect::<Result<_>>()?;
+        let compiled = Arc::new(CompiledChecks { checks: checks.to_vec(), compiled });
+        self.compiled.insert(key, compiled.clone());
+        Ok(compiled)
+    }
+
+    async fn run(
+        &self,
+        model: &str,
+        check: &CompiledCheck,
+        stage: Stage,
+        content: &Value,
+    ) -> Result<Option<Rejection>> {
+        let rejection = match check {
+            CompiledCheck::Schema(schema) => {
+                let errors = match schema.validate(content) {
+                    Ok(()) => return Ok(None),
+                    Err(errors) => errors.map(|e| e.to_string()).collect::<Vec<_>>(),
+                };
+                Rejection::new(
+                    "schema_violation",
+                    format!("Invalid {stage}: {}", errors.join(", ")),
+                )
+            },
+            CompiledCheck::MaxSize(max_bytes) => {
+                let size = serde_json::to_vec(content)?.len();
+                if size <= *max_bytes {
+                    return Ok(None);
+                }
+                Rejection::new(
+                    "size_exceeded",
+                    format!("The {stage} of {size} bytes exceeds {max_bytes} bytes"),
+                )
+            },
+            CompiledCheck::Regex(regexes) => {
+                let texts = texts(content);
+                for regex in regexes {
+                    if texts.iter().any(|text| regex.is_match(text)) {
+                        let message = format!("The {stage} matches a forbidden pattern");
+                        return Ok(Some(Rejection::new("pattern_matched", message)));
+                    }
+                }
+                return Ok(None);
+            },
+            CompiledCheck::Keywords(keywords) => {
+                let texts: Vec<_> = texts(content).iter().map(|text| text.to_lowercase()).collect();
+                let keyword = keywords
+                    .iter()
+                    .find(|keyword| texts.iter().any(|text| text.contains(keyword.as_str())));
+                if keyword.is_none() {
+                    return Ok(None);
+                }
+                Rejection::new(
+                    "keyword_matched",
+                    format!("The {stage} contains a forbidden keyword"),
+                )
+            },
+            CompiledCheck::Moderation { url, timeout_secs } => {
+                let request = ModerationRequest { stage, model, content };
+                let response: ModerationResponse = self
+                    .http
+                    .post(url)
+                    .timeout(Duration::from_secs(*timeout_secs))
+                    .json(&request)
+                    .send()
+                    .await
+                    .and_then(Response::error_for_status)?
+                    .json()
+                    .await?;
+                if response.allowed {
+                    return Ok(None);
+                }
+                let reason = response.reason.unwrap_or_else(|| "no reason given".to_owned());
+                Rejection::new("moderation_rejected", format!("The {stage} was rejected: {reason}"))
+            },
+        };
+        Ok(Some(rejection))
+    }
+}
+
+/// Validate a check, e.g. that its patterns compile. Returns the problem found, if any.
+pub fn validate_check(check: &ContentCheck) -> Option<String> {
+    match check {
+        ContentCheck::Schema { schema } => compile_schema(schema).err().map(|e| e.to_string()),
+        ContentCheck::MaxSize { max_bytes: 0 } => Some("max_bytes: must be positive".to_owned()),
+        ContentCheck::Regex { patterns } => patterns
+            .iter()
+            .find_map(|pattern| Regex::new(pattern).err())
+            .map(|e| e.to_string()),
+        ContentCheck::Keywords { keywords } if keywords.iter().any(String::is_empty) => {
+            Some("keywords: must not be empty".to_owned())
+        },
+        ContentCheck::Moderation { url, .. } => {
+            reqwest::Url::parse(url).err().map(|e| format!("url: {e}"))
+        },
+        _ => None,
+    }
+}
+
+fn compile_schema(schema: &Value) -> Result<Box<JSONSchema>> {
+    let schema = JSONSchema::compile(schema).map_err(|e| Error::InvalidSchema(e.to_string()))?;
+    Ok(Box::new(schema))
+}
+
+/// All strings in a JSON value, object keys excluded.
+fn texts(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(text) => vec![text],
+        Value::Array(values) => values.iter().flat_map(texts).collect(),
+        Value::Object(map) => map.values().flat_map(texts).collect(),
+        _ => Vec::new(),
+    }
+}
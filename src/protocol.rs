@@ -1,41 +1,70 @@
 use async_trait::async_trait;
-use std::str::FromStr;
+use serde::Serialize;
 use subxt::{
     backend::{legacy::rpc_methods::Bytes, rpc::RpcClient},
+    blocks::ExtrinsicEvents,
     config::{
         substrate::{BlakeTwo256, SubstrateHeader},
         Hasher as HasherT, SubstrateExtrinsicParams,
     },
     custom_values::Yes,
+    error::DispatchError,
     events::StaticEvent,
+    ext::{codec::Decode, scale_value::At},
+    metadata::types::StorageEntryType,
     rpc_params,
     storage::Address,
-    utils::{AccountId32, MultiAddress, MultiSignature, H256},
+    tx::{Payload, TxStatus},
+    utils::{MultiAddress, MultiSignature, H256},
     Config, OnlineClient,
 };
-use subxt_signer::{sr25519::Keypair, SecretUri};
 use thiserror::Error;
-use tokio::sync::broadcast::Sender;
+use tokio::sync::mpsc::{self, error::TrySendError};
 use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
 
-use crate::types::{
-    AgreementDetails, AgreementId, Balance, ContentId, Hasher, ModelId, OrderId, Result,
+use crate::{
+    signer::ProviderKey,
+    types::{
+        AccountId, AgreementDetails, AgreementId, Balance, ContentId, Hasher, ModelId,
+        OrderDetails, OrderId, Result, TxReceipt,
+    },
 };
 
 #[subxt::subxt(runtime_metadata_path = "metadata.scale")]
 mod airo {
-    use runtime_types::pallet_execution::types::AgreementDetails as RuntimeAgreementDetails;
+    use runtime_types::{
+        pallet_execution::types::AgreementDetails as RuntimeAgreementDetails,
+        pallet_market::types::OrderDetails as RuntimeOrderDetails,
+    };
 
-    use crate::types::{AgreementDetails, ModelId};
+    use crate::types::{AgreementDetails, ModelId, OrderDetails};
 
     impl From<RuntimeAgreementDetails> for AgreementDetails {
         fn from(value: RuntimeAgreementDetails) -> Self {
-            Self { model_id: value.model_id }
+            Self {
+                consumer: value.consumer,
+                provider: value.provider,
+                model_id: value.model_id,
+                price_per_request: value.price_per_request,
+                royalty_per_request: value.royalty_per_request,
+                requests_count: value.requests_count,
+                requests_total: value.requests_total,
+            }
+        }
+    }
+
+    impl From<RuntimeOrderDetails> for OrderDetails {
+        fn from(value: RuntimeOrderDetails) -> Self {
+            Self {
+                consumer: value.consumer,
+                model_id: value.model_id,
+                requests_total: value.requests_total,
+            }
         }
     }
 }
 
-type AccountId = AccountId32;
 type Block = subxt::blocks::Block<RuntimeConfig, Client>;
 type Client = OnlineClient<RuntimeConfig>;
 
@@ -51,7 +80,12 @@ impl Config for RuntimeConfig {
     type AssetId = u32;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, strum::EnumDiscriminants)]
+#[strum_discriminants(
+    name(ChainEventName),
+    derive(Serialize, ToSchema),
+    serde(rename_all = "snake_case")
+)]
 pub enum ChainEvent {
     /// A new order has been created.
     OrderCreated {
@@ -60,10 +94,30 @@ pub enum ChainEvent {
         /// The model ID.
         model_id: ModelId,
     },
-    /// A bid has been accepted.
+    /// A bid has been placed, by this provider or a competitor.
+    BidCreated {
+        /// The order ID.
+        order_id: OrderId,
+        /// The bidding provider.
+        provider: AccountId,
+        /// The price per request offered.
+        price_per_request: Balance,
+        /// Whether the bid was placed by this provider.
+        own: bool,
+    },
+    /// A bid has been accepted, by this provider or a competitor.
     BidAccepted {
         /// The order ID.
         order_id: OrderId,
+        /// The provider of the accepted bid.
+        provider: AccountId,
+        /// Whether the bid was placed by this provider.
+        own: bool,
+    },
+    /// An agreement has been created from an accepted bid. The agreement ID is the order ID.
+    AgreementCreated {
+        /// The agreement ID.
+        agreement_id: AgreementId,
     },
     /// A request has been created.
     RequestCreated {
@@ -74,25 +128,42 @@ pub enum ChainEvent {
         /// The content ID.
         content_id: ContentId,
     },
+    /// A response has been recorded.
+    ResponseCreated {
+        /// The agreement ID.
+        agreement_id: AgreementId,
+        /// The request index.
+        request_index: u32,
+        /// The content ID of the result.
+        content_id: ContentId,
+    },
 }
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Failed to get the next block")]
     NextBlock,
+    #[error("Unexpected account info layout")]
+    UnexpectedAccountInfo,
+    #[error("Subscriber {0} stopped receiving chain events")]
+    SubscriberClosed(&'static str),
+    #[error("The request has been responded to already")]
+    ResponseAlreadyExists,
+    #[error("The keys of storage {0} can not be decoded")]
+    UndecodableKey(&'static str),
+    #[error("The transaction was not included in a block: {0}")]
+    TxNotIncluded(String),
 }
 
 pub struct AiroClient {
     rpc: RpcClient,
     client: Client,
-    signer: Keypair,
+    signer: ProviderKey,
     provider: AccountId,
 }
 
 impl AiroClient {
-    pub async fn new(url: &str, secret_uri: &str) -> Result<Self> {
-        let uri = SecretUri::from_str(secret_uri)?;
-        let signer = Keypair::from_uri(&uri)?;
+    pub async fn new(url: &str, signer: ProviderKey) -> Result<Self> {
         let provider = signer.public_key().to_account_id();
 
         // TODO. It might make sense to reconnect automatically
@@ -104,28 +175,111 @@ impl AiroClient {
         Ok(Self { rpc, client, signer, provider })
     }
 
+    /// Account of the provider the client signs transactions for.
+    pub fn provider(&self) -> &AccountId {
+        &self.provider
+    }
+
+    /// Free balance of the provider. The bundled metadata covers Airo pallets only, so the
+    /// account is queried dynamically.
+    pub async fn free_balance(&self) -> Result<Balance> {
+        let key = vec![subxt::dynamic::Value::from_bytes(self.provider.0)];
+        let query = subxt::dynamic::storage("System", "Account", key);
+        let Some(account) = self.fetch(query).await? else {
+            return Ok(0);
+        };
+        let free = account.to_value()?.at("data").at("free").and_then(|free| free.as_u128());
+        free.ok_or_else(|| Error::UnexpectedAccountInfo.into())
+    }
+
     async fn fetch<'a, K, V>(&self, query: K) -> Result<Option<V>>
     where
         K: Address<IsFetchable = Yes, Target = V> + 'a,
     {
         self.client.storage().at_latest().await?.fetch(&query).await.map_err(Into::into)
     }
+
+    /// Sign and submit a transaction. The fee is the one reported by its `TransactionFeePaid`
+    /// event, unknown if the runtime does not emit one.
+    async fn submit<Call: Payload>(&self, call: &Call) -> Result<TxReceipt> {
+        let tx = self.client.tx().create_signed(call, &self.signer, Default::default()).await?;
+        // Waiting for the outcome surfaces the errors of the pallets, e.g. on duplicate responses.
+        // Finalization is not awaited, it would hold up the engines for several blocks
+        let mut progress = tx.submit_and_watch().await?;
+        let in_block = loop {
+            match progress
+                .next()
+                .await
+                .ok_or(Error::TxNotIncluded("the watch ended".to_owned()))??
+            {
+                TxStatus::InBestBlock(in_block) | TxStatus::InFinalizedBlock(in_block) => {
+                    break in_block
+                },
+                TxStatus::Error { message }
+                | TxStatus::Invalid { message }
+                | TxStatus::Dropped { message } => return Err(Error::TxNotIncluded(message).into()),
+                _ => continue,
+            }
+        };
+        let events = in_block.wait_for_success().await?;
+        let hash = events.extrinsic_hash();
+        let fee = paid_fee(&events)?;
+        if fee.is_none() {
+            tracing::warn!("⚠️ Fee of transaction {hash:?} is unknown");
+        }
+        Ok(TxReceipt { hash, fee })
+    }
+}
+
+/// Delivers chain events to every subscriber, in order. Each subscriber has a bounded queue of
+/// its own: when a subscriber falls behind, publishing waits for it rather than dropping events.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Vec<(&'static str, mpsc::Sender<ChainEvent>)>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe to all events published from now on. Up to `capacity` events are queued.
+    pub fn subscribe(&mut self, name: &'static str, capacity: usize) -> mpsc::Receiver<ChainEvent> {
+        let (tx, rx) = mpsc::channel(capacity);
+        self.subscribers.push((name, tx));
+        rx
+    }
+
+    /// Deliver an event to every subscriber. Fails if a subscriber is gone.
+    pub async fn publish(&self, event: ChainEvent) -> Result<()> {
+        for (name, tx) in &self.subscribers {
+            match tx.try_send(event.clone()) {
+                Ok(()) => {},
+                Err(TrySendError::Full(event)) => {
+                    tracing::warn!("⏳ Subscriber {name} is behind. Waiting for it to catch up");
+                    tx.send(event).await.map_err(|_| Error::SubscriberClosed(name))?;
+                },
+                Err(TrySendError::Closed(_)) => return Err(Error::SubscriberClosed(name).into()),
+            }
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
 pub trait ChainListener {
-    async fn listen(&self, token: CancellationToken, sender: Sender<ChainEvent>) -> Result<()>;
+    async fn listen(&self, token: CancellationToken, bus: EventBus) -> Result<()>;
 }
 
 #[async_trait]
 impl ChainListener for AiroClient {
-    async fn listen(&self, token: CancellationToken, sender: Sender<ChainEvent>) -> Result<()> {
+    async fn listen(&self, token: CancellationToken, bus: EventBus) -> Result<()> {
         // TODO. Handle `subscribe_best` might result in a possible rollback.
         let mut blocks_sub = self.client.blocks().subscribe_best().await?;
         while let Some(block) = blocks_sub.next().await {
             tokio::select! {
                 _ = token.cancelled() => return Ok(()),
-                result = process_block(block?, &self.provider, &sender) => result?,
+                result = process_block(block?, &self.provider, &bus) => result?,
             }
         }
 
@@ -134,14 +288,10 @@ impl ChainListener for AiroClient {
     }
 }
 
-async fn process_block(
-    block: Block,
-    provider: &AccountId,
-    sender: &Sender<ChainEvent>,
-) -> Result<()> {
+async fn process_block(block: Block, provider: &AccountId, bus: &EventBus) -> Result<()> {
     use airo::{
-        airo_execution::events::RequestCreated,
-        airo_market::events::{BidAccepted, OrderCreated},
+        airo_execution::events::{AgreementCreated, RequestCreated, ResponseCreated},
+        airo_market::events::{BidAccepted, BidCreated, OrderCreated},
     };
 
     let events = block.events().await?;
@@ -156,17 +306,34 @@ async fn process_block(
                 if let Some(event) = event.as_event::<OrderCreated>()? {
                     let order_id = event.order_id;
                     let model_id = event.model_id;
-                    sender.send(ChainEvent::OrderCreated { order_id, model_id })?;
+                    bus.publish(ChainEvent::OrderCreated { order_id, model_id }).await?;
+                }
+            },
+            (BidCreated::PALLET, BidCreated::EVENT) => {
+                if let Some(event) = event.as_event::<BidCreated>()? {
+                    bus.publish(ChainEvent::BidCreated {
+                        order_id: event.order_id,
+                        own: event.provider.eq(provider),
+                        provider: event.provider,
+                        price_per_request: event.price_per_request,
+                    })
+                    .await?;
                 }
             },
             (BidAccepted::PALLET, BidAccepted::EVENT) => {
                 if let Some(event) = event.as_event::<BidAccepted>()? {
-                    if event.provider.ne(provider) {
-                        // Skip events referencing other providers
-                        continue;
-                    }
-                    let order_id = event.order_id;
-                    sender.send(ChainEvent::BidAccepted { order_id })?;
+                    bus.publish(ChainEvent::BidAccepted {
+                        order_id: event.order_id,
+                        own: event.provider.eq(provider),
+                        provider: event.provider,
+                    })
+                    .await?;
+                }
+            },
+            (AgreementCreated::PALLET, AgreementCreated::EVENT) => {
+                if let Some(event) = event.as_event::<AgreementCreated>()? {
+                    let agreement_id = event.agreement_id;
+                    bus.publish(ChainEvent::AgreementCreated { agreement_id }).await?;
                 }
             },
             (RequestCreated::PALLET, RequestCreated::EVENT) => {
@@ -174,11 +341,25 @@ async fn process_block(
                     let agreement_id = event.agreement_id;
                     let request_index = event.request_index;
                     let content_id = event.content_id;
-                    sender.send(ChainEvent::RequestCreated {
+                    bus.publish(ChainEvent::RequestCreated {
                         agreement_id,
                         request_index,
                         content_id,
-                    })?;
+                    })
+                    .await?;
+                }
+            },
+            (ResponseCreated::PALLET, ResponseCreated::EVENT) => {
+                if let Some(event) = event.as_event::<ResponseCreated>()? {
+                    let agreement_id = event.agreement_id;
+                    let request_index = event.request_index;
+                    let content_id = event.content_id;
+                    bus.publish(ChainEvent::ResponseCreated {
+                        agreement_id,
+                        request_index,
+                        content_id,
+                    })
+                    .await?;
                 }
             },
             _ => {},
@@ -189,22 +370,21 @@ async fn process_block(
 
 #[async_trait]
 pub trait TxSubmitter {
-    async fn bid_create(&self, order_id: OrderId, price_per_request: Balance) -> Result<()>;
+    async fn bid_create(&self, order_id: OrderId, price_per_request: Balance) -> Result<TxReceipt>;
 
     async fn response_create(
         &self,
         agreement_id: AgreementId,
         request_index: u32,
         content_id: ContentId,
-    ) -> Result<()>;
+    ) -> Result<TxReceipt>;
 }
 
 #[async_trait]
 impl TxSubmitter for AiroClient {
-    async fn bid_create(&self, order_id: OrderId, price_per_request: Balance) -> Result<()> {
+    async fn bid_create(&self, order_id: OrderId, price_per_request: Balance) -> Result<TxReceipt> {
         let tx = airo::tx().airo_market().bid_create(order_id, price_per_request);
-        let _hash = self.client.tx().sign_and_submit_default(&tx, &self.signer).await?;
-        Ok(())
+        self.submit(&tx).await
     }
 
     async fn response_create(
@@ -212,19 +392,38 @@ impl TxSubmitter for AiroClient {
         agreement_id: AgreementId,
         request_index: u32,
         content_id: ContentId,
-    ) -> Result<()> {
+    ) -> Result<TxReceipt> {
         let tx =
             airo::tx()
                 .airo_execution()
                 .response_create(agreement_id, request_index, content_id);
-        let _hash = self.client.tx().sign_and_submit_default(&tx, &self.signer).await?;
-        Ok(())
+        self.submit(&tx).await.map_err(|e| {
+            let exists = e
+                .downcast_ref()
+                .is_some_and(|e| is_module_error(e, "AiroExecution", "ResponseAlreadyExists"));
+            if exists {
+                Error::ResponseAlreadyExists.into()
+            } else {
+                e
+            }
+        })
     }
 }
 
+/// Whether a transaction failed with the given error of the given pallet.
+fn is_module_error(error: &subxt::Error, pallet: &str, variant: &str) -> bool {
+    let subxt::Error::Runtime(DispatchError::Module(error)) = error else {
+        return false;
+    };
+    error
+        .details()
+        .is_ok_and(|details| details.pallet.name() == pallet && details.variant.name == variant)
+}
+
 #[async_trait]
 pub trait StateReader {
     async fn get_agreement(&self, agreement_id: AgreementId) -> Result<Option<AgreementDetails>>;
+    async fn get_order(&self, order_id: OrderId) -> Result<Option<OrderDetails>>;
 }
 
 #[async_trait]
@@ -234,6 +433,85 @@ impl StateReader for AiroClient {
         let agreement = self.fetch(query).await?.map(Into::into);
         Ok(agreement)
     }
+
+    async fn get_order(&self, order_id: OrderId) -> Result<Option<OrderDetails>> {
+        let query = airo::storage().airo_market().orders(order_id);
+        let order = self.fetch(query).await?.map(Into::into);
+        Ok(order)
+    }
+}
+
+/// Fee reported by the `TransactionFeePaid` event of a transaction. The event is looked up by name,
+/// as the transaction payment pallet is not part of every runtime.
+fn paid_fee(events: &ExtrinsicEvents<RuntimeConfig>) -> Result<Option<Balance>> {
+    for event in events.iter() {
+        let event = event?;
+        if event.pallet_name() == "TransactionPayment"
+            && event.variant_name() == "TransactionFeePaid"
+        {
+            let fee = event.field_values()?.at("actual_fee").and_then(|fee| fee.as_u128());
+            return Ok(fee);
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the open orders and bids of the market, e.g. to learn of the bids placed before start.
+#[async_trait]
+pub trait MarketReader {
+    /// Open orders, with the model they are for.
+    async fn list_orders(&self) -> Result<Vec<(OrderId, ModelId)>>;
+    /// Open bids, as the order, the bidding provider and the price per request.
+    async fn list_bids(&self) -> Result<Vec<(OrderId, AccountId, Balance)>>;
+}
+
+impl AiroClient {
+    /// Decodes the first key of an `AiroMarket` storage map entry, which must use a concat hasher.
+    fn first_key<K: Decode>(&self, storage: &'static str, key_bytes: &[u8]) -> Result<K> {
+        let metadata = self.client.metadata();
+        let hasher = metadata
+            .pallet_by_name("AiroMarket")
+            .and_then(|pallet| pallet.storage())
+            .and_then(|entries| entries.entry_by_name(storage))
+            .and_then(|entry| match entry.entry_type() {
+                StorageEntryType::Map { hashers, .. } => hashers.first().copied(),
+                StorageEntryType::Plain(_) => None,
+            })
+            .filter(|hasher| hasher.ends_with_key())
+            .ok_or(Error::UndecodableKey(storage))?;
+        // Skip the pallet and storage prefixes, then the hash of the key.
+        let mut bytes = key_bytes
+            .get(32 + hasher.len_excluding_key()..)
+            .ok_or(Error::UndecodableKey(storage))?;
+        Ok(K::decode(&mut bytes)?)
+    }
+}
+
+#[async_trait]
+impl MarketReader for AiroClient {
+    async fn list_orders(&self) -> Result<Vec<(OrderId, ModelId)>> {
+        let query = airo::storage().airo_market().orders_iter();
+        let mut entries = self.client.storage().at_latest().await?.iter(query).await?;
+        let mut orders = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+            let order_id = self.first_key("Orders", &entry.key_bytes)?;
+            orders.push((order_id, entry.value.model_id));
+        }
+        Ok(orders)
+    }
+
+    async fn list_bids(&self) -> Result<Vec<(OrderId, AccountId, Balance)>> {
+        let query = airo::storage().airo_market().order_bids_iter();
+        let mut entries = self.client.storage().at_latest().await?.iter(query).await?;
+        let mut bids = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+            let order_id = self.first_key("OrderBids", &entry.key_bytes)?;
+            bids.push((order_id, entry.value.provider, entry.value.price_per_request));
+        }
+        Ok(bids)
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,159 @@
+use std::{collections::BTreeSet, fs, path::PathBuf, sync::Arc, time::SystemTime};
+
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::{
+    sync::watch,
+    time::{interval, Duration},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    config::{BiddingConfig, Config, EngineConfig},
+    data::{ConsumerRepo, ConsumerRepoFac, ModelRepo},
+    types::{Model, ModelName, Result},
+};
+
+/// How often the configuration file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Applies configuration changes to the running wingman. The file is reloaded when it changes or,
+/// on Unix, on `SIGHUP`. Models and engine settings are updated in place, other settings require
+/// a restart.
+pub struct ConfigReloader {
+    path: Option<PathBuf>,
+    /// The configuration applied last, if any.
+    current: Option<Config>,
+    /// Models defined in the configuration. Models added through the API are left alone.
+    managed: BTreeSet<ModelName>,
+    model_repo: Arc<dyn ModelRepo + Send + Sync>,
+    /// Consumer policies set through the API, kept over the ones of the configuration.
+    consumer_repo: Arc<dyn ConsumerRepo + Send + Sync>,
+    bidding_tx: watch::Sender<BiddingConfig>,
+    engine_tx: watch::Sender<EngineConfig>,
+}
+
+impl ConfigReloader {
+    pub fn new(path: Option<PathBuf>, model_repo: Arc<dyn ModelRepo + Send + Sync>) -> Self {
+        let (bidding_tx, _) = watch::channel(BiddingConfig::default());
+        let (engine_tx, _) = watch::channel(EngineConfig::default());
+        Self {
+            path,
+            current: None,
+            managed: BTreeSet::new(),
+            model_repo,
+            consumer_repo: Arc::new(ConsumerRepoFac::in_memory()),
+            bidding_tx,
+            engine_tx,
+        }
+    }
+
+    /// Keep the consumer policies of the repo over the ones of the configuration.
+    pub fn with_consumers(mut self, consumer_repo: Arc<dyn ConsumerRepo + Send + Sync>) -> Self {
+        self.consumer_repo = consumer_repo;
+        self
+    }
+
+    /// Bidding settings, updated on every reload.
+    pub fn bidding(&self) -> watch::Receiver<BiddingConfig> {
+        self.bidding_tx.subscribe()
+    }
+
+    /// Engine settings, updated on every reload.
+    pub fn engine(&self) -> watch::Receiver<EngineConfig> {
+        self.engine_tx.subscribe()
+    }
+
+    /// Apply a validated configuration. The signer is never kept.
+    pub async fn apply(&mut self, mut config: Config) {
+        config.signer = None;
+        let current = self.current.take().unwrap_or_else(|| config.clone());
+        if config.node != current.node
+            || config.http != current.http
+            || config.cache != current.cache
+            || config.storage != current.storage
+        {
+            tracing::warn!("⚠️ Node, http, cache and storage settings are applied on restart only");
+        }
+
+        let names: BTreeSet<_> = config.models.keys().cloned().collect();
+        let removed: Vec<_> = self.managed.difference(&names).cloned().collect();
+        let mut saved = Vec::new();
+        for (name, details) in &config.models {
+            if self.managed.contains(name) && current.models.get(name) == Some(details) {
+                continue;
+            }
+            let mut details = details.clone();
+            if let Some(consumers) = self.consumer_repo.get(name).await {
+                details.consumers = consumers;
+            }
+            saved.push(Model::new(name.clone(), details));
+        }
+        if !saved.is_empty() || !removed.is_empty() {
+            tracing::info!("🔄 Models updated: {} saved, {} removed", saved.len(), removed.len());
+            self.model_repo.apply(saved, removed).await;
+        }
+        self.managed = names;
+
+        self.bidding_tx
+            .send_if_modified(|bidding| replace_if_changed(bidding, &config.bidding));
+        self.engine_tx
+            .send_if_modified(|engine| replace_if_changed(engine, &config.engine));
+        self.current = Some(config);
+    }
+
+    /// Reload the configuration file. Invalid configurations are reported and ignored.
+    pub async fn reload(&mut self) {
+        match Config::load_from(self.path.as_deref()) {
+            Ok(config) => {
+                tracing::info!("🔄 Configuration reloaded");
+                self.apply(config).await;
+            },
+            Err(e) => tracing::error!("🚫 Configuration not reloaded. {e}"),
+        }
+    }
+
+    /// Reload the configuration whenever the file changes or `SIGHUP` is received.
+    pub async fn run(mut self, token: CancellationToken) -> Result<()> {
+        #[cfg(unix)]
+        let mut hangups = signal(SignalKind::hangup())?;
+        let mut poll = interval(POLL_INTERVAL);
+        let mut modified = self.modified();
+        loop {
+            #[cfg(unix)]
+            let hangup = hangups.recv();
+            #[cfg(not(unix))]
+            let hangup = std::future::pending::<Option<()>>();
+
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = hangup => {
+                    tracing::info!("🔄 Received SIGHUP");
+                    self.reload().await;
+                },
+                _ = poll.tick() => {
+                    let now = self.modified();
+                    if now != modified {
+                        modified = now;
+                        self.reload().await;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        self.path
+            .as_ref()
+            .and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+    }
+}
+
+fn replace_if_changed<T: Clone + PartialEq>(current: &mut T, new: &T) -> bool {
+    if current == new {
+        return false;
+    }
+    current.clone_from(new);
+    true
+}
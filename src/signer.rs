@@ -0,0 +1,255 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use crypto_secretbox::{aead::Aead, KeyInit, XSalsa20Poly1305};
+use schnorrkel::{signing_context, PublicKey as SrPublicKey, SecretKey};
+use serde::Deserialize;
+use subxt::{tx::Signer, utils::AccountId32, Config};
+use subxt_signer::{
+    sr25519::{Keypair, PublicKey, Signature},
+    SecretUri,
+};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::config::SignerConfig;
+
+/// Key type of account keys in a Substrate keystore.
+pub const ACCOUNT_KEY_TYPE: &str = "acco";
+/// Environment variable holding the passphrase of an encrypted keyfile.
+pub const PASSPHRASE_ENV: &str = "AIRO_PASSPHRASE";
+
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+const SCRYPT_PARAMS_LEN: usize = 32 + 3 * 4;
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to read {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("{path} must not be accessible by group or others (mode {mode:o})")]
+    InsecurePermissions { path: PathBuf, mode: u32 },
+    #[error("Invalid secret uri")]
+    InvalidUri,
+    #[error("Invalid secret: {0}")]
+    InvalidSecret(String),
+    #[error("No {ACCOUNT_KEY_TYPE} key found in keystore {0}")]
+    KeyNotFound(PathBuf),
+    #[error("Keystore {0} holds several keys. Set the public key to pick one")]
+    AmbiguousKey(PathBuf),
+    #[error("Invalid keyfile: {0}")]
+    InvalidKeyfile(String),
+    #[error("Unsupported keyfile encoding: {0}")]
+    UnsupportedEncoding(String),
+    #[error("Keyfile passphrase is not set ({PASSPHRASE_ENV})")]
+    MissingPassphrase,
+    #[error("Failed to decrypt keyfile. The passphrase might be wrong")]
+    Decrypt,
+}
+
+/// The key the provider signs transactions with. Secret material is zeroized when dropped.
+pub enum ProviderKey {
+    /// Key derived from a secret uri.
+    Uri(Keypair),
+    /// Key given in its expanded form, as exported by polkadot.js.
+    Expanded(schnorrkel::Keypair),
+}
+
+impl ProviderKey {
+    /// Load the key from the configured source.
+    pub fn load(config: &SignerConfig) -> Result<Self, Error> {
+        match config {
+            SignerConfig::Uri { suri } => Self::from_uri(suri),
+            SignerConfig::File { path } => Self::from_uri(&read_secret(path)?),
+            SignerConfig::Keystore { path, public_key } => {
+                Self::from_keystore(path, public_key.as_deref())
+            },
+            SignerConfig::Json { path, passphrase_file } => {
+                let passphrase = match passphrase_file {
+                    Some(file) => read_secret(file)?,
+                    None => env::var(PASSPHRASE_ENV)
+                        .map(Zeroizing::new)
+                        .map_err(|_| Error::MissingPassphrase)?,
+                };
+                let json = fs::read_to_string(path)
+                    .map_err(|source| Error::Read { path: path.clone(), source })?;
+                Self::from_json(&json, passphrase.trim_end_matches(['\r', '\n']))
+            },
+        }
+    }
+
+    /// Derive the key from a secret uri, e.g. a mnemonic phrase or a hex seed.
+    pub fn from_uri(suri: &str) -> Result<Self, Error> {
+        let uri = SecretUri::from_str(suri.trim()).map_err(|_| Error::InvalidUri)?;
+        let keypair = Keypair::from_uri(&uri).map_err(|e| Error::InvalidSecret(e.to_string()))?;
+        Ok(Self::Uri(keypair))
+    }
+
+    /// Load the account key from a Substrate keystore directory. Keys are stored in files named
+    /// after the hex encoded key type and public key, holding the secret uri as a JSON string.
+    pub fn from_keystore(dir: &Path, public_key: Option<&str>) -> Result<Self, Error> {
+        let prefix = hex::encode(ACCOUNT_KEY_TYPE);
+        let public_key = public_key.map(|key| key.trim_start_matches("0x").to_lowercase());
+        let entries =
+            fs::read_dir(dir).map_err(|source| Error::Read { path: dir.to_owned(), source })?;
+        let mut candidates =
+            entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| {
+                let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                    return false;
+                };
+                let Some(key) = name.strip_prefix(&prefix) else {
+                    return false;
+                };
+                public_key.as_ref().is_none_or(|public_key| key == public_key)
+            });
+        let path = candidates.next().ok_or_else(|| Error::KeyNotFound(dir.to_owned()))?;
+        if candidates.next().is_some() {
+            return Err(Error::AmbiguousKey(dir.to_owned()));
+        }
+
+        let content = read_secret(&path)?;
+        let suri = Zeroizing::new(serde_json::from_str::<String>(&content).map_err(|_| {
+            Error::InvalidSecret(format!("{} is not a JSON string", path.display()))
+        })?);
+        Self::from_uri(&suri)
+    }
+
+    /// Decrypt a keyfile exported from polkadot.js. Only sr25519 keys encrypted with scrypt and
+    /// xsalsa20-poly1305 are supported.
+    pub fn from_json(json: &str, passphrase: &str) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Keyfile {
+            encoded: String,
+            encoding: Encoding,
+            address: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Encoding {
+            content: Vec<String>,
+            #[serde(rename = "type")]
+            kind: Vec<String>,
+            version: String,
+        }
+
+        let keyfile: Keyfile =
+            serde_json::from_str(json).map_err(|e| Error::InvalidKeyfile(e.to_string()))?;
+        let encoding = keyfile.encoding;
+        if encoding.version != "3"
+            || encoding.content != ["pkcs8", "sr25519"]
+            || encoding.kind != ["scrypt", "xsalsa20-poly1305"]
+        {
+            return Err(Error::UnsupportedEncoding(format!(
+                "version {} of {:?} with {:?}",
+                encoding.version, encoding.content, encoding.kind
+            )));
+        }
+
+        let encoded = STANDARD
+            .decode(keyfile.encoded)
+            .map_err(|e| Error::InvalidKeyfile(e.to_string()))?;
+        if encoded.len() < SCRYPT_PARAMS_LEN + NONCE_LEN {
+            return Err(Error::InvalidKeyfile("encoded key is too short".to_owned()));
+        }
+        let (params, rest) = encoded.split_at(SCRYPT_PARAMS_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let key = derive_key(passphrase, params)?;
+        let plaintext = Zeroizing::new(
+            XSalsa20Poly1305::new(key.as_ref().into())
+                .decrypt(nonce.into(), ciphertext)
+                .map_err(|_| Error::Decrypt)?,
+        );
+
+        let invalid = || Error::InvalidKeyfile("unexpected pkcs8 layout".to_owned());
+        let secret = plaintext.strip_prefix(&PKCS8_HEADER).ok_or_else(invalid)?;
+        let (secret, public) = secret.split_at_checked(64).ok_or_else(invalid)?;
+        let public = public.strip_prefix(&PKCS8_DIVIDER).ok_or_else(invalid)?;
+        let secret = SecretKey::from_ed25519_bytes(secret)
+            .map_err(|e| Error::InvalidSecret(e.to_string()))?;
+        let public =
+            SrPublicKey::from_bytes(public).map_err(|e| Error::InvalidSecret(e.to_string()))?;
+        let keypair = secret.to_keypair();
+        if keypair.public != public {
+            return Err(Error::InvalidKeyfile("public key does not match the secret".to_owned()));
+        }
+
+        let key = Self::Expanded(keypair);
+        if AccountId32::from_str(&keyfile.address).ok() != Some(key.public_key().into()) {
+            return Err(Error::InvalidKeyfile("address does not match the key".to_owned()));
+        }
+        Ok(key)
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        match self {
+            Self::Uri(keypair) => keypair.public_key(),
+            Self::Expanded(keypair) => PublicKey(keypair.public.to_bytes()),
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> Signature {
+        match self {
+            Self::Uri(keypair) => keypair.sign(message),
+            Self::Expanded(keypair) => {
+                let context = signing_context(b"substrate");
+                Signature(keypair.sign(context.bytes(message)).to_bytes())
+            },
+        }
+    }
+}
+
+impl<T: Config> Signer<T> for ProviderKey
+where
+    T::AccountId: From<PublicKey>,
+    T::Address: From<PublicKey>,
+    T::Signature: From<Signature>,
+{
+    fn account_id(&self) -> T::AccountId {
+        self.public_key().into()
+    }
+
+    fn address(&self) -> T::Address {
+        self.public_key().into()
+    }
+
+    fn sign(&self, signer_payload: &[u8]) -> T::Signature {
+        ProviderKey::sign(self, signer_payload).into()
+    }
+}
+
+/// Derive the keyfile encryption key with the scrypt parameters stored in the keyfile.
+fn derive_key(passphrase: &str, params: &[u8]) -> Result<Zeroizing<[u8; 32]>, Error> {
+    let (salt, params) = params.split_at(32);
+    let [n, p, r] = [0, 1, 2]
+        .map(|i| u32::from_le_bytes(params[i * 4..i * 4 + 4].try_into().expect("4 bytes")));
+    if !n.is_power_of_two() {
+        return Err(Error::InvalidKeyfile(format!("scrypt cost {n} is not a power of two")));
+    }
+    let params = scrypt::Params::new(n.ilog2() as u8, r, p, 32)
+        .map_err(|e| Error::InvalidKeyfile(e.to_string()))?;
+    let mut key = Zeroizing::new([0; 32]);
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, key.as_mut())
+        .map_err(|e| Error::InvalidKeyfile(e.to_string()))?;
+    Ok(key)
+}
+
+/// Read a secret from a file that is accessible by its owner only.
+pub fn read_secret(path: &Path) -> Result<Zeroizing<String>, Error> {
+    let read_error = |source| Error::Read { path: path.to_owned(), source };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path).map_err(read_error)?.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Err(Error::InsecurePermissions { path: path.to_owned(), mode });
+        }
+    }
+    let secret = Zeroizing::new(fs::read_to_string(path).map_err(read_error)?);
+    Ok(Zeroizing::new(secret.trim_end_matches(['\r', '\n']).to_owned()))
+}
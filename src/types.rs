@@ -1,24 +1,133 @@
-use std::error::Error;
 pub use std::result::Result as stdResult;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+};
 
 use primitive_types::H256;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use subxt::config::{substrate::BlakeTwo256, Hasher as HasherT};
+use subxt::{
+    config::{substrate::BlakeTwo256, Hasher as HasherT},
+    utils::AccountId32,
+};
 use utoipa::ToSchema;
 
 pub type Result<T> = stdResult<T, Box<dyn Error + Send + Sync>>;
 
 pub type Hasher = BlakeTwo256;
 
+pub type AccountId = AccountId32;
+
 pub type Balance = u128;
 pub type ModelName = String;
 pub type ModelId = H256;
 pub type OrderId = u32;
 pub type ContentId = H256;
 pub type AgreementId = OrderId;
+pub type TxHash = H256;
+
+/// Receipt of a submitted transaction.
+#[derive(Clone, Copy, Debug)]
+pub struct TxReceipt {
+    pub hash: TxHash,
+    /// Fee paid for the transaction, `None` if it is unknown.
+    pub fee: Option<Balance>,
+}
+/// Agreement details as stored on chain.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct AgreementDetails {
+    #[schema(value_type = String)]
+    pub consumer: AccountId,
+    #[schema(value_type = String)]
+    pub provider: AccountId,
+    #[schema(value_type = String)]
+    pub model_id: ModelId,
+    #[schema(value_type = u128)]
+    pub price_per_request: Balance,
+    #[schema(value_type = u128)]
+    pub royalty_per_request: Balance,
+    /// Number of requests created by the consumer.
+    pub requests_count: u32,
+    /// Number of requests the consumer has paid for.
+    pub requests_total: u32,
+}
+
+/// Order details as stored on chain.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrderDetails {
+    #[schema(value_type = String)]
+    pub consumer: AccountId,
+    #[schema(value_type = String)]
     pub model_id: ModelId,
+    /// Number of requests the consumer orders.
+    pub requests_total: u32,
+}
+
+/// Local processing state of an agreement.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AgreementState {
+    /// Requests of the agreement are being served.
+    Active,
+    /// All requests of the agreement have been processed.
+    Completed,
+    /// The response window closed before all requests were processed.
+    Expired,
+}
+
+/// An agreement won by the provider along with its local processing state.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct Agreement {
+    pub id: AgreementId,
+    pub details: AgreementDetails,
+    pub state: AgreementState,
+    /// Number of requests answered.
+    pub requests_answered: u32,
+    /// Number of requests that failed and were not answered.
+    pub requests_failed: u32,
+    /// Amount earned by answering requests.
+    #[schema(value_type = u128)]
+    pub earned: Balance,
+    /// Time the bid was accepted, in milliseconds since the Unix epoch.
+    pub accepted_at: u64,
+}
+
+impl Agreement {
+    pub fn new(id: AgreementId, details: AgreementDetails, accepted_at: u64) -> Self {
+        Self {
+            id,
+            details,
+            state: AgreementState::Active,
+            requests_answered: 0,
+            requests_failed: 0,
+            earned: 0,
+            accepted_at,
+        }
+    }
+
+    /// Whether a request index is within the requests the consumer has paid for.
+    pub fn in_range(&self, request_index: u32) -> bool {
+        request_index < self.details.requests_total
+    }
+
+    /// Number of requests the consumer has paid for but not created yet.
+    pub fn outstanding(&self) -> u32 {
+        self.details.requests_total.saturating_sub(self.details.requests_count)
+    }
+
+    /// Record the outcome of a request.
+    pub fn record_outcome(&mut self, answered: bool) {
+        if answered {
+            self.requests_answered += 1;
+            self.earned += self.details.price_per_request;
+        } else {
+            self.requests_failed += 1;
+        }
+        if self.requests_answered + self.requests_failed >= self.details.requests_total {
+            self.state = AgreementState::Completed;
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +137,210 @@ pub struct ExecutionResult {
     pub error: Option<String>,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
+    /// Tokens consumed by the prediction, reported by LLM backends.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// Code of the content policy the request was rejected by, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_code: Option<String>,
+}
+
+/// Tokens consumed by a prediction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+/// Processing status of a request.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestStatus {
+    /// The request is being processed.
+    Received,
+    /// The response has been submitted.
+    Responded,
+    /// Processing failed and the request was not answered.
+    Failed,
+    /// The request was not processed, e.g. as its consumer is not served.
+    Refused,
+}
+
+/// Record of a processed request.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RequestRecord {
+    pub agreement_id: AgreementId,
+    pub request_index: u32,
+    #[schema(value_type = String)]
+    pub model_id: ModelId,
+    #[schema(value_type = String)]
+    pub input_content_id: ContentId,
+    #[schema(value_type = Option<String>)]
+    pub output_content_id: Option<ContentId>,
+    pub status: RequestStatus,
+    /// Status of the prediction reported by the model.
+    pub prediction_status: Option<String>,
+    pub error: Option<String>,
+    /// Whether the response was served from the result cache.
+    pub cached: bool,
+    /// Time the request was received, in milliseconds since the Unix epoch.
+    pub received_at: u64,
+    pub predict_started_at: Option<String>,
+    pub predict_completed_at: Option<String>,
+    /// Time the response was submitted, in milliseconds since the Unix epoch.
+    pub responded_at: Option<u64>,
+    /// Metrics reported by the model.
+    pub metrics: Option<HashMap<String, Value>>,
+    /// Hash of the `response_create` transaction.
+    #[schema(value_type = Option<String>)]
+    pub tx_hash: Option<TxHash>,
+}
+
+impl RequestRecord {
+    pub fn new(
+        agreement_id: AgreementId,
+        request_index: u32,
+        model_id: ModelId,
+        input_content_id: ContentId,
+        received_at: u64,
+    ) -> Self {
+        Self {
+            agreement_id,
+            request_index,
+            model_id,
+            input_content_id,
+            output_content_id: None,
+            status: RequestStatus::Received,
+            prediction_status: None,
+            error: None,
+            cached: false,
+            received_at,
+            predict_started_at: None,
+            predict_completed_at: None,
+            responded_at: None,
+            metrics: None,
+            tx_hash: None,
+        }
+    }
+}
+
+/// Last completed step of a queued request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkStage {
+    Received,
+    Downloaded,
+    Predicted,
+    Uploaded,
+}
+
+/// A request in the work queue, along with what it takes to resume it from its last step.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct WorkItem {
+    pub agreement_id: AgreementId,
+    pub request_index: u32,
+    #[schema(value_type = String)]
+    pub model_id: ModelId,
+    #[schema(value_type = String)]
+    pub input_content_id: ContentId,
+    pub stage: WorkStage,
+    /// The input, once downloaded.
+    #[schema(value_type = Option<Object>)]
+    pub input: Option<Value>,
+    /// The execution result, once predicted.
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<Value>,
+    /// Content ID of the execution result, once uploaded.
+    #[schema(value_type = Option<String>)]
+    pub output_content_id: Option<ContentId>,
+    /// Time the request was received, in milliseconds since the Unix epoch.
+    pub received_at: u64,
+}
+
+impl WorkItem {
+    pub fn new(
+        agreement_id: AgreementId,
+        request_index: u32,
+        model_id: ModelId,
+        input_content_id: ContentId,
+        received_at: u64,
+    ) -> Self {
+        Self {
+            agreement_id,
+            request_index,
+            model_id,
+            input_content_id,
+            stage: WorkStage::Received,
+            input: None,
+            result: None,
+            output_content_id: None,
+            received_at,
+        }
+    }
+
+    pub fn key(&self) -> (AgreementId, u32) {
+        (self.agreement_id, self.request_index)
+    }
+}
+
+pub type DeadLetterId = u64;
+
+/// A request that failed, kept until it is retried or discarded.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetter {
+    pub id: DeadLetterId,
+    /// The request, at the step it failed at.
+    pub item: WorkItem,
+    /// Why the request failed.
+    pub reason: String,
+    /// Time the request failed, in milliseconds since the Unix epoch.
+    pub failed_at: u64,
+}
+
+/// Kind of a ledger entry.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LedgerEntryKind {
+    /// Price of an answered request.
+    Earning,
+    /// Fee paid for a `bid_create` transaction.
+    BidFee,
+    /// Fee paid for a `response_create` transaction.
+    ResponseFee,
+}
+
+/// Entry of the earnings ledger.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct LedgerEntry {
+    /// Time of the entry, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub kind: LedgerEntryKind,
+    /// The agreement, or the order in case of a bid fee.
+    pub agreement_id: AgreementId,
+    #[schema(value_type = String)]
+    pub model_id: ModelId,
+    #[schema(value_type = u128)]
+    pub amount: Balance,
+    #[schema(value_type = Option<String>)]
+    pub tx_hash: Option<TxHash>,
+}
+
+/// A bid placed by a competing provider.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CompetitorBid {
+    pub order_id: OrderId,
+    #[schema(value_type = String)]
+    pub model_id: ModelId,
+    #[schema(value_type = String)]
+    pub provider: AccountId,
+    #[schema(value_type = u128)]
+    pub price_per_request: Balance,
+    /// Time the bid was seen, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Whether the bid was accepted.
+    pub won: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, ToSchema)]
@@ -46,9 +359,387 @@ impl Model {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct ModelDetails {
     #[schema(value_type = u128)]
+    #[serde(deserialize_with = "deserialize_balance")]
     pub price_per_request: Balance,
-    pub url: String,
+    /// Base urls of the replicas serving the model. A single `url` is accepted as well.
+    #[serde(alias = "url", deserialize_with = "deserialize_urls")]
+    pub urls: Vec<String>,
+    /// Backend the replicas are served by. Defaults to Cog.
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default)]
+    pub balancing: Balancing,
+    /// How long a prediction waits for a replica to become ready before it fails, in seconds.
+    /// Defaults to 60.
+    #[serde(default)]
+    pub acquire_timeout_secs: Option<u64>,
+    /// How long a prediction may run before it is cancelled and fails, in seconds. Not limited
+    /// unless set.
+    #[serde(default)]
+    pub predict_timeout_secs: Option<u64>,
+    /// Version of the model. Cached results of other versions are not reused.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Result caching. Disabled unless set, since some models are non-deterministic.
+    #[serde(default)]
+    pub cache: Option<CacheSettings>,
+    /// Maximum number of requests committed to across the active agreements of the model. No
+    /// bids are placed on orders that would exceed it. Unlimited unless set.
+    #[serde(default)]
+    pub capacity: Option<u32>,
+    /// Batching of concurrent requests into one prediction. Disabled unless set, since the model
+    /// must accept a list of inputs.
+    #[serde(default)]
+    pub batching: Option<BatchSettings>,
+    /// Consumers served and their prices. Every consumer is served at the model price by default.
+    #[serde(default)]
+    pub consumers: ConsumerPolicy,
+    /// Checks of the inputs and outputs. Nothing is checked by default.
+    #[serde(default)]
+    pub content: ContentPolicy,
+}
+
+/// Checks run on the content of requests. A request failing a check is answered with a failure
+/// carrying the code of the check, instead of being predicted or returning its output.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentPolicy {
+    /// Checks of the input, before the prediction, in order.
+    pub input: Vec<ContentCheck>,
+    /// Checks of the output, after the prediction, in order.
+    pub output: Vec<ContentCheck>,
+}
+
+/// A check of the content of a request.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ContentCheck {
+    /// The content must be valid against a JSON schema.
+    Schema {
+        #[schema(value_type = Object)]
+        schema: Value,
+    },
+    /// The content must not exceed a size, as JSON.
+    MaxSize { max_bytes: usize },
+    /// No text in the content may match any of the regular expressions.
+    Regex { patterns: Vec<String> },
+    /// No text in the content may contain any of the keywords, ignoring case.
+    Keywords { keywords: Vec<String> },
+    /// An external moderation service must allow the content. The content is posted as
+    /// `{"stage", "model", "content"}` and `{"allowed", "reason"}` is expected back. The request
+    /// fails without a response if the service is unavailable, so it can be retried.
+    Moderation {
+        url: String,
+        #[serde(default = "default_moderation_timeout_secs")]
+        timeout_secs: u64,
+    },
+}
+
+fn default_moderation_timeout_secs() -> u64 {
+    10
+}
+
+/// Which consumers a model is served to, and at what price. Consumers are identified by their
+/// account, as found in orders and agreements.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConsumerPolicy {
+    /// Consumers served exclusively, e.g. under private deals. Every consumer is served if empty.
+    #[schema(value_type = Vec<String>)]
+    pub allow: BTreeSet<AccountId>,
+    /// Consumers never served.
+    #[schema(value_type = Vec<String>)]
+    pub deny: BTreeSet<AccountId>,
+    /// Prices per request of specific consumers, bid instead of the model price.
+    #[schema(value_type = HashMap<String, u128>)]
+    #[serde(deserialize_with = "deserialize_prices")]
+    pub prices: BTreeMap<AccountId, Balance>,
+}
+
+impl ConsumerPolicy {
+    /// Whether the policy serves every consumer at the model price.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && self.prices.is_empty()
+    }
+
+    /// Whether a consumer is served.
+    pub fn allows(&self, consumer: &AccountId) -> bool {
+        !self.deny.contains(consumer) && (self.allow.is_empty() || self.allow.contains(consumer))
+    }
+
+    /// Price per request of a consumer, if it has a price of its own.
+    pub fn price(&self, consumer: &AccountId) -> Option<Balance> {
+        self.prices.get(consumer).copied()
+    }
+}
+
+fn deserialize_prices<'de, D>(deserializer: D) -> stdResult<BTreeMap<AccountId, Balance>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Price(#[serde(deserialize_with = "deserialize_balance")] Balance);
+
+    let prices = BTreeMap::<AccountId, Price>::deserialize(deserializer)?;
+    Ok(prices.into_iter().map(|(consumer, Price(price))| (consumer, price)).collect())
+}
+
+fn deserialize_urls<'de, D>(deserializer: D) -> stdResult<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Urls {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Urls::deserialize(deserializer)? {
+        Urls::One(url) => vec![url],
+        Urls::Many(urls) => urls,
+    })
+}
+
+/// Deserialize a balance from an integer or a decimal string. JSON integers are read as 128-bit
+/// integers. Config formats like TOML have no 128-bit integers, so large balances are given as
+/// strings there.
+pub(crate) fn deserialize_balance<'de, D>(deserializer: D) -> stdResult<Balance, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserializer.deserialize_u128(BalanceVisitor)
+}
+
+/// Like [deserialize_balance], for balances in internally tagged enums. Their content is
+/// buffered before it is deserialized, which supports no 128-bit integers.
+pub(crate) fn deserialize_tagged_balance<'de, D>(deserializer: D) -> stdResult<Balance, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserializer.deserialize_any(BalanceVisitor)
+}
+
+struct BalanceVisitor;
+
+impl serde::de::Visitor<'_> for BalanceVisitor {
+    type Value = Balance;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a non-negative integer or a decimal string")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> stdResult<Balance, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u128<E: serde::de::Error>(self, v: u128) -> stdResult<Balance, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> stdResult<Balance, E> {
+        Balance::try_from(v).map_err(E::custom)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> stdResult<Balance, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+/// Result caching settings of a model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CacheSettings {
+    /// Time to live of a cached result in seconds.
+    pub ttl_secs: u64,
+}
+
+/// Request batching settings of a model. Inputs are sent as a list in `input_field` and the
+/// outputs are expected as a list in the same order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BatchSettings {
+    /// Maximum number of requests in a batch, at most the engine concurrency. Defaults to 4.
+    #[serde(default = "default_batch_size")]
+    pub max_size: usize,
+    /// Time the first request of a batch waits for others, in milliseconds. Defaults to 50.
+    #[serde(default = "default_batch_window_ms")]
+    pub window_ms: u64,
+    /// Input field holding the list of inputs. Defaults to `inputs`.
+    #[serde(default = "default_batch_input_field")]
+    pub input_field: String,
+    /// Output field holding the list of outputs. The output itself is the list unless set.
+    #[serde(default)]
+    pub output_field: Option<String>,
+}
+
+fn default_batch_size() -> usize {
+    4
+}
+
+fn default_batch_window_ms() -> u64 {
+    50
+}
+
+fn default_batch_input_field() -> String {
+    "inputs".to_owned()
+}
+
+/// Inference backend serving the replicas of a model.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Backend {
+    /// Model packaged with Cog.
+    #[default]
+    Cog,
+    /// Model served over a JSON HTTP API.
+    HttpJson(Box<HttpJsonSettings>),
+    /// LLM served over an OpenAI-compatible API, e.g. by vLLM or llama.cpp.
+    #[serde(rename = "openai")]
+    OpenAi(OpenAiSettings),
+    /// Model served over the Open Inference Protocol v2, e.g. by Triton or KServe.
+    #[serde(rename = "kserve")]
+    KServe(KServeSettings),
+    /// Model run as a local command per request. Urls name worker slots, e.g. `exec://worker-1`.
+    Subprocess(SubprocessSettings),
+}
+
+/// Settings of a model served over a JSON HTTP API. Request and response bodies are mapped with
+/// templates holding `{{path}}` placeholders, see the `backend::template` module.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpJsonSettings {
+    /// Path of the prediction endpoint relative to the replica url. Requests are POSTed.
+    pub predict_path: String,
+    /// Path of the health endpoint. A successful GET means the replica is ready. Replicas are
+    /// assumed ready if not set.
+    pub health_path: Option<String>,
+    /// Path of the endpoint serving the API schema.
+    pub schema_path: Option<String>,
+    /// Path of the endpoint cancelling a prediction. `{id}` is replaced by the prediction ID.
+    pub cancel_path: Option<String>,
+    /// Headers added to every request.
+    pub headers: BTreeMap<String, String>,
+    /// Template of the request body, rendered with `input` and `id`, the ID assigned to the
+    /// prediction. The input is sent as is if not set.
+    #[schema(value_type = Option<Object>)]
+    pub request: Option<Value>,
+    /// Template of the output, rendered with `response`. The response body is the output if
+    /// not set.
+    #[schema(value_type = Option<Object>)]
+    pub output: Option<Value>,
+    /// Template of the error, rendered with `response`. The prediction failed if it renders to a
+    /// non-empty value.
+    pub error: Option<String>,
+    /// Template of the prediction ID, rendered with `response`. The assigned ID if not set.
+    pub id: Option<String>,
+}
+
+/// Settings of an LLM served over an OpenAI-compatible API.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OpenAiSettings {
+    /// Name of the model as listed by `/v1/models`.
+    pub model: String,
+    /// Endpoint requests are sent to. Defaults to chat completions.
+    #[serde(default)]
+    pub endpoint: OpenAiEndpoint,
+    /// Environment variable holding the API key, if the server requires one.
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// System prompt prepended to chat requests that do not have one.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Upper bound of `max_tokens`. Requests asking for more are capped.
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+/// Endpoint of an OpenAI-compatible API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAiEndpoint {
+    /// `/v1/chat/completions`
+    #[default]
+    Chat,
+    /// `/v1/completions`
+    Completion,
+}
+
+/// Settings of a model served over the Open Inference Protocol v2.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KServeSettings {
+    /// Name of the model on the inference server.
+    pub model: String,
+    /// Version of the model. The server picks one if not set.
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// Settings of a model run as a local command.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubprocessSettings {
+    /// Program and arguments. With file input, `{input}` in an argument is replaced by the path
+    /// of the input file, which is appended to the arguments otherwise.
+    pub command: Vec<String>,
+    pub input: InputMode,
+    /// The command is killed if it runs longer. Defaults to 60 seconds.
+    pub timeout_secs: u64,
+    /// Maximum size of stdout and of the captured stderr. Defaults to 1 MiB.
+    pub max_output_bytes: usize,
+    /// Environment of the command. Nothing else is inherited but a default `PATH`.
+    pub env: BTreeMap<String, String>,
+    /// Working directory of the command.
+    #[schema(value_type = Option<String>)]
+    pub working_dir: Option<std::path::PathBuf>,
+    pub limits: ResourceLimits,
+}
+
+impl Default for SubprocessSettings {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            input: InputMode::default(),
+            timeout_secs: 60,
+            max_output_bytes: 1024 * 1024,
+            env: BTreeMap::new(),
+            working_dir: None,
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+/// How the input JSON is passed to a command.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMode {
+    #[default]
+    Stdin,
+    /// In a temporary file readable by the owner only.
+    File,
+}
+
+/// Resource limits of a command.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Address space limit in MiB.
+    pub memory_mb: Option<u64>,
+    /// CPU time limit in seconds.
+    pub cpu_secs: Option<u64>,
+}
+
+/// Strategy used to route predictions between the replicas of a model.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Balancing {
+    /// Route to the replica with the fewest predictions in flight.
+    #[default]
+    LeastOutstanding,
+    /// Route to replicas in turn.
+    RoundRobin,
 }
@@ -14,3 +14,39 @@ macro_rules! retry_on_err_or_none {
         retry_on_err_or_none!($n, 1000, $fn)
     };
 }
+
+/// Milliseconds elapsed since the Unix epoch.
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Calendar date (`YYYY-MM-DD`, UTC) of a time given in milliseconds since the Unix epoch.
+pub fn utc_date(millis: u64) -> String {
+    // Civil from days algorithm by Howard Hinnant.
+    let days = (millis / 86_400_000) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// RFC 3339 timestamp (UTC) of a time given in milliseconds since the Unix epoch.
+pub fn rfc3339(millis: u64) -> String {
+    let time_of_day = millis % 86_400_000;
+    let (hours, minutes) = (time_of_day / 3_600_000, time_of_day / 60_000 % 60);
+    let (seconds, fraction) = (time_of_day / 1000 % 60, time_of_day % 1000);
+    format!("{}T{hours:02}:{minutes:02}:{seconds:02}.{fraction:03}Z", utc_date(millis))
+}
+
+/// Current time as an RFC 3339 timestamp.
+pub fn now_rfc3339() -> String {
+    rfc3339(now_millis())
+}
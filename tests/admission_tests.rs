@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use airo_wingman::{
+    balancer::ReplicaPool,
+    data::{AgreementRepo, AgreementRepoFac},
+    engine::{Admission, AdmissionControl},
+    protocol::StateReader,
+    types::{
+        AccountId, Agreement, AgreementDetails, AgreementId, Model, ModelDetails, OrderDetails,
+        OrderId, Result,
+    },
+};
+use async_trait::async_trait;
+
+/// Orders request as many requests as their ID, order 0 does not exist and order 99 can't be read.
+struct Orders(Model);
+
+#[async_trait]
+impl StateReader for Orders {
+    async fn get_agreement(&self, _: AgreementId) -> Result<Option<AgreementDetails>> {
+        Ok(None)
+    }
+
+    async fn get_order(&self, order_id: OrderId) -> Result<Option<OrderDetails>> {
+        if order_id == 99 {
+            return Err("Connection reset".into());
+        }
+        Ok((order_id > 0).then(|| OrderDetails {
+            consumer: consumer(),
+            model_id: self.0.id,
+            requests_total: order_id,
+        }))
+    }
+}
+
+fn consumer() -> AccountId {
+    AccountId::from([1; 32])
+}
+
+fn admitted(consumer: Option<AccountId>) -> Admission {
+    Admission::Admitted { consumer }
+}
+
+#[tokio::test]
+async fn test_admission_control() {
+    let details = ModelDetails {
+        urls: vec!["http://localhost:5000".to_owned()],
+        capacity: Some(10),
+        ..Default::default()
+    };
+    let model = Model::new("hello".to_owned(), details);
+    let agreement_repo = Arc::new(AgreementRepoFac::in_memory());
+    let admission = AdmissionControl::new(
+        Arc::new(ReplicaPool::new()),
+        agreement_repo.clone(),
+        Arc::new(Orders(model.clone())),
+    );
+
+    let details = AgreementDetails {
+        consumer: AccountId::from([1; 32]),
+        provider: AccountId::from([2; 32]),
+        model_id: model.id,
+        price_per_request: 1,
+        royalty_per_request: 0,
+        requests_count: 2,
+        requests_total: 6,
+    };
+    agreement_repo.save(Agreement::new(1, details, 0)).await;
+    assert_eq!(admission.committed(&model.id).await, 4);
+
+    assert_eq!(admission.admit(&model, 0).await, Admission::OrderNotFound);
+    let unavailable = Admission::OrderUnavailable { reason: "Connection reset".to_owned() };
+    assert_eq!(admission.admit(&model, 99).await, unavailable);
+    assert_eq!(admission.admit(&model, 5).await, admitted(Some(consumer())));
+    // The pending bid reserves capacity until it is released
+    assert_eq!(
+        admission.admit(&model, 2).await,
+        Admission::OverCapacity { committed: 9, requested: 2, capacity: 10 }
+    );
+    admission.release(5);
+    assert_eq!(admission.admit(&model, 2).await, admitted(Some(consumer())));
+
+    let mut unlimited = model.clone();
+    unlimited.details.capacity = None;
+    assert_eq!(admission.admit(&unlimited, 100).await, admitted(None));
+}
+
+#[tokio::test]
+async fn test_consumer_policy() {
+    let details =
+        ModelDetails { urls: vec!["http://localhost:5000".to_owned()], ..Default::default() };
+    let mut model = Model::new("hello".to_owned(), details);
+    let admission = AdmissionControl::new(
+        Arc::new(ReplicaPool::new()),
+        Arc::new(AgreementRepoFac::in_memory()),
+        Arc::new(Orders(model.clone())),
+    );
+
+    model.details.consumers.deny.insert(consumer());
+    let refused = Admission::ConsumerRefused { consumer: consumer() };
+    assert_eq!(admission.admit(&model, 1).await, refused);
+
+    model.details.consumers.deny.clear();
+    model.details.consumers.allow.insert(AccountId::from([3; 32]));
+    assert_eq!(admission.admit(&model, 1).await, refused);
+
+    model.details.consumers.allow.insert(consumer());
+    assert_eq!(admission.admit(&model, 1).await, admitted(Some(consumer())));
+}
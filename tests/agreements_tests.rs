@@ -0,0 +1,140 @@
+use std::{sync::Arc, time::Duration};
+
+use airo_wingman::{
+    balancer::ReplicaPool,
+    cache::ResultCache,
+    config::EngineConfig,
+    data::{
+        AgreementRepo, AgreementRepoFac, HistoryRepo, HistoryRepoFac, LedgerRepoFac, ModelRepo,
+        ModelRepoFac, WorkQueueFac,
+    },
+    engine::{Engine, ExecutionEngine, RequestProcessor},
+    protocol::ChainEvent,
+    types::{
+        AccountId, Agreement, AgreementDetails, AgreementState, Model, ModelDetails, RequestStatus,
+    },
+    utils::now_millis,
+};
+use primitive_types::H256;
+use tokio::sync::{mpsc, watch};
+
+use crate::common::StubChain;
+
+mod common;
+
+fn details(requests_total: u32) -> AgreementDetails {
+    AgreementDetails {
+        consumer: AccountId::from([1; 32]),
+        provider: AccountId::from([2; 32]),
+        model_id: H256::zero(),
+        price_per_request: 10,
+        royalty_per_request: 1,
+        requests_count: 0,
+        requests_total,
+    }
+}
+
+#[tokio::test]
+async fn test_agreement_outcomes() {
+    let repo = AgreementRepoFac::in_memory();
+    repo.save(Agreement::new(42, details(2), 0)).await;
+
+    let agreement = repo.update(42, Box::new(|a| a.record_outcome(true))).await.unwrap();
+    assert_eq!((agreement.requests_answered, agreement.earned), (1, 10));
+    assert_eq!(agreement.state, AgreementState::Active);
+
+    let agreement = repo.update(42, Box::new(|a| a.record_outcome(false))).await.unwrap();
+    assert_eq!((agreement.requests_failed, agreement.earned), (1, 10));
+    assert_eq!(agreement.state, AgreementState::Completed);
+
+    assert!(repo.update(7, Box::new(|a| a.record_outcome(true))).await.is_none());
+}
+
+#[tokio::test]
+async fn test_agreements_persist() {
+    let path = std::env::temp_dir().join(format!("aw-agreements-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    {
+        let repo = AgreementRepoFac::file(&path).await.unwrap();
+        repo.save(Agreement::new(1, details(3), 0)).await;
+        repo.save(Agreement::new(2, details(1), 0)).await;
+        repo.update(1, Box::new(|a| a.record_outcome(true))).await;
+        repo.remove(2).await;
+    }
+
+    let repo = AgreementRepoFac::file(&path).await.unwrap();
+    let agreements = repo.list().await;
+    assert_eq!(agreements.len(), 1);
+    assert_eq!((agreements[0].id, agreements[0].requests_answered), (1, 1));
+    assert!(agreements[0].in_range(2) && !agreements[0].in_range(3));
+    // Superseded records are dropped on load, and the journal is appended to afterwards
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+    repo.save(Agreement::new(3, details(1), 0)).await;
+    assert_eq!(AgreementRepoFac::file(&path).await.unwrap().list().await.len(), 2);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_agreements_expire() {
+    let agreement_repo = Arc::new(AgreementRepoFac::in_memory());
+    let accepted_at = now_millis() - 2000;
+    agreement_repo.save(Agreement::new(1, details(3), accepted_at)).await;
+    let mut completed = Agreement::new(2, details(1), accepted_at);
+    completed.record_outcome(true);
+    agreement_repo.save(completed.clone()).await;
+    // Finished long ago
+    agreement_repo.save(Agreement { id: 3, accepted_at: 0, ..completed }).await;
+    let processor = RequestProcessor::new(
+        Arc::new(StubChain::default()),
+        Arc::new(ReplicaPool::new()),
+        Arc::new(ResultCache::new(1 << 20)),
+        Arc::new(HistoryRepoFac::in_memory()),
+        agreement_repo.clone(),
+        Arc::new(LedgerRepoFac::in_memory()),
+        Arc::new(WorkQueueFac::in_memory()),
+    );
+    let settings = watch::channel(EngineConfig { response_window_secs: 1, ..Default::default() });
+    let (_chain_tx, chain_rx) = mpsc::channel(1);
+    let model_repo = Arc::new(ModelRepoFac::in_memory());
+    let retry_rx = mpsc::channel(1).1;
+    let mut engine = ExecutionEngine::new(chain_rx, retry_rx, processor, model_repo, settings.1);
+
+    // Agreements are swept while waiting for chain events, which never come. Finished agreements
+    // stay listed for a while before they are evicted
+    let _ = tokio::time::timeout(Duration::from_millis(100), engine.recv()).await;
+    let states: Vec<_> = agreement_repo.list().await.iter().map(|a| (a.id, a.state)).collect();
+    assert_eq!(states, [(1, AgreementState::Expired), (2, AgreementState::Completed)]);
+}
+
+#[tokio::test]
+async fn test_denied_consumer_recorded() {
+    let mut model = Model::new("hello".to_owned(), ModelDetails::default());
+    model.details.consumers.deny.insert(AccountId::from([1; 32]));
+    let model_repo = Arc::new(ModelRepoFac::in_memory());
+    model_repo.save(model.clone()).await;
+    let agreement_repo = Arc::new(AgreementRepoFac::in_memory());
+    agreement_repo
+        .save(Agreement::new(1, AgreementDetails { model_id: model.id, ..details(3) }, 0))
+        .await;
+    let history_repo = Arc::new(HistoryRepoFac::in_memory());
+    let processor = RequestProcessor::new(
+        Arc::new(StubChain::default()),
+        Arc::new(ReplicaPool::new()),
+        Arc::new(ResultCache::new(1 << 20)),
+        history_repo.clone(),
+        agreement_repo,
+        Arc::new(LedgerRepoFac::in_memory()),
+        Arc::new(WorkQueueFac::in_memory()),
+    );
+    let settings = watch::channel(EngineConfig::default()).1;
+    let (_chain_tx, chain_rx) = mpsc::channel(1);
+    let retry_rx = mpsc::channel(1).1;
+    let mut engine = ExecutionEngine::new(chain_rx, retry_rx, processor, model_repo, settings);
+
+    let request =
+        ChainEvent::RequestCreated { agreement_id: 1, request_index: 0, content_id: H256::zero() };
+    engine.process_chain_event(request).await.unwrap();
+    let record = history_repo.get(1, 0).await.unwrap();
+    assert_eq!(record.status, RequestStatus::Refused);
+    assert!(record.error.unwrap().contains("is not served"));
+}
@@ -0,0 +1,304 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use airo_wingman::{
+    backend::{self, template, Health, Status},
+    types::{
+        Backend, HttpJsonSettings, InputMode, KServeSettings, OpenAiSettings, SubprocessSettings,
+    },
+};
+use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+
+use crate::common::serve;
+
+mod common;
+
+/// Serve a stub completion API echoing the prompt, or failing on an empty one.
+async fn stub_completions() -> String {
+    let complete = |Json(body): Json<Value>| async move {
+        let prompt = body["prompt"].as_str().unwrap_or_default().to_owned();
+        if prompt.is_empty() {
+            return Json(json!({ "error": { "message": "empty prompt" } }));
+        }
+        Json(json!({ "id": "cmpl-1", "choices": [{ "text": prompt.to_uppercase() }] }))
+    };
+    let app = Router::new().route("/v1/complete", post(complete));
+    format!("{}/", serve(app).await)
+}
+
+#[test]
+fn test_render_template() {
+    let context = json!({ "input": { "prompt": "hi", "max_tokens": 5, "stop": ["\n"] } });
+    let template = json!({
+        "prompt": "{{input.prompt}}",
+        "max_tokens": "{{ input.max_tokens }}",
+        "stop": "{{input.stop}}",
+        "note": "say {{input.prompt}} in {{input.max_tokens}} tokens{{input.missing}}",
+        "missing": "{{input.missing}}",
+        "fixed": 1
+    });
+
+    assert_eq!(
+        template::render(&template, &context),
+        json!({
+            "prompt": "hi",
+            "max_tokens": 5,
+            "stop": ["\n"],
+            "note": "say hi in 5 tokens",
+            "missing": null,
+            "fixed": 1
+        })
+    );
+    assert_eq!(template::lookup(&context, "input.stop.0"), Some(&json!("\n")));
+}
+
+#[tokio::test]
+async fn test_http_json_backend() {
+    let settings = HttpJsonSettings {
+        predict_path: "v1/complete".to_owned(),
+        request: Some(json!({ "prompt": "{{input.text}}" })),
+        output: Some(json!({ "text": "{{response.choices.0.text}}" })),
+        error: Some("{{response.error.message}}".to_owned()),
+        id: Some("{{response.id}}".to_owned()),
+        ..Default::default()
+    };
+    let backend =
+        backend::connect(&stub_completions().await, &Backend::HttpJson(Box::new(settings)))
+            .unwrap();
+
+    let prediction = backend.predict("test", json!({ "text": "hello" })).await.unwrap();
+    assert_eq!(prediction.status, Status::Succeeded);
+    assert_eq!(prediction.id.as_deref(), Some("cmpl-1"));
+    assert_eq!(prediction.output, Some(json!({ "text": "HELLO" })));
+
+    let prediction = backend.predict("test", json!({ "text": "" })).await.unwrap();
+    assert_eq!(prediction.status, Status::Failed);
+    assert_eq!(prediction.error.as_deref(), Some("empty prompt"));
+    assert_eq!(prediction.output, None);
+}
+
+#[test]
+fn test_backend_discriminator() {
+    let details: airo_wingman::types::ModelDetails = serde_json::from_value(json!({
+        "price_per_request": 1,
+        "urls": ["http://localhost:8080"],
+        "backend": { "type": "http_json", "predict_path": "predict" }
+    }))
+    .unwrap();
+    let Backend::HttpJson(settings) = details.backend else {
+        panic!("expected http_json backend");
+    };
+    assert_eq!(settings.predict_path, "predict");
+}
+
+/// Serve a stub OpenAI-compatible API with a single model answering with the last message.
+async fn stub_openai() -> String {
+    let models = || async {
+        Json(json!({ "object": "list", "data": [{ "id": "llama", "object": "model" }] }))
+    };
+    let chat = |Json(body): Json<Value>| async move {
+        let messages = body["messages"].as_array().cloned().unwrap_or_default();
+        let last = messages.last().map(|message| message["content"].clone()).unwrap_or_default();
+        Json(json!({
+            "id": "chatcmpl-1",
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": last } }],
+            "usage": {
+                "prompt_tokens": messages.len(),
+                "completion_tokens": body["max_tokens"],
+                "total_tokens": 0
+            }
+        }))
+    };
+    let app = Router::new()
+        .route("/v1/models", axum::routing::get(models))
+        .route("/v1/chat/completions", post(chat));
+    format!("{}/", serve(app).await)
+}
+
+fn openai(model: &str) -> Backend {
+    Backend::OpenAi(OpenAiSettings {
+        model: model.to_owned(),
+        system_prompt: Some("Be brief.".to_owned()),
+        max_tokens: Some(16),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_openai_backend() {
+    let url = stub_openai().await;
+    let backend = backend::connect(&url, &openai("llama")).unwrap();
+    assert_eq!(backend.health().await.unwrap(), Health::Ready);
+
+    let prediction = backend
+        .predict("test", json!({ "prompt": "Hello", "max_tokens": 100 }))
+        .await
+        .unwrap();
+    assert_eq!(prediction.status, Status::Succeeded);
+    assert_eq!(prediction.output, Some(json!("Hello")));
+    // The system prompt is prepended and max_tokens capped
+    let usage = prediction.usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (2, 16));
+
+    // The schema describes the input, not the served model
+    let schema = backend.schema().await.unwrap().unwrap();
+    let schema = JSONSchema::compile(&schema).unwrap();
+    assert!(schema.is_valid(&json!("Hello")));
+    assert!(schema.is_valid(&json!({ "prompt": "Hello", "max_tokens": 100 })));
+    assert!(schema.is_valid(&json!({ "messages": [{ "role": "user", "content": "Hello" }] })));
+    assert!(!schema.is_valid(&json!({ "temperature": 0.5 })));
+    assert!(!schema.is_valid(&json!({ "prompt": "Hello", "temperature": "hot" })));
+
+    let missing = backend::connect(&url, &openai("mistral")).unwrap();
+    assert_eq!(missing.health().await.unwrap(), Health::SetupFailed);
+}
+
+/// Serve a stub Open Inference Protocol server with a model doubling its input. Once the flag is
+/// set, the model is reloaded with its input renamed to `z`.
+async fn stub_kserve() -> (String, Arc<AtomicBool>) {
+    let reloaded = Arc::new(AtomicBool::new(false));
+    let input_name = {
+        let reloaded = reloaded.clone();
+        move || if reloaded.load(Ordering::SeqCst) { "z" } else { "x" }
+    };
+    let ok = || async { Json(json!({})) };
+    let metadata = {
+        let input_name = input_name.clone();
+        move || async move {
+            Json(json!({
+                "name": "doubler",
+                "platform": "onnxruntime_onnx",
+                "inputs": [{ "name": input_name(), "datatype": "FP32", "shape": [-1, 3] }],
+                "outputs": [{ "name": "y", "datatype": "FP32", "shape": [-1, 3] }]
+            }))
+        }
+    };
+    let infer = move |Json(body): Json<Value>| async move {
+        let input = &body["inputs"][0];
+        if input["name"] != input_name() {
+            let error = json!({ "error": "unexpected input" });
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+        let data: Vec<f64> = input["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x.as_f64().unwrap() * 2.0)
+            .collect();
+        Json(json!({
+            "model_name": "doubler",
+            "id": "1",
+            "outputs": [{ "name": "y", "datatype": "FP32", "shape": input["shape"], "data": data }]
+        }))
+        .into_response()
+    };
+    let app = Router::new()
+        .route("/v2/health/ready", axum::routing::get(ok))
+        .route("/v2/models/doubler", axum::routing::get(metadata))
+        .route("/v2/models/doubler/ready", axum::routing::get(ok))
+        .route("/v2/models/doubler/infer", post(infer));
+    (format!("{}/", serve(app).await), reloaded)
+}
+
+#[tokio::test]
+async fn test_kserve_backend() {
+    let (url, reloaded) = stub_kserve().await;
+    let settings = KServeSettings { model: "doubler".to_owned(), version: None };
+    let backend = backend::connect(&url, &Backend::KServe(settings)).unwrap();
+    assert_eq!(backend.health().await.unwrap(), Health::Ready);
+
+    let schema = backend.schema().await.unwrap().unwrap();
+    assert_eq!(schema["required"], json!(["x"]));
+    assert_eq!(schema["properties"]["x"]["items"]["maxItems"], json!(3));
+
+    let prediction = backend
+        .predict("test", json!({ "x": [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]] }))
+        .await
+        .unwrap();
+    assert_eq!(prediction.status, Status::Succeeded);
+    assert_eq!(prediction.output, Some(json!({ "y": [[2.0, 4.0, 6.0], [8.0, 10.0, 12.0]] })));
+
+    let ragged = backend.predict("test", json!({ "x": [[1.0], [2.0, 3.0]] })).await;
+    assert!(ragged.is_err());
+    let misshaped = backend.predict("test", json!({ "x": [[1.0, 2.0]] })).await;
+    assert!(misshaped.is_err());
+    let extra = backend.predict("test", json!({ "x": [[1.0, 2.0, 3.0]], "w": [1.0] })).await;
+    assert!(extra.is_err());
+
+    // Flat data is reshaped, the unknown dimension inferred
+    let flat = json!({ "x": [1.0, 2.0, 3.0, 4.0, 5.0, 6.0] });
+    let prediction = backend.predict("test", flat).await.unwrap();
+    assert_eq!(prediction.output, Some(json!({ "y": [[2.0, 4.0, 6.0], [8.0, 10.0, 12.0]] })));
+    assert!(backend.predict("test", json!({ "x": [1.0, 2.0] })).await.is_err());
+
+    // The metadata is fetched again once a prediction fails
+    reloaded.store(true, Ordering::SeqCst);
+    let prediction = backend.predict("test", json!({ "x": [[1.0, 2.0, 3.0]] })).await.unwrap();
+    assert_eq!(prediction.status, Status::Failed);
+    let prediction = backend.predict("test", json!({ "z": [[1.0, 2.0, 3.0]] })).await.unwrap();
+    assert_eq!(prediction.status, Status::Succeeded);
+
+    let missing = KServeSettings { model: "missing".to_owned(), version: None };
+    let missing = backend::connect(&url, &Backend::KServe(missing)).unwrap();
+    assert_eq!(missing.health().await.unwrap(), Health::SetupFailed);
+}
+
+#[cfg(unix)]
+fn subprocess(command: &[&str]) -> SubprocessSettings {
+    SubprocessSettings {
+        command: command.iter().map(|arg| arg.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_subprocess_backend() {
+    let echo = backend::connect("exec://worker-1", &Backend::Subprocess(subprocess(&["cat"])));
+    let echo = echo.unwrap();
+    assert_eq!(echo.health().await.unwrap(), Health::Ready);
+    let prediction = echo.predict("test", json!({ "prompt": "hi" })).await.unwrap();
+    assert_eq!(prediction.status, Status::Succeeded);
+    assert_eq!(prediction.output, Some(json!({ "prompt": "hi" })));
+
+    let mut file = subprocess(&["sh", "-c", "cat \"$1\"; echo working >&2", "sh", "{input}"]);
+    file.input = InputMode::File;
+    let file = backend::connect("exec://worker-1", &Backend::Subprocess(file)).unwrap();
+    let prediction = file.predict("test", json!([1, 2])).await.unwrap();
+    assert_eq!(prediction.output, Some(json!([1, 2])));
+    assert_eq!(prediction.logs, "working\n");
+
+    // The sleep started by the command is killed along with it, and the logs are kept
+    let mut slow = subprocess(&["sh", "-c", "echo started >&2; sleep 30 & wait"]);
+    slow.timeout_secs = 1;
+    let slow = backend::connect("exec://worker-1", &Backend::Subprocess(slow)).unwrap();
+    let started = Instant::now();
+    let prediction = slow.predict("test", json!({})).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(prediction.status, Status::Failed);
+    assert_eq!(prediction.error.as_deref(), Some("Timed out after 1s"));
+    assert_eq!(prediction.logs, "started\n");
+
+    let mut verbose = subprocess(&["cat"]);
+    verbose.max_output_bytes = 4;
+    let verbose = backend::connect("exec://worker-1", &Backend::Subprocess(verbose)).unwrap();
+    let prediction = verbose.predict("test", json!({ "long": "output" })).await.unwrap();
+    assert_eq!(prediction.error.as_deref(), Some("Output exceeds 4 bytes"));
+
+    let failing = subprocess(&["sh", "-c", "echo broken >&2; exit 3"]);
+    let failing = backend::connect("exec://worker-1", &Backend::Subprocess(failing)).unwrap();
+    let prediction = failing.predict("test", json!({})).await.unwrap();
+    assert_eq!(prediction.error.as_deref(), Some("Command exited with code 3"));
+    assert_eq!(prediction.logs, "broken\n");
+
+    let missing = subprocess(&["no-such-model"]);
+    let missing = backend::connect("exec://worker-1", &Backend::Subprocess(missing)).unwrap();
+    assert_eq!(missing.health().await.unwrap(), Health::SetupFailed);
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use airo_wingman::{
+    backend::{Health, Status},
+    balancer::ReplicaPool,
+    types::{Backend, Balancing, Model, ModelDetails, SubprocessSettings},
+};
+use axum::{routing::get, Json, Router};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+/// Serve a stub Cog API which always reports the given health status.
+async fn stub_cog(status: &'static str) -> String {
+    let health = move || async move {
+        Json(json!({
+            "status": status,
+            "setup": { "started_at": "", "completed_at": "", "logs": "", "status": "succeeded" }
+        }))
+    };
+    let app = Router::new().route("/health-check", get(health));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+fn model(urls: Vec<String>, balancing: Balancing) -> Model {
+    Model::new(
+        "stub".to_owned(),
+        ModelDetails { price_per_request: 1, urls, balancing, ..Default::default() },
+    )
+}
+
+#[tokio::test]
+async fn test_round_robin() {
+    let urls = vec![stub_cog("READY").await, stub_cog("READY").await];
+    let model = model(urls.clone(), Balancing::RoundRobin);
+    let pool = ReplicaPool::new();
+
+    let first = pool.acquire(&model).await.unwrap().url().to_owned();
+    let second = pool.acquire(&model).await.unwrap().url().to_owned();
+    assert_ne!(first, second);
+    assert_eq!(pool.capacity(&model), 2);
+}
+
+#[tokio::test]
+async fn test_least_outstanding() {
+    let urls = vec![stub_cog("READY").await, stub_cog("READY").await];
+    let model = model(urls, Balancing::LeastOutstanding);
+    let pool = ReplicaPool::new();
+
+    let first = pool.acquire(&model).await.unwrap();
+    let second = pool.acquire(&model).await.unwrap();
+    assert_ne!(first.url(), second.url());
+    let released = first.url().to_owned();
+    drop(first);
+    assert_eq!(pool.acquire(&model).await.unwrap().url(), released);
+}
+
+#[tokio::test]
+async fn test_skip_busy_and_failed() {
+    let ready = stub_cog("READY").await;
+    let urls = vec![stub_cog("BUSY").await, "http://127.0.0.1:1/".to_owned(), ready.clone()];
+    let model = model(urls, Balancing::RoundRobin);
+    let pool = ReplicaPool::new();
+
+    for _ in 0..3 {
+        assert_eq!(pool.acquire(&model).await.unwrap().url(), ready);
+    }
+    assert_eq!(pool.capacity(&model), 2);
+}
+
+#[tokio::test]
+async fn test_setup_failed() {
+    let urls = vec![stub_cog("SETUP_FAILED").await];
+    let model = model(urls, Balancing::default());
+    let pool = ReplicaPool::new();
+
+    assert!(pool.acquire(&model).await.is_err());
+    assert_eq!(pool.capacity(&model), 0);
+}
+
+#[tokio::test]
+async fn test_acquire_timeout() {
+    let mut model = model(vec![stub_cog("BUSY").await], Balancing::default());
+    model.details.acquire_timeout_secs = Some(1);
+    let pool = ReplicaPool::new();
+
+    let acquired = tokio::time::timeout(Duration::from_secs(3), pool.acquire(&model)).await;
+    assert!(acquired.expect("acquire should give up on its own").is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_predict_cancelled() {
+    let settings = SubprocessSettings {
+        command: ["sh", "-c", "sleep 30 & wait"].iter().map(|arg| arg.to_string()).collect(),
+        ..Default::default()
+    };
+    let mut model = model(vec!["exec://worker-1".to_owned()], Balancing::default());
+    model.details.backend = Backend::Subprocess(settings);
+    let pool = ReplicaPool::new();
+    let token = CancellationToken::new();
+
+    // Cancelled on the backend once the timeout elapses, which frees the replica
+    let replica = pool.acquire(&model).await.unwrap();
+    let started = Instant::now();
+    let timeout = Some(Duration::from_secs(1));
+    let prediction = replica.predict_until("slow", json!({}), timeout, &token).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(prediction.status, Status::Failed);
+    assert_eq!(prediction.error.as_deref(), Some("Timed out after 1s"));
+    assert_eq!(replica.health().await.unwrap(), Health::Ready);
+
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        cancel.cancel();
+    });
+    let started = Instant::now();
+    assert!(replica.predict_until("shutdown", json!({}), None, &token).await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(replica.health().await.unwrap(), Health::Ready);
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use airo_wingman::{
+    backend::{Prediction, Status},
+    balancer::ReplicaPool,
+    batcher::{split, Batcher},
+    types::{Backend, BatchSettings, Model, ModelDetails, SubprocessSettings},
+};
+use serde_json::{json, Value};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+fn settings(max_size: usize, output_field: Option<&str>) -> BatchSettings {
+    BatchSettings {
+        max_size,
+        window_ms: 50,
+        input_field: "inputs".to_owned(),
+        output_field: output_field.map(str::to_owned),
+    }
+}
+
+fn prediction(status: Status, output: Option<Value>) -> Prediction {
+    Prediction {
+        id: Some("batch".to_owned()),
+        status,
+        output,
+        error: None,
+        started_at: None,
+        completed_at: None,
+        metrics: None,
+        usage: None,
+        logs: String::new(),
+    }
+}
+
+#[test]
+fn test_split() {
+    let outputs = json!({ "outputs": ["a", "b"] });
+    let predictions =
+        split(&settings(2, Some("outputs")), prediction(Status::Succeeded, Some(outputs)), 2);
+    let outputs: Vec<_> = predictions.iter().map(|p| p.output.clone()).collect();
+    assert_eq!(outputs, [Some(json!("a")), Some(json!("b"))]);
+    assert!(predictions.iter().all(|p| p.status == Status::Succeeded));
+
+    let predictions =
+        split(&settings(3, None), prediction(Status::Succeeded, Some(json!(["a", "b"]))), 3);
+    assert_eq!(predictions.len(), 3);
+    assert!(predictions.iter().all(|p| p.status == Status::Failed && p.output.is_none()));
+    assert_eq!(predictions[0].error.as_deref(), Some("Batch output has 2 items, expected 3"));
+
+    let mut failed = prediction(Status::Failed, None);
+    failed.error = Some("out of memory".to_owned());
+    let predictions = split(&settings(2, None), failed, 2);
+    assert_eq!(predictions[1].error.as_deref(), Some("out of memory"));
+}
+
+#[tokio::test]
+async fn test_batch_predictions() {
+    // The batch input is echoed, so the outputs are the inputs
+    let backend = SubprocessSettings { command: vec!["cat".to_owned()], ..Default::default() };
+    let details = ModelDetails {
+        urls: vec!["exec://worker-1".to_owned()],
+        backend: Backend::Subprocess(backend),
+        batching: Some(settings(3, Some("inputs"))),
+        ..Default::default()
+    };
+    let model = Model::new("echo".to_owned(), details);
+    let settings = model.details.batching.clone().unwrap();
+    let batcher = Arc::new(Batcher::new(
+        Arc::new(ReplicaPool::new()),
+        TaskTracker::new(),
+        CancellationToken::new(),
+    ));
+
+    let predict = |i: usize| batcher.predict(&model, &settings, json!({ "prompt": i }));
+    let predictions = tokio::join!(predict(0), predict(1), predict(2), predict(3));
+    let predictions =
+        [predictions.0, predictions.1, predictions.2, predictions.3].map(Result::unwrap);
+    for (i, prediction) in predictions.iter().enumerate() {
+        assert_eq!(prediction.output, Some(json!({ "prompt": i })));
+    }
+    // A full batch of 3, then the last request alone once the window closed
+    assert_eq!(predictions[0].id, predictions[2].id);
+    assert_ne!(predictions[0].id, predictions[3].id);
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use airo_wingman::protocol::{ChainEvent, EventBus};
+use primitive_types::H256;
+
+fn request(request_index: u32) -> ChainEvent {
+    ChainEvent::RequestCreated { agreement_id: 1, request_index, content_id: H256::zero() }
+}
+
+#[tokio::test]
+async fn test_slow_subscriber_loses_no_events() {
+    let mut bus = EventBus::new();
+    let mut fast = bus.subscribe("fast", 100);
+    let mut slow = bus.subscribe("slow", 2);
+    let publisher = tokio::spawn(async move {
+        for request_index in 0..10 {
+            bus.publish(request(request_index)).await.unwrap();
+        }
+    });
+
+    // The publisher waits for the slow subscriber once its queue is full
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!publisher.is_finished());
+    for expected in 0..10 {
+        let event = tokio::time::timeout(Duration::from_secs(1), slow.recv()).await.unwrap();
+        let Some(ChainEvent::RequestCreated { request_index, .. }) = event else {
+            panic!("unexpected event {event:?}");
+        };
+        assert_eq!(request_index, expected);
+    }
+    publisher.await.unwrap();
+
+    let mut received = 0;
+    while fast.try_recv().is_ok() {
+        received += 1;
+    }
+    assert_eq!(received, 10);
+}
+
+#[tokio::test]
+async fn test_closed_subscriber() {
+    let mut bus = EventBus::new();
+    drop(bus.subscribe("gone", 1));
+    assert!(bus.publish(request(0)).await.is_err());
+}
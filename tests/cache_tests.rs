@@ -0,0 +1,62 @@
+use airo_wingman::cache::ResultCache;
+use primitive_types::H256;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_cache_hit_and_expiry() {
+    let cache = ResultCache::new(1 << 20);
+    let key = (H256::repeat_byte(1), H256::repeat_byte(2), Some("v1".to_owned()));
+    cache.insert(key.clone(), H256::repeat_byte(3), Duration::from_millis(100));
+    assert_eq!(cache.get(&key), Some(H256::repeat_byte(3)));
+
+    let other_version = (key.0, key.1, Some("v2".to_owned()));
+    assert_eq!(cache.get(&other_version), None);
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(cache.get(&key), None);
+    assert!(cache.is_empty());
+}
+
+#[tokio::test]
+async fn test_cache_evicts_least_recently_used() {
+    let key = |byte| (H256::repeat_byte(byte), H256::zero(), None);
+    let ttl = Duration::from_secs(60);
+    let probe = ResultCache::new(1 << 20);
+    probe.insert(key(0), H256::zero(), ttl);
+    let cache = ResultCache::new(2 * probe.size());
+
+    cache.insert(key(1), H256::repeat_byte(1), ttl);
+    cache.insert(key(2), H256::repeat_byte(2), ttl);
+    assert_eq!(cache.get(&key(1)), Some(H256::repeat_byte(1)));
+    cache.insert(key(3), H256::repeat_byte(3), ttl);
+
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&key(2)), None);
+    assert_eq!(cache.get(&key(1)), Some(H256::repeat_byte(1)));
+    assert_eq!(cache.get(&key(3)), Some(H256::repeat_byte(3)));
+}
+
+#[tokio::test]
+async fn test_cache_budgets_bytes() {
+    let ttl = Duration::from_secs(60);
+    let short = (H256::repeat_byte(1), H256::zero(), None);
+    let probe = ResultCache::new(1 << 20);
+    probe.insert(short.clone(), H256::zero(), ttl);
+    let cache = ResultCache::new(3 * probe.size());
+
+    cache.insert(short.clone(), H256::repeat_byte(1), ttl);
+    assert_eq!(cache.size(), probe.size());
+
+    // A long version takes the room of several entries
+    let long = (H256::repeat_byte(2), H256::zero(), Some("v".repeat(2 * probe.size())));
+    cache.insert(long.clone(), H256::repeat_byte(2), ttl);
+    assert_eq!(cache.get(&short), None);
+    assert_eq!(cache.get(&long), Some(H256::repeat_byte(2)));
+    assert!(cache.size() <= 3 * probe.size());
+
+    // Results larger than the whole budget are not cached
+    let huge = (H256::repeat_byte(3), H256::zero(), Some("v".repeat(3 * probe.size())));
+    cache.insert(huge.clone(), H256::repeat_byte(3), ttl);
+    assert_eq!(cache.get(&huge), None);
+    assert_eq!(cache.get(&long), Some(H256::repeat_byte(2)));
+}
@@ -0,0 +1,43 @@
+use airo_wingman::cli::{Cli, Command, ModelsCommand};
+use clap::Parser;
+
+#[test]
+fn test_run_is_the_default_command() {
+    let cli = Cli::try_parse_from(["wingman"]).unwrap();
+    assert!(cli.command.is_none());
+}
+
+#[test]
+fn test_parse_models_add() {
+    let cli = Cli::try_parse_from([
+        "wingman",
+        "models",
+        "add",
+        "hello",
+        "--price",
+        "10",
+        "--url",
+        "http://localhost:5000",
+        "--url",
+        "http://localhost:5001",
+        "--api-url",
+        "http://wingman:8000",
+    ])
+    .unwrap();
+
+    let Some(Command::Models { api, command: ModelsCommand::Add { name, price, urls, .. } }) =
+        cli.command
+    else {
+        panic!("expected models add");
+    };
+    assert_eq!(api.api_url.as_str(), "http://wingman:8000/");
+    assert_eq!(name, "hello");
+    assert_eq!(price, 10);
+    assert_eq!(urls.len(), 2);
+}
+
+#[test]
+fn test_models_add_requires_url() {
+    let result = Cli::try_parse_from(["wingman", "models", "add", "hello", "--price", "10"]);
+    assert!(result.is_err());
+}
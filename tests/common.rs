@@ -1,10 +1,27 @@
+#![allow(dead_code)]
+
+use airo_wingman::{
+    protocol::{DataExchange, Error as ProtocolError, StateReader, TxSubmitter},
+    types::{
+        AgreementDetails, AgreementId, Balance, ContentId, OrderDetails, OrderId, Result, TxReceipt,
+    },
+};
+use async_trait::async_trait;
+use axum::{routing::post, Json, Router};
 use base64::{engine::general_purpose::STANDARD as Base64, Engine};
+use primitive_types::H256;
+use serde_json::{json, Value};
 use std::{
     ffi::OsStr,
     fs,
     path::{Path, PathBuf},
     process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
 };
+use tokio::net::TcpListener;
 
 pub fn cmd<I, S, P>(program: &str, args: I, dir: Option<P>) -> String
 where
@@ -43,7 +60,7 @@ fn docker_run(model: &str, host_port: u16) -> String {
 pub fn docker_port(container_id: &str) -> u16 {
     cmd("docker", ["port", container_id, "5000"], None::<&str>)
         .split(':')
-        .last()
+        .next_back()
         .expect("port mapping should exist")
         .trim()
         .parse()
@@ -65,3 +82,81 @@ pub fn encode_file(path: &str) -> String {
     let mime = tree_magic_mini::from_u8(&bytes);
     format!("data:{mime};base64,{}", Base64.encode(bytes))
 }
+
+/// Serve a stub on a free local port. Returns its base url, e.g. `http://127.0.0.1:4000`.
+pub async fn serve(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+/// Moderation stub rejecting content mentioning "spam". Returns the url of the moderation API.
+pub async fn moderation() -> String {
+    let moderate = |Json(body): Json<Value>| async move {
+        let spam = body["content"].to_string().contains("spam");
+        Json(json!({ "allowed": !spam, "reason": spam.then_some("spam") }))
+    };
+    let app = Router::new().route("/moderate", post(moderate));
+    format!("{}/moderate", serve(app).await)
+}
+
+/// Chain stub recording uploads and responses. Responses fail as existing if `responded`, and
+/// fail outright while `rejecting`. Their fee is unknown if `unknown_fee`. Downloads return
+/// `input`, if any.
+#[derive(Default)]
+pub struct StubChain {
+    pub input: Option<Vec<u8>>,
+    pub uploads: Mutex<Vec<ContentId>>,
+    pub responses: Mutex<Vec<(AgreementId, u32, ContentId)>>,
+    pub responded: bool,
+    pub rejecting: AtomicBool,
+    pub unknown_fee: bool,
+}
+
+#[async_trait]
+impl TxSubmitter for StubChain {
+    async fn bid_create(&self, _: OrderId, _: Balance) -> Result<TxReceipt> {
+        Err("Bids are not supported".into())
+    }
+
+    async fn response_create(
+        &self,
+        agreement_id: AgreementId,
+        request_index: u32,
+        content_id: ContentId,
+    ) -> Result<TxReceipt> {
+        if self.rejecting.load(Ordering::SeqCst) {
+            return Err("Transaction dropped".into());
+        }
+        if self.responded {
+            return Err(ProtocolError::ResponseAlreadyExists.into());
+        }
+        self.responses.lock().unwrap().push((agreement_id, request_index, content_id));
+        let fee = if self.unknown_fee { None } else { Some(1) };
+        Ok(TxReceipt { hash: H256::zero(), fee })
+    }
+}
+
+#[async_trait]
+impl StateReader for StubChain {
+    async fn get_agreement(&self, _: AgreementId) -> Result<Option<AgreementDetails>> {
+        Ok(None)
+    }
+
+    async fn get_order(&self, _: OrderId) -> Result<Option<OrderDetails>> {
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl DataExchange for StubChain {
+    async fn upload(&self, content_id: ContentId, _: Vec<u8>) -> Result<()> {
+        self.uploads.lock().unwrap().push(content_id);
+        Ok(())
+    }
+
+    async fn download(&self, _: ContentId) -> Result<Option<Vec<u8>>> {
+        Ok(self.input.clone())
+    }
+}
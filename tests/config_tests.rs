@@ -0,0 +1,155 @@
+use std::{fs, path::PathBuf};
+
+use airo_wingman::{config::Config, types::ModelDetails};
+
+fn write_config(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("wingman-{}-{name}", std::process::id()));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_config_from_toml() {
+    let path = write_config(
+        "config.toml",
+        r#"
+        [node]
+        url = "wss://node.example.com"
+
+        [signer]
+        type = "uri"
+        suri = "//Alice"
+
+        [http]
+        bind = "127.0.0.1:9000"
+
+        [engine]
+        concurrency = 8
+
+        [models.hello]
+        price_per_request = 10
+        urls = ["http://localhost:5000"]
+        "#,
+    );
+    let config = Config::from_file(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(config.node.url, "wss://node.example.com");
+    assert_eq!(config.http.bind.port(), 9000);
+    assert_eq!(config.engine.concurrency, 8);
+    assert!(config.bidding.enabled);
+    assert_eq!(config.models["hello"].price_per_request, 10);
+    assert!(config.validate().is_empty());
+
+    // The secret is never written out
+    let dumped = serde_json::to_value(&config).unwrap();
+    assert_eq!(dumped["signer"], serde_json::json!({ "type": "uri" }));
+}
+
+#[test]
+fn test_config_reports_all_problems() {
+    let path = write_config(
+        "config.yaml",
+        r#"
+node:
+  url: http://node.example.com
+engine:
+  concurrency: 0
+models:
+  hello:
+    price_per_request: 10
+    urls: ["not a url", "not a url"]
+"#,
+    );
+    let config = Config::from_file(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    let problems = config.validate();
+    assert_eq!(problems.len(), 6, "{problems:#?}");
+}
+
+#[test]
+fn test_config_rejects_unknown_fields() {
+    let path = write_config("unknown.toml", "[engine]\nthreads = 2\n");
+    let result = Config::from_file(&path);
+    fs::remove_file(path).unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_consumer_policy_from_toml() {
+    const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+    const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+    let path = write_config(
+        "consumers.toml",
+        &format!(
+            r#"
+            [signer]
+            type = "uri"
+            suri = "//Alice"
+
+            [models.hello]
+            price_per_request = 10
+            urls = ["http://localhost:5000"]
+
+            [models.hello.consumers]
+            allow = ["{ALICE}", "{BOB}"]
+            deny = ["{BOB}"]
+
+            [models.hello.consumers.prices]
+            "{ALICE}" = "340282366920938463463374607431768211455"
+            "#
+        ),
+    );
+    let config = Config::from_file(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    let consumers = &config.models["hello"].consumers;
+    let alice = ALICE.parse().unwrap();
+    assert!(consumers.allows(&alice));
+    assert!(!consumers.allows(&BOB.parse().unwrap()));
+    assert_eq!(consumers.price(&alice), Some(u128::MAX));
+    assert_eq!(
+        config.validate(),
+        [format!("models.hello.consumers: {BOB} is both allowed and denied")]
+    );
+}
+
+#[test]
+fn test_batch_fits_concurrency() {
+    let path = write_config(
+        "batching.toml",
+        r#"
+        [node]
+        url = "ws://localhost:9944"
+
+        [signer]
+        type = "uri"
+        suri = "//Alice"
+
+        [engine]
+        concurrency = 2
+
+        [models.hello]
+        price_per_request = 10
+        urls = ["http://localhost:5000"]
+        batching = { max_size = 3 }
+        "#,
+    );
+    let config = Config::from_file(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        config.validate(),
+        ["models.hello.batching.max_size: must be at most engine.concurrency (2)"]
+    );
+}
+
+#[test]
+fn test_model_single_url() {
+    let details: ModelDetails =
+        serde_json::from_str(r#"{ "price_per_request": 10, "url": "http://localhost:5000" }"#)
+            .unwrap();
+    assert_eq!(details.urls, ["http://localhost:5000"]);
+}
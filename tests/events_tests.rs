@@ -0,0 +1,100 @@
+use airo_wingman::{
+    balancer::ReplicaPool,
+    engine::{Engine, EventRelay},
+    events::{ChainEventName, EventHub, EventKind, ReplicaHealth, WingmanEvent},
+    http::HttpServer,
+    protocol::ChainEvent,
+    types::{AccountId, Model, ModelDetails},
+};
+use serde_json::json;
+use tokio::sync::{broadcast::Receiver, mpsc};
+use utoipa::OpenApi;
+
+/// Events published so far.
+fn drain(rx: &mut Receiver<WingmanEvent>) -> Vec<EventKind> {
+    std::iter::from_fn(|| rx.try_recv().ok()).map(|event| event.kind).collect()
+}
+
+#[test]
+fn test_event_json() {
+    let event = WingmanEvent {
+        timestamp: 42,
+        kind: EventKind::ChainEventReceived { name: ChainEventName::OrderCreated },
+    };
+    let expected =
+        json!({ "timestamp": 42, "type": "chain_event_received", "name": "order_created" });
+    assert_eq!(serde_json::to_value(event).unwrap(), expected);
+
+    let event = WingmanEvent {
+        timestamp: 42,
+        kind: EventKind::RequestResponded { agreement_id: 1, request_index: 2, cached: true },
+    };
+    let expected = json!({
+        "timestamp": 42,
+        "type": "request_responded",
+        "agreement_id": 1,
+        "request_index": 2,
+        "cached": true,
+    });
+    assert_eq!(serde_json::to_value(event).unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_relay_chain_events() {
+    let events = EventHub::new();
+    let mut rx = events.subscribe();
+    let mut relay = EventRelay::new(mpsc::channel(1).1, events);
+
+    let provider = AccountId::from([1; 32]);
+    let bid = |own| ChainEvent::BidCreated {
+        order_id: 7,
+        provider: provider.clone(),
+        price_per_request: 90,
+        own,
+    };
+    relay.process_chain_event(bid(false)).await.unwrap();
+    relay.process_chain_event(bid(true)).await.unwrap();
+    let accepted = ChainEvent::BidAccepted { order_id: 7, provider: provider.clone(), own: true };
+    relay.process_chain_event(accepted).await.unwrap();
+
+    let received = |name| EventKind::ChainEventReceived { name };
+    assert_eq!(
+        drain(&mut rx),
+        [
+            received(ChainEventName::BidCreated),
+            received(ChainEventName::BidCreated),
+            EventKind::BidPlaced { order_id: 7, price: 90 },
+            received(ChainEventName::BidAccepted),
+            EventKind::BidAccepted { order_id: 7 },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_health_transitions() {
+    let events = EventHub::new();
+    let mut rx = events.subscribe();
+    let pool = ReplicaPool::with_events(events);
+    let details =
+        ModelDetails { urls: vec!["http://127.0.0.1:1/".to_owned()], ..Default::default() };
+    let model = Model::new("down".to_owned(), details);
+
+    // Only the first check is a transition
+    pool.check(&model).await.unwrap();
+    pool.check(&model).await.unwrap();
+    let url = "http://127.0.0.1:1/".to_owned();
+    assert_eq!(
+        drain(&mut rx),
+        [EventKind::HealthChanged { url, health: ReplicaHealth::Unreachable }]
+    );
+}
+
+#[test]
+fn test_events_documented() {
+    let openapi = serde_json::to_value(HttpServer::openapi()).unwrap();
+    assert!(openapi["paths"]["/v1/events"]["get"].is_object());
+    let schemas = &openapi["components"]["schemas"];
+    assert!(schemas["WingmanEvent"].is_object());
+    assert!(schemas["EventKind"].is_object());
+    assert!(schemas["ReplicaHealth"]["enum"].is_array());
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use airo_wingman::{
+    data::{HistoryFilter, HistoryRepo, HistoryRepoFac, Retention},
+    types::{RequestRecord, RequestStatus},
+};
+use primitive_types::H256;
+
+fn record(agreement_id: u32, request_index: u32, received_at: u64) -> RequestRecord {
+    RequestRecord::new(agreement_id, request_index, H256::zero(), H256::zero(), received_at)
+}
+
+#[tokio::test]
+async fn test_query_history() {
+    let repo = HistoryRepoFac::in_memory();
+    for (agreement_id, request_index, received_at) in [(1, 0, 10), (1, 1, 20), (2, 0, 30)] {
+        repo.save(record(agreement_id, request_index, received_at)).await;
+    }
+    let mut failed = record(1, 1, 20);
+    failed.status = RequestStatus::Failed;
+    repo.save(failed).await;
+
+    let (total, page) = repo.query(&HistoryFilter::default(), 0, 2).await;
+    assert_eq!(total, 3);
+    assert_eq!(page.iter().map(|r| r.received_at).collect::<Vec<_>>(), [30, 20]);
+
+    let filter = HistoryFilter { agreement_id: Some(1), ..Default::default() };
+    let (total, page) = repo.query(&filter, 1, 10).await;
+    assert_eq!(total, 2);
+    assert_eq!(page[0].request_index, 0);
+
+    let filter = HistoryFilter { status: Some(RequestStatus::Failed), ..Default::default() };
+    assert_eq!(repo.query(&filter, 0, 10).await.0, 1);
+}
+
+#[tokio::test]
+async fn test_persist_history() {
+    let path = std::env::temp_dir().join(format!("aw-history-{}.jsonl", std::process::id()));
+    {
+        let repo = HistoryRepoFac::file(&path).await.unwrap();
+        repo.save(record(7, 3, 10)).await;
+        let mut responded = record(7, 3, 10);
+        responded.status = RequestStatus::Responded;
+        repo.save(responded).await;
+    }
+
+    let repo = HistoryRepoFac::file(&path).await.unwrap();
+    let record = repo.get(7, 3).await.unwrap();
+    assert_eq!(record.status, RequestStatus::Responded);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_history_retention() {
+    let path = std::env::temp_dir().join(format!("aw-retention-{}.jsonl", std::process::id()));
+    let repo = HistoryRepoFac::file(&path).await.unwrap();
+    for (request_index, received_at) in [(0, 1_000), (1, 5_000), (2, 8_000), (3, 9_000)] {
+        let mut record = record(1, request_index, received_at);
+        record.status = RequestStatus::Responded;
+        repo.save(record).await;
+    }
+    // Requests still being processed are kept, however old
+    repo.save(record(2, 0, 0)).await;
+
+    let retention = Retention { max_age: Duration::from_secs(7), max_records: 2 };
+    assert_eq!(repo.prune(&retention, 10_000).await, 2);
+    let (total, page) = repo.query(&HistoryFilter::default(), 0, 10).await;
+    assert_eq!(total, 3);
+    assert_eq!(page.iter().map(|r| r.received_at).collect::<Vec<_>>(), [9_000, 8_000, 0]);
+
+    let retention = Retention { max_age: Duration::from_secs(1), max_records: 10 };
+    assert_eq!(repo.prune(&retention, 10_000).await, 1);
+    drop(repo);
+
+    // Pruned records are gone from the journal as well
+    let repo = HistoryRepoFac::file(&path).await.unwrap();
+    assert!(repo.get(1, 2).await.is_none());
+    assert!(repo.get(1, 3).await.is_some() && repo.get(2, 0).await.is_some());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use crate::common::{serve, StubChain};
+use airo_wingman::{
+    balancer::ReplicaPool,
+    cache::ResultCache,
+    data::{
+        AgreementRepoFac, ConsumerRepo, ConsumerRepoFac, HistoryRepoFac, LedgerRepoFac,
+        MarketRepoFac, ModelRepoFac, WorkQueueFac,
+    },
+    engine::RequestProcessor,
+    events::EventHub,
+    http::HttpServer,
+    types::{AccountId, BatchSettings, ModelDetails},
+};
+
+mod common;
+
+/// Serve the API with in-memory repos. Returns the base url of the v1 API.
+async fn api() -> String {
+    api_with_consumers(Arc::new(ConsumerRepoFac::in_memory())).await
+}
+
+async fn api_with_consumers(consumer_repo: Arc<dyn ConsumerRepo + Send + Sync>) -> String {
+    let chain = Arc::new(StubChain::default());
+    let processor = RequestProcessor::new(
+        chain.clone(),
+        Arc::new(ReplicaPool::new()),
+        Arc::new(ResultCache::new(1 << 20)),
+        Arc::new(HistoryRepoFac::in_memory()),
+        Arc::new(AgreementRepoFac::in_memory()),
+        Arc::new(LedgerRepoFac::in_memory()),
+        Arc::new(WorkQueueFac::in_memory()),
+    );
+    let server = HttpServer::new(
+        ([127, 0, 0, 1], 0).into(),
+        processor,
+        Arc::new(ModelRepoFac::in_memory()),
+        chain,
+        Arc::new(MarketRepoFac::in_memory()),
+        EventHub::new(),
+    )
+    .with_consumers(consumer_repo);
+    format!("{}/v1", serve(server.router()).await)
+}
+
+#[tokio::test]
+async fn test_save_model_with_large_price() {
+    let api = api().await;
+    let http = reqwest::Client::new();
+    // Above u64::MAX, which JSON parsers read as a float unless asked for a 128-bit integer
+    let body = r#"{ "price_per_request": 340282366920938463463374607431768211455, "urls": ["http://localhost:5000"] }"#;
+    let response = http
+        .put(format!("{api}/models/large"))
+        .header("content-type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success(), "{}", response.text().await.unwrap());
+
+    // Read as text, as the price does not fit a JSON value
+    let models = http.get(format!("{api}/models")).send().await.unwrap().text().await.unwrap();
+    assert!(models.contains(&format!(r#""price_per_request":{}"#, u128::MAX)), "{models}");
+}
+
+#[tokio::test]
+async fn test_save_model_validated() {
+    let consumer_repo = Arc::new(ConsumerRepoFac::in_memory());
+    let api = api_with_consumers(consumer_repo.clone()).await;
+    let http = reqwest::Client::new();
+    let mut details =
+        ModelDetails { urls: vec!["http://localhost:5000".to_owned()], ..Default::default() };
+    details.consumers.deny.insert(AccountId::from([1; 32]));
+
+    let response = http.put(format!("{api}/models/hello")).json(&details).send().await.unwrap();
+    assert!(response.status().is_success(), "{}", response.text().await.unwrap());
+    // The policy is kept across reloads and restarts
+    assert_eq!(consumer_repo.get(&"hello".to_owned()).await, Some(details.consumers.clone()));
+
+    details.capacity = Some(0);
+    let response = http.put(format!("{api}/models/hello")).json(&details).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(response.text().await.unwrap(), "capacity: must be positive");
+
+    // Limits of the engine apply as well
+    details.capacity = None;
+    details.batching = Some(BatchSettings {
+        max_size: 5,
+        window_ms: 50,
+        input_field: "inputs".to_owned(),
+        output_field: None,
+    });
+    let response = http.put(format!("{api}/models/hello")).json(&details).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let problem = "batching.max_size: must be at most engine.concurrency (4)";
+    assert_eq!(response.text().await.unwrap(), problem);
+}
@@ -0,0 +1,59 @@
+use airo_wingman::{
+    data::ledger::{daily, per_model, to_csv, EarningsSummary},
+    types::{LedgerEntry, LedgerEntryKind},
+    utils::utc_date,
+};
+use primitive_types::H256;
+
+const DAY: u64 = 86_400_000;
+
+fn entry(timestamp: u64, kind: LedgerEntryKind, model: u8, amount: u128) -> LedgerEntry {
+    let model_id = H256::repeat_byte(model);
+    LedgerEntry { timestamp, kind, agreement_id: 1, model_id, amount, tx_hash: None }
+}
+
+#[test]
+fn test_utc_date() {
+    assert_eq!(utc_date(0), "1970-01-01");
+    assert_eq!(utc_date(951_782_400_000), "2000-02-29");
+    assert_eq!(utc_date(1_790_000_000_000), "2026-09-21");
+}
+
+#[test]
+fn test_earnings_summaries() {
+    let entries = [
+        entry(0, LedgerEntryKind::BidFee, 1, 3),
+        entry(10, LedgerEntryKind::Earning, 1, 100),
+        entry(20, LedgerEntryKind::ResponseFee, 1, 2),
+        entry(DAY, LedgerEntryKind::Earning, 2, 50),
+    ];
+
+    let days = daily(&entries);
+    assert_eq!(days.len(), 2);
+    assert_eq!((days[0].key.as_str(), days[0].requests, days[0].earned), ("1970-01-01", 1, 100));
+    assert_eq!((days[0].fees, days[0].net), (5, 95));
+    assert_eq!(days[1].key, "1970-01-02");
+
+    let models = per_model(&entries);
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[1].earned, 50);
+
+    let csv = to_csv(&entries);
+    assert_eq!(csv.lines().count(), 5);
+    assert!(csv.lines().nth(1).unwrap().starts_with("0,1970-01-01,bid_fee,1,0x0101"));
+}
+
+#[test]
+fn test_summaries_round_trip_large_amounts() {
+    let entries = [
+        entry(0, LedgerEntryKind::Earning, 1, u128::from(u64::MAX) * 4),
+        entry(10, LedgerEntryKind::ResponseFee, 1, 1),
+        entry(20, LedgerEntryKind::BidFee, 2, u128::from(u64::MAX) * 2),
+    ];
+    let summaries = per_model(&entries);
+    let json = serde_json::to_string(&summaries).unwrap();
+    let parsed: Vec<EarningsSummary> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, summaries);
+    assert_eq!(parsed[0].net, i128::from(u64::MAX) * 4 - 1);
+    assert_eq!(parsed[1].net, -i128::from(u64::MAX) * 2);
+}
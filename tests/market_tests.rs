@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use airo_wingman::{
+    config::BidStrategy,
+    data::{MarketRepo, MarketRepoFac},
+    engine::{bid_engine::bid_price, MarketWatcher},
+    protocol::MarketReader,
+    types::{AccountId, Balance, ModelId, OrderId, Result},
+};
+use async_trait::async_trait;
+use primitive_types::H256;
+use tokio::sync::mpsc;
+
+struct OpenMarket;
+
+#[async_trait]
+impl MarketReader for OpenMarket {
+    async fn list_orders(&self) -> Result<Vec<(OrderId, ModelId)>> {
+        Ok(vec![(1, H256::repeat_byte(1)), (2, H256::repeat_byte(2))])
+    }
+
+    async fn list_bids(&self) -> Result<Vec<(OrderId, AccountId, Balance)>> {
+        Ok(vec![
+            (1, AccountId::from([1; 32]), 100),
+            (1, AccountId::from([9; 32]), 50),
+            (2, AccountId::from([2; 32]), 70),
+        ])
+    }
+}
+
+#[tokio::test]
+async fn test_market_stats() {
+    let repo = MarketRepoFac::in_memory();
+    let model_id = H256::repeat_byte(1);
+    let (alice, bob) = (AccountId::from([1; 32]), AccountId::from([2; 32]));
+    repo.record_order(1, model_id).await;
+    repo.record_order(2, model_id).await;
+    repo.record_bid(1, alice.clone(), 100, 0).await;
+    repo.record_bid(1, bob.clone(), 80, 0).await;
+    repo.record_bid(2, alice.clone(), 90, 0).await;
+    // Bids on orders created before the wingman started are not attributed
+    repo.record_bid(3, alice.clone(), 1, 0).await;
+    repo.record_acceptance(1, &alice).await;
+    repo.record_acceptance(2, &alice).await;
+
+    let bids = repo.bids(&model_id).await;
+    assert_eq!(bids.len(), 3);
+    assert!(bids[0].won && !bids[1].won);
+
+    let stats = repo.stats(&model_id).await.unwrap();
+    assert_eq!((stats.bids, stats.won), (3, 2));
+    assert_eq!((stats.lowest, stats.highest, stats.average), (80, 100, 90));
+    assert_eq!((stats.lowest_winning, stats.last_winning), (Some(90), Some(90)));
+    assert_eq!(repo.list().await, vec![stats]);
+    assert!(repo.stats(&H256::zero()).await.is_none());
+}
+
+#[tokio::test]
+async fn test_bid_strategies() {
+    let repo = MarketRepoFac::in_memory();
+    let model_id = H256::repeat_byte(1);
+    repo.record_order(1, model_id).await;
+    repo.record_bid(1, AccountId::from([1; 32]), 90, 0).await;
+    let market = repo.stats(&model_id).await;
+    let market = market.as_ref();
+
+    assert_eq!(bid_price(&BidStrategy::Fixed, 100, market), 100);
+    let undercut = BidStrategy::Undercut { step: 5, floor_percent: 50 };
+    assert_eq!(bid_price(&undercut, 100, market), 85);
+    assert_eq!(bid_price(&undercut, 100, None), 100);
+    // Never above the model price, never below the floor
+    assert_eq!(bid_price(&undercut, 80, market), 80);
+    assert_eq!(bid_price(&undercut, 200, market), 100);
+    assert_eq!(bid_price(&BidStrategy::Match { floor_percent: 0 }, 100, market), 90);
+}
+
+#[tokio::test]
+async fn test_load_open_market() {
+    let repo = Arc::new(MarketRepoFac::in_memory());
+    let (_tx, rx) = mpsc::channel(1);
+    let watcher = MarketWatcher::new(rx, repo.clone());
+    watcher.load(&OpenMarket, &AccountId::from([9; 32])).await.unwrap();
+
+    // Own bids are left out, the bids on orders created before start are attributed
+    let stats = repo.stats(&H256::repeat_byte(1)).await.unwrap();
+    assert_eq!((stats.bids, stats.lowest), (1, 100));
+    let stats = repo.stats(&H256::repeat_byte(2)).await.unwrap();
+    assert_eq!((stats.bids, stats.lowest), (1, 70));
+}
+
+#[tokio::test]
+async fn test_market_stats_large_prices() {
+    let repo = MarketRepoFac::in_memory();
+    let model_id = H256::repeat_byte(1);
+    repo.record_order(1, model_id).await;
+    repo.record_bid(1, AccountId::from([1; 32]), Balance::MAX, 0).await;
+    repo.record_bid(1, AccountId::from([2; 32]), Balance::MAX - 1, 0).await;
+
+    let stats = repo.stats(&model_id).await.unwrap();
+    assert_eq!((stats.lowest, stats.highest), (Balance::MAX - 1, Balance::MAX));
+    assert!(stats.average >= stats.lowest && stats.average <= stats.highest);
+}
@@ -0,0 +1,81 @@
+use airo_wingman::{
+    policy::{validate_check, ContentFilter, Stage},
+    types::ContentCheck,
+};
+use serde_json::{json, Value};
+
+use crate::common::moderation;
+
+mod common;
+
+/// Code of the check rejecting the content, if any.
+async fn rejected_by(check: ContentCheck, content: Value) -> Option<String> {
+    let filter = ContentFilter::new();
+    let rejection = filter.check("hello", &[check], Stage::Input, &content).await.unwrap();
+    rejection.map(|rejection| rejection.code)
+}
+
+#[tokio::test]
+async fn test_content_checks() {
+    let schema =
+        || ContentCheck::Schema { schema: json!({ "type": "object", "required": ["prompt"] }) };
+    assert_eq!(rejected_by(schema(), json!({ "prompt": "hi" })).await, None);
+    assert_eq!(rejected_by(schema(), json!({ "text": "hi" })).await.unwrap(), "schema_violation");
+
+    let size = || ContentCheck::MaxSize { max_bytes: 16 };
+    assert_eq!(rejected_by(size(), json!({ "prompt": "hi" })).await, None);
+    let long = json!({ "prompt": "a long prompt" });
+    assert_eq!(rejected_by(size(), long).await.unwrap(), "size_exceeded");
+
+    let regex = || ContentCheck::Regex { patterns: vec![r"\d{4}-\d{4}".to_owned()] };
+    assert_eq!(rejected_by(regex(), json!({ "prompt": "call 1234" })).await, None);
+    let card = json!({ "messages": [{ "text": "card 1234-5678" }] });
+    assert_eq!(rejected_by(regex(), card).await.unwrap(), "pattern_matched");
+
+    let keywords = || ContentCheck::Keywords { keywords: vec!["Forbidden".to_owned()] };
+    assert_eq!(rejected_by(keywords(), json!(["allowed"])).await, None);
+    let shouting = json!(["FORBIDDEN words"]);
+    assert_eq!(rejected_by(keywords(), shouting).await.unwrap(), "keyword_matched");
+}
+
+#[tokio::test]
+async fn test_moderation() {
+    let url = moderation().await;
+    let check = || ContentCheck::Moderation { url: url.clone(), timeout_secs: 5 };
+    assert_eq!(rejected_by(check(), json!({ "prompt": "hi" })).await, None);
+    let spam = json!({ "prompt": "buy spam" });
+    assert_eq!(rejected_by(check(), spam).await.unwrap(), "moderation_rejected");
+
+    // An unavailable service is an error, not a rejection
+    let down = ContentCheck::Moderation { url: "http://127.0.0.1:1/".to_owned(), timeout_secs: 1 };
+    let filter = ContentFilter::new();
+    assert!(filter.check("hello", &[down], Stage::Output, &json!("hi")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_checks_recompiled_on_change() {
+    let filter = ContentFilter::new();
+    let regex = |pattern: &str| [ContentCheck::Regex { patterns: vec![pattern.to_owned()] }];
+    let content = json!("secret");
+    assert!(filter
+        .check("hello", &regex("sec"), Stage::Input, &content)
+        .await
+        .unwrap()
+        .is_some());
+    // Same model and stage, updated patterns
+    assert!(filter
+        .check("hello", &regex("pub"), Stage::Input, &content)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_validate_check() {
+    let invalid = ContentCheck::Regex { patterns: vec!["(".to_owned()] };
+    assert!(validate_check(&invalid).is_some());
+    let invalid = ContentCheck::Schema { schema: json!({ "type": 1 }) };
+    assert!(validate_check(&invalid).is_some());
+    assert!(validate_check(&ContentCheck::MaxSize { max_bytes: 0 }).is_some());
+    assert!(validate_check(&ContentCheck::Keywords { keywords: vec!["spam".to_owned()] }).is_none());
+}
@@ -0,0 +1,226 @@
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use airo_wingman::{
+    balancer::ReplicaPool,
+    cache::ResultCache,
+    config::EngineConfig,
+    data::{
+        AgreementRepoFac, HistoryRepo, HistoryRepoFac, LedgerRepo, LedgerRepoFac, ModelRepoFac,
+        WorkQueue, WorkQueueFac,
+    },
+    engine::{Engine, ExecutionEngine, RequestProcessor},
+    types::{ContentCheck, Model, ModelDetails, RequestStatus, WorkItem, WorkStage},
+};
+use primitive_types::H256;
+use serde_json::json;
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
+
+use crate::common::StubChain;
+
+mod common;
+
+fn processor(
+    chain: Arc<StubChain>,
+    history_repo: Arc<dyn HistoryRepo + Send + Sync>,
+    ledger_repo: Arc<dyn LedgerRepo + Send + Sync>,
+    work_queue: Arc<dyn WorkQueue + Send + Sync>,
+) -> RequestProcessor {
+    RequestProcessor::new(
+        chain,
+        Arc::new(ReplicaPool::new()),
+        Arc::new(ResultCache::new(1 << 20)),
+        history_repo,
+        Arc::new(AgreementRepoFac::in_memory()),
+        ledger_repo,
+        work_queue,
+    )
+}
+
+fn predicted(model: &Model, request_index: u32) -> WorkItem {
+    let mut item = WorkItem::new(1, request_index, model.id, H256::zero(), 0);
+    item.stage = WorkStage::Predicted;
+    item.result = Some(json!({ "status": "succeeded", "output": "hello" }));
+    item
+}
+
+#[tokio::test]
+async fn test_queue_persists() {
+    let path = std::env::temp_dir().join(format!("aw-queue-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let model = Model::new("hello".to_owned(), ModelDetails::default());
+    {
+        let queue = WorkQueueFac::file(&path).await.unwrap();
+        assert!(queue.push(WorkItem::new(1, 0, model.id, H256::zero(), 0)).await);
+        assert!(queue.push(WorkItem::new(1, 1, model.id, H256::zero(), 1)).await);
+        assert!(!queue.push(WorkItem::new(1, 1, model.id, H256::zero(), 2)).await);
+        queue.update(predicted(&model, 1)).await;
+        queue.finish((1, 0)).await;
+    }
+
+    let queue = WorkQueueFac::file(&path).await.unwrap();
+    let items = queue.list().await;
+    assert_eq!(items.len(), 1);
+    assert_eq!((items[0].request_index, items[0].stage), (1, WorkStage::Predicted));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_dead_letters_persist() {
+    let path = std::env::temp_dir().join(format!("aw-dead-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let model = Model::new("hello".to_owned(), ModelDetails::default());
+    {
+        let queue = WorkQueueFac::file(&path).await.unwrap();
+        let first = queue.bury(predicted(&model, 0), "first".to_owned(), 1).await;
+        let second = queue.bury(predicted(&model, 1), "second".to_owned(), 2).await;
+        assert_ne!(first, second);
+        assert!(queue.remove_dead_letter(first).await.is_some());
+        assert!(queue.remove_dead_letter(first).await.is_none());
+    }
+
+    let queue = WorkQueueFac::file(&path).await.unwrap();
+    let letters = queue.dead_letters().await;
+    assert_eq!(letters.len(), 1);
+    assert_eq!((letters[0].item.request_index, letters[0].reason.as_str()), (1, "second"));
+    // IDs are not reused after a restart
+    let third = queue.bury(predicted(&model, 2), "third".to_owned(), 3).await;
+    assert!(third > letters[0].id);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_failed_request_is_dead_letter() {
+    let chain = Arc::new(StubChain::default());
+    let work_queue = Arc::new(WorkQueueFac::in_memory());
+    let history_repo = Arc::new(HistoryRepoFac::in_memory());
+    let ledger_repo = Arc::new(LedgerRepoFac::in_memory());
+    let processor = processor(chain, history_repo, ledger_repo, work_queue.clone());
+    let model = Model::new("hello".to_owned(), ModelDetails::default());
+    let item = WorkItem::new(1, 0, model.id, H256::zero(), 0);
+    work_queue.push(item.clone()).await;
+
+    // The input can't be downloaded
+    assert!(processor.resume(&model, item).await.is_err());
+    assert!(work_queue.list().await.is_empty());
+    let letters = processor.dead_letters().await;
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].item.stage, WorkStage::Received);
+    assert!(letters[0].reason.contains("not found"));
+
+    assert!(processor.discard(letters[0].id).await);
+    assert!(!processor.discard(letters[0].id).await);
+    assert!(processor.dead_letters().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_retry_dead_letter() {
+    let chain = Arc::new(StubChain::default());
+    chain.rejecting.store(true, Ordering::SeqCst);
+    let history_repo = Arc::new(HistoryRepoFac::in_memory());
+    let ledger_repo = Arc::new(LedgerRepoFac::in_memory());
+    let work_queue = Arc::new(WorkQueueFac::in_memory());
+    let (retry_tx, retry_rx) = mpsc::channel(1);
+    let processor = processor(chain.clone(), history_repo.clone(), ledger_repo, work_queue.clone())
+        .with_retries(retry_tx);
+    let (_chain_tx, chain_rx) = mpsc::channel(1);
+    let model_repo = Arc::new(ModelRepoFac::in_memory());
+    let settings = watch::channel(EngineConfig::default()).1;
+    let mut engine =
+        ExecutionEngine::new(chain_rx, retry_rx, processor.clone(), model_repo, settings);
+    let token = CancellationToken::new();
+    tokio::spawn({
+        let token = token.clone();
+        async move { engine.run(token).await }
+    });
+    let model = Model::new("hello".to_owned(), ModelDetails::default());
+    let item = predicted(&model, 0);
+    work_queue.push(item.clone()).await;
+
+    assert!(processor.resume(&model, item).await.is_err());
+    let letters = processor.dead_letters().await;
+    assert_eq!(letters[0].item.stage, WorkStage::Uploaded);
+
+    // The retry is processed by the engine, and responds without uploading the result again
+    chain.rejecting.store(false, Ordering::SeqCst);
+    assert!(processor.retry(letters[0].id, model.clone()).await);
+    assert!(!processor.retry(letters[0].id, model).await);
+    let responded = async {
+        while history_repo.get(1, 0).await.unwrap().status != RequestStatus::Responded {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), responded).await.unwrap();
+    token.cancel();
+    assert_eq!(chain.uploads.lock().unwrap().len(), 1);
+    assert_eq!(chain.responses.lock().unwrap().len(), 1);
+    assert!(processor.dead_letters().await.is_empty());
+    assert!(work_queue.list().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_resume_from_last_step() {
+    for responded in [false, true] {
+        let chain = Arc::new(StubChain { responded, ..Default::default() });
+        let history_repo = Arc::new(HistoryRepoFac::in_memory());
+        let ledger_repo = Arc::new(LedgerRepoFac::in_memory());
+        let work_queue = Arc::new(WorkQueueFac::in_memory());
+        let processor =
+            processor(chain.clone(), history_repo.clone(), ledger_repo.clone(), work_queue.clone());
+        let model = Model::new("hello".to_owned(), ModelDetails::default());
+        let item = predicted(&model, 0);
+        work_queue.push(item.clone()).await;
+
+        // The prediction is not repeated, the result is uploaded and the request responded
+        processor.resume(&model, item).await.unwrap();
+        assert_eq!(chain.uploads.lock().unwrap().len(), 1);
+        assert_eq!(chain.responses.lock().unwrap().len(), if responded { 0 } else { 1 });
+        let record = history_repo.get(1, 0).await.unwrap();
+        assert_eq!(record.status, RequestStatus::Responded);
+        assert_eq!(ledger_repo.entries().await.len(), if responded { 0 } else { 1 });
+        assert!(work_queue.list().await.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_fee_not_recorded() {
+    let chain = Arc::new(StubChain { unknown_fee: true, ..Default::default() });
+    let history_repo = Arc::new(HistoryRepoFac::in_memory());
+    let ledger_repo = Arc::new(LedgerRepoFac::in_memory());
+    let work_queue = Arc::new(WorkQueueFac::in_memory());
+    let processor = processor(chain.clone(), history_repo, ledger_repo.clone(), work_queue.clone());
+    let model = Model::new("hello".to_owned(), ModelDetails::default());
+    let item = predicted(&model, 0);
+    work_queue.push(item.clone()).await;
+
+    // Responded, but no fee is made up for the ledger
+    processor.resume(&model, item).await.unwrap();
+    assert_eq!(chain.responses.lock().unwrap().len(), 1);
+    assert!(ledger_repo.entries().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_rejected_input_is_answered() {
+    let input = serde_json::to_vec(&json!({ "prompt": "something forbidden" })).unwrap();
+    let chain = Arc::new(StubChain { input: Some(input), ..Default::default() });
+    let history_repo = Arc::new(HistoryRepoFac::in_memory());
+    let work_queue = Arc::new(WorkQueueFac::in_memory());
+    let ledger_repo = Arc::new(LedgerRepoFac::in_memory());
+    let processor = processor(chain.clone(), history_repo.clone(), ledger_repo, work_queue.clone());
+    // The model has no replicas, so it must not be predicted with
+    let mut model = Model::new("hello".to_owned(), ModelDetails::default());
+    let check = ContentCheck::Keywords { keywords: vec!["forbidden".to_owned()] };
+    model.details.content.input.push(check);
+    let item = WorkItem::new(1, 0, model.id, H256::zero(), 0);
+    work_queue.push(item.clone()).await;
+
+    processor.resume(&model, item).await.unwrap();
+    assert_eq!(chain.uploads.lock().unwrap().len(), 1);
+    assert_eq!(chain.responses.lock().unwrap().len(), 1);
+    let record = history_repo.get(1, 0).await.unwrap();
+    assert_eq!(record.status, RequestStatus::Responded);
+    assert!(record.error.unwrap().starts_with("keyword_matched"));
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use airo_wingman::{
+    config::Config,
+    data::{ConsumerRepo, ConsumerRepoFac, ModelRepo, ModelRepoFac},
+    reload::ConfigReloader,
+    types::{AccountId, ConsumerPolicy, Model, ModelDetails},
+};
+
+fn details(price_per_request: u128) -> ModelDetails {
+    ModelDetails {
+        price_per_request,
+        urls: vec!["http://localhost:5000".to_owned()],
+        ..Default::default()
+    }
+}
+
+async fn price_of(repo: &dyn ModelRepo, name: &str) -> Option<u128> {
+    let id = Model::new(name.to_owned(), ModelDetails::default()).id;
+    repo.get_by_model_id(&id).await.map(|model| model.details.price_per_request)
+}
+
+#[tokio::test]
+async fn test_reload_applies_models_and_settings() {
+    let model_repo = Arc::new(ModelRepoFac::in_memory());
+    let mut reloader = ConfigReloader::new(None, model_repo.clone());
+    let mut bidding = reloader.bidding();
+    let mut engine = reloader.engine();
+
+    let mut config = Config::default();
+    config.models.insert("hello".to_owned(), details(1));
+    config.models.insert("world".to_owned(), details(2));
+    reloader.apply(config.clone()).await;
+    // Models added through the API are not managed by the configuration
+    model_repo.save(Model::new("api".to_owned(), details(3))).await;
+    bidding.borrow_and_update();
+    engine.borrow_and_update();
+
+    config.models.remove("world");
+    config.models.insert("hello".to_owned(), details(10));
+    config.bidding.enabled = false;
+    reloader.apply(config).await;
+
+    assert_eq!(price_of(model_repo.as_ref(), "hello").await, Some(10));
+    assert_eq!(price_of(model_repo.as_ref(), "world").await, None);
+    assert_eq!(price_of(model_repo.as_ref(), "api").await, Some(3));
+    assert!(bidding.has_changed().unwrap());
+    assert!(!bidding.borrow().enabled);
+    assert!(!engine.has_changed().unwrap());
+}
+
+#[tokio::test]
+async fn test_reload_keeps_api_consumers() {
+    let path = std::env::temp_dir().join(format!("aw-consumers-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut policy = ConsumerPolicy::default();
+    policy.deny.insert(AccountId::from([1; 32]));
+    ConsumerRepoFac::file(&path)
+        .await
+        .unwrap()
+        .save("hello".to_owned(), policy.clone())
+        .await;
+
+    // Policies set through the API survive a restart, and are kept over the configuration
+    let consumer_repo = Arc::new(ConsumerRepoFac::file(&path).await.unwrap());
+    let model_repo = Arc::new(ModelRepoFac::in_memory());
+    let mut reloader = ConfigReloader::new(None, model_repo.clone()).with_consumers(consumer_repo);
+    let mut config = Config::default();
+    config.models.insert("hello".to_owned(), details(1));
+    config.models.insert("world".to_owned(), details(2));
+    reloader.apply(config.clone()).await;
+    config.models.insert("hello".to_owned(), details(10));
+    reloader.apply(config).await;
+
+    let models = model_repo.list().await;
+    let consumers = |name: &str| &models.iter().find(|m| m.name == name).unwrap().details.consumers;
+    assert_eq!(consumers("hello"), &policy);
+    assert!(consumers("world").is_empty());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,87 @@
+use airo_wingman::{
+    config::{SchedulingConfig, SchedulingPolicy},
+    engine::{Scheduled, Scheduler},
+    types::{AgreementId, Balance, Model, ModelDetails, WorkItem},
+};
+use primitive_types::H256;
+
+const HOUR: u64 = 60 * 60 * 1000;
+const NOW: u64 = 100 * HOUR;
+
+fn scheduled(agreement_id: AgreementId, request_index: u32, price: Balance) -> Scheduled {
+    let model = Model::new("hello".to_owned(), ModelDetails::default());
+    let item = WorkItem::new(agreement_id, request_index, model.id, H256::zero(), NOW);
+    Scheduled { model, item, price, deadline: NOW + 24 * HOUR }
+}
+
+fn config(policy: SchedulingPolicy) -> SchedulingConfig {
+    SchedulingConfig { policy, ..Default::default() }
+}
+
+/// Agreement IDs of the requests in the order they are popped.
+fn drain(scheduler: &Scheduler, config: &SchedulingConfig) -> Vec<AgreementId> {
+    std::iter::from_fn(|| scheduler.pop(config, NOW))
+        .map(|s| s.item.agreement_id)
+        .collect()
+}
+
+#[test]
+fn test_strict_priority() {
+    let scheduler = Scheduler::new();
+    scheduler.push(scheduled(1, 0, 10));
+    scheduler.push(scheduled(2, 0, 30));
+    scheduler.push(scheduled(3, 0, 20));
+    scheduler.push(scheduled(2, 1, 30));
+    assert_eq!(drain(&scheduler, &config(SchedulingPolicy::StrictPriority)), [2, 2, 3, 1]);
+
+    scheduler.push(scheduled(1, 1, 10));
+    scheduler.push(scheduled(2, 2, 30));
+    assert_eq!(drain(&scheduler, &config(SchedulingPolicy::Fifo)), [1, 2]);
+}
+
+#[test]
+fn test_deadlines_and_starvation() {
+    let scheduler = Scheduler::new();
+    scheduler.push(scheduled(1, 0, 100));
+    // Cheap, but waiting for long
+    let mut waiting = scheduled(2, 0, 1);
+    waiting.item.received_at = NOW - HOUR;
+    scheduler.push(waiting);
+    // Cheap, but about to expire
+    let mut urgent = scheduled(3, 0, 1);
+    urgent.deadline = NOW + 60_000;
+    scheduler.push(urgent);
+    assert_eq!(drain(&scheduler, &config(SchedulingPolicy::StrictPriority)), [3, 2, 1]);
+    assert!(scheduler.is_empty());
+}
+
+#[test]
+fn test_weighted_fair() {
+    let scheduler = Scheduler::new();
+    for request_index in 0..6 {
+        scheduler.push(scheduled(1, request_index, 20));
+        scheduler.push(scheduled(2, request_index, 10));
+    }
+    // The agreement paying twice as much is served twice as often, without starving the other
+    let order = drain(&scheduler, &config(SchedulingPolicy::WeightedFair));
+    assert_eq!(order[..6].iter().filter(|&&id| id == 1).count(), 4);
+    assert_eq!(order.len(), 12);
+}
+
+#[test]
+fn test_weighted_fair_large_prices() {
+    let scheduler = Scheduler::new();
+    let config = config(SchedulingPolicy::WeightedFair);
+    // Served requests of a cheap agreement move the virtual time forward
+    scheduler.push(scheduled(9, 0, 1));
+    scheduler.push(scheduled(9, 1, 1));
+    assert_eq!(drain(&scheduler, &config), [9, 9]);
+
+    // Prices of whole tokens in plancks still weigh in, far past the virtual time
+    for request_index in 0..6 {
+        scheduler.push(scheduled(1, request_index, 2 * 10u128.pow(20)));
+        scheduler.push(scheduled(2, request_index, 10u128.pow(20)));
+    }
+    let order = drain(&scheduler, &config);
+    assert_eq!(order[..6].iter().filter(|&&id| id == 1).count(), 4);
+}
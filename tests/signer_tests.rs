@@ -0,0 +1,106 @@
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use airo_wingman::signer::{Error, ProviderKey};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use crypto_secretbox::{aead::Aead, KeyInit, XSalsa20Poly1305};
+use subxt::utils::AccountId32;
+
+const SURI: &str = "//Alice";
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("wingman-signer-{}-{name}", std::process::id()))
+}
+
+fn write_secret(path: &Path, content: &str, mode: u32) {
+    fs::write(path, content).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+fn account_id(key: &ProviderKey) -> AccountId32 {
+    key.public_key().into()
+}
+
+#[test]
+fn test_key_file_requires_owner_only_permissions() {
+    let path = temp_path("suri");
+    write_secret(&path, SURI, 0o644);
+    let insecure = airo_wingman::signer::read_secret(&path);
+    write_secret(&path, &format!("{SURI}\n"), 0o600);
+    let secret = airo_wingman::signer::read_secret(&path);
+    fs::remove_file(path).unwrap();
+
+    assert!(matches!(insecure, Err(Error::InsecurePermissions { mode: 0o644, .. })));
+    assert_eq!(secret.unwrap().as_str(), SURI);
+}
+
+#[test]
+fn test_key_from_keystore() {
+    let alice = ProviderKey::from_uri(SURI).unwrap();
+    let public_key = hex::encode(alice.public_key().0);
+    let dir = temp_path("keystore");
+    fs::create_dir_all(&dir).unwrap();
+    write_secret(&dir.join(format!("{}{public_key}", hex::encode("acco"))), "\"//Alice\"", 0o600);
+    write_secret(&dir.join(format!("{}{public_key}", hex::encode("babe"))), "\"//Bob\"", 0o600);
+
+    let key = ProviderKey::from_keystore(&dir, None);
+    let picked = ProviderKey::from_keystore(&dir, Some(&format!("0x{public_key}")));
+    let missing = ProviderKey::from_keystore(&dir, Some("00"));
+    fs::remove_dir_all(dir).unwrap();
+
+    assert_eq!(account_id(&key.unwrap()), account_id(&alice));
+    assert_eq!(account_id(&picked.unwrap()), account_id(&alice));
+    assert!(matches!(missing, Err(Error::KeyNotFound(_))));
+}
+
+/// Encrypt a keypair the way polkadot.js exports it.
+fn polkadot_js_keyfile(keypair: &schnorrkel::Keypair, passphrase: &str) -> String {
+    let salt = [7u8; 32];
+    let (log_n, r, p) = (15u8, 8u32, 1u32);
+    let mut key = [0u8; 32];
+    let params = scrypt::Params::new(log_n, r, p, 32).unwrap();
+    scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut key).unwrap();
+
+    let mut plaintext = vec![48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+    plaintext.extend(keypair.secret.to_ed25519_bytes());
+    plaintext.extend([161, 35, 3, 33, 0]);
+    plaintext.extend(keypair.public.to_bytes());
+    let nonce = [9u8; 24];
+    let ciphertext = XSalsa20Poly1305::new(&key.into())
+        .encrypt(&nonce.into(), plaintext.as_slice())
+        .unwrap();
+
+    let mut encoded = salt.to_vec();
+    for param in [1u32 << log_n, p, r] {
+        encoded.extend(param.to_le_bytes());
+    }
+    encoded.extend(nonce);
+    encoded.extend(ciphertext);
+    let address = AccountId32(keypair.public.to_bytes());
+    serde_json::json!({
+        "encoded": STANDARD.encode(encoded),
+        "encoding": {
+            "content": ["pkcs8", "sr25519"],
+            "type": ["scrypt", "xsalsa20-poly1305"],
+            "version": "3"
+        },
+        "address": address.to_string(),
+        "meta": { "name": "provider" }
+    })
+    .to_string()
+}
+
+#[test]
+fn test_key_from_polkadot_js_keyfile() {
+    let keypair = schnorrkel::MiniSecretKey::from_bytes(&[1; 32])
+        .unwrap()
+        .expand_to_keypair(schnorrkel::ExpansionMode::Ed25519);
+    let json = polkadot_js_keyfile(&keypair, "correct horse");
+
+    let key = ProviderKey::from_json(&json, "correct horse").unwrap();
+    assert_eq!(account_id(&key), AccountId32(keypair.public.to_bytes()));
+    assert!(matches!(ProviderKey::from_json(&json, "wrong"), Err(Error::Decrypt)));
+}